@@ -12,6 +12,125 @@
 -- - `feedgen.parseSelector`: parses a string as a CSS selector (more below).
 -- - `feedgen.parseHtml`: parses a source buffer as an HTML document (more
 --   below).
+-- - `feedgen.parseHtmlFragment`: parses a snippet of HTML (e.g., one embedded
+--   in a JSON API response) as a fragment, without adding the <head> and
+--   <body> a document would get. Returns the same kind of handle as
+--   `parseHtml`; `:root()` is an <html> element containing the snippet's
+--   top-level nodes.
+-- - `feedgen.parseXml`: parses a source buffer (or a string) as an XML
+--   document. Unlike `parseHtml`, it's strict and raises an error if the
+--   document is not well-formed. The document is returned as a tree of plain
+--   tables, each with a `type` field: `document` (with the fields `root`, the
+--   root element, and `children`), `element` (with `name` as written in the
+--   source, `localName`, `namespace` (the namespace URI, if any), `prefix`,
+--   `attrs`, and `children`), `text` and `comment` (with `text`), and
+--   `processingInstruction` (with `target` and `value`). `attrs` is keyed by
+--   the attribute names as written in the source; namespaced attributes are
+--   also available under `{namespace-uri}local-name`. Element and document
+--   nodes have the following methods:
+--   - `node:child(localName, namespace)` returns the first child element with
+--     the given local name (and namespace URI, if provided).
+--   - `node:childrenNamed(localName, namespace)` returns a list of all such
+--     child elements.
+--   - `node:text()` returns the concatenated text of all descendant nodes.
+--   - `node:attr(localName, namespace)` returns the value of an attribute.
+-- - `feedgen.fetch(url, options)`: performs an HTTP request, e.g., to retrieve
+--   a detail page linked from the source page. Only available inside
+--   `extract` and `prepare`. `url` is resolved against the source page URL. `options` is an
+--   optional table with the fields `method` (`"GET"` or `"POST"`, defaults to
+--   `"GET"`), `headers` (a table of header values keyed by their names), and
+--   `body` (a string). Returns a table with the fields `status` (the HTTP
+--   status code), `ok` (whether the status is 2xx), `url` (the final URL after
+--   redirects), `headers` (keyed by lowercase names), and `body` (a buffer that
+--   can be passed to `feedgen.parseHtml`). Requests are spaced out by the
+--   extractor's `request-delay` and time out after its `request-timeout`; a
+--   failed request raises an error.
+-- - `feedgen.readability(buf)`: finds the main content of an article page (a
+--   buffer or a string of HTML) by a readability-style heuristic, with
+--   navigation, comments, and the like removed. Returns the content's HTML, or
+--   `nil` if the page has too little text to tell. Useful as an entry's
+--   `content` for a page retrieved with `feedgen.fetch`.
+-- - `feedgen.metadata(buf)`: returns a list of the articles described by the
+--   JSON-LD, microdata, or OpenGraph metadata of a page (a buffer or a string
+--   of HTML). Each is a table with the fields `id` and `url` (as written in the
+--   page, possibly relative or `nil`), `title`, `description` (plain text),
+--   `author`, `pubDate`, `image`, and `categories`; any but `categories` may
+--   be `nil`. The OpenGraph tags are only used if the other metadata describes
+--   no articles.
+-- - `feedgen.loginRequired()`: raises an error telling Feedgen that the source
+--   page is a login page (e.g., because the session has expired). Feedgen then
+--   runs `prepare` (see below) again and retries the page once.
+--
+-- - `feedgen.date`: a table of date utilities:
+--   - `feedgen.date.parse(s, format, tz)` parses a datetime and returns a table
+--     that can be used as an entry's `pubDate` (see below). `format` is either
+--     a format description (see
+--     https://time-rs.github.io/book/api/format-description.html), `"rfc2822"`,
+--     or `"rfc3339"`. If the string doesn't include a UTC offset, the datetime
+--     is assumed to be in the timezone `tz` (e.g., `"Europe/Berlin"`), which
+--     defaults to the feed's `timezone`. Formats without time components are
+--     allowed and result in midnight.
+--   - `feedgen.date.parseRelative(s, locales)` converts a relative timestamp
+--     (e.g., `"5 min ago"`, `"yesterday"`, or `"vor 2 Tagen"`) to a `pubDate`
+--     table, counting from the time the source page was fetched. Returns `nil`
+--     if the string is not recognized. `locales` is a list of languages to try
+--     (`"en"` and `"de"` are supported) and defaults to `{"en"}`.
+--
+-- - `feedgen.html`: a table of HTML text utilities:
+--   - `feedgen.html.escape(s)` escapes `&`, `<`, `>`, quotes, and `/` so that
+--     the text can be safely embedded into description HTML.
+--   - `feedgen.html.unescape(s)` replaces character references (`&amp;`,
+--     `&#39;`, etc.) with the characters they represent. Call it twice for
+--     double-encoded text (`&amp;amp;`).
+--
+-- - `feedgen.base64` and `feedgen.hex`: binary-to-text encodings. Strings
+--   may contain arbitrary bytes.
+--   - `feedgen.base64.encode(s, urlSafe)` encodes a string as base64 (with the
+--     URL-safe alphabet if `urlSafe` is `true`).
+--   - `feedgen.base64.decode(s, urlSafe)` decodes base64. Padding is optional,
+--     and whitespace is ignored. Raises an error if the input is malformed.
+--   - `feedgen.hex.encode(s)` encodes a string as lowercase hex digits.
+--   - `feedgen.hex.decode(s)` decodes hex digits (in either case). Raises an
+--     error if the input is malformed.
+--
+-- - `feedgen.digest`: hash functions for deriving stable entry ids (e.g., from
+--   the URL or the contents of an entry) when a site doesn't provide any:
+--   `feedgen.digest.sha256(s)`, `feedgen.digest.sha1(s)`, and
+--   `feedgen.digest.md5(s)` return the digest of a string as lowercase hex.
+--
+-- - `feedgen.url`: a table of URL utilities:
+--   - `feedgen.url.parse(s)` parses an absolute URL, returning a `Url`.
+--   - `feedgen.url.join(base, url)` resolves a relative URL against `base` (a
+--     string or a `Url`), returning a `Url`.
+--   - `feedgen.url.encodeQuery(params)` encodes a table as a query string.
+--     `params` is either a map of names to values (encoded in the order of the
+--     names) or a list of `{name, value}` pairs.
+--   - `feedgen.url.decodeQuery(query)` decodes a query string into a table
+--     mapping names to values.
+--
+--   A `Url` has the fields `scheme`, `username`, `password`, `host`, `port`,
+--   `path`, `pathSegments` (a list), `query`, and `fragment` (missing
+--   components are `nil`), and the methods `url:join(relative)` and
+--   `url:queryParam(name)`. `tostring(url)` returns the URL as a string, and
+--   entries accept a `Url` wherever a URL is expected.
+--
+-- - `feedgen.util`: a table of string utilities (all of them are UTF-8-aware):
+--   - `feedgen.util.trim(s)` removes leading and trailing whitespace.
+--   - `feedgen.util.collapseWhitespace(s)` replaces runs of whitespace with a
+--     single space and trims the string.
+--   - `feedgen.util.truncate(s, maxLength, ellipsis)` shortens the string to at
+--     most `maxLength` characters, ending it with `ellipsis` (defaults to "…")
+--     if it had to be cut.
+--   - `feedgen.util.stripTags(s)` parses the string as an HTML fragment and
+--     returns its text with the tags removed and character references decoded.
+--
+-- - `feedgen.kv`: a persistent key-value store scoped to the feed, for
+--   remembering state (e.g., pagination cursors) across updates. Only available
+--   inside `extract` and `prepare`. When previewing an extractor, values are
+--   kept only until `extract` returns.
+--   - `feedgen.kv.get(key)` returns the value stored under `key`, or `nil`.
+--   - `feedgen.kv.set(key, value)` stores a string, number, or boolean under
+--     `key`. Setting a key to `nil` removes it.
 --
 -- - `feedgen.log`: a table of logging functions:
 --   - `feedgen.log.trace`: logs a message at the TRACE level.
@@ -24,6 +143,12 @@
 --   if by calls to `tostring` and joined together with a space character.
 --
 -- - `print` and `warn` log messages at the INFO and WARN levels respectively.
+--
+-- - `require(name)` loads a module from the directories listed in the `path`
+--   option of the `[lua]` config section (and only from there). Dots in the
+--   name separate directories: `require("common.dates")` runs
+--   `common/dates.lua` (or `common/dates/init.lua`) once and returns its
+--   result, which is cached for subsequent calls.
 
 local months = {
   Jan = 1,
@@ -41,16 +166,73 @@ local months = {
 }
 
 -- The extractor script must export a **global** function named `extract`.
-function extract(source)
+--
+-- Alternatively, the script can return a module table instead:
+--
+--   return {
+--     init = function(options) ... end,
+--     prepare = function(ctx) ... end,
+--     extract = function(source, ctx) ... end,
+--   }
+--
+-- In this case, `extract` is taken from the table, and the optional `init`
+-- function is called once after the script is loaded with the `options` table
+-- from the extractor's config section, which makes it easy to reuse a single
+-- parameterized module for several feeds.
+--
+-- The script may also define a function `prepare(ctx)` (a global one or a
+-- field of the module table), which is called before the source page is first
+-- fetched, e.g., to log in:
+--
+--   function prepare(ctx)
+--     local response = feedgen.fetch("/login", {
+--       method = "POST",
+--       headers = { ["Content-Type"] = "application/x-www-form-urlencoded" },
+--       body = feedgen.url.encodeQuery({
+--         user = ctx.options.user,
+--         password = ctx.options.password,
+--       }),
+--     })
+--
+--     if not response.ok then
+--       error("could not log in: " .. response.status)
+--     end
+--   end
+--
+-- `ctx` is like the one `extract` receives, without `response`. Cookies set by
+-- the responses are kept and sent with the following requests for the feed
+-- (see `cookie-file` in the config to keep them across restarts). If
+-- `prepare` raises an error, the update fails and `prepare` is tried again on
+-- the next update. Once it succeeds, it only runs again when `extract` calls
+-- `feedgen.loginRequired()`.
+function extract(source, ctx)
   -- This function is called every time a feed is updated to extract feed
   -- entries from the retrieved source page.
   --
-  -- The function is provided a single argument of type `Source`, which is a
+  -- The first argument is of type `Source`, which is a
   -- reference-counted handle to the buffer with the source page's contents.
   -- The `__len` and `__tostring` metamethods are defined for the `Source`,
   -- meaning you can use `#source` to get the length of the contents and
   -- `tostring(source)` to load the contents into the VM as a Lua string (not
   -- recommended: they can be huge).
+  --
+  -- The second argument is a table describing the extraction:
+  -- - `ctx.feedName`: the name of the feed (`nil` when previewing an
+  --   extractor).
+  -- - `ctx.url`: the URL of the source page as a `Url` (see `feedgen.url`).
+  -- - `ctx.fetchTime`: the time the page was fetched, as a `pubDate` table.
+  -- - `ctx.options`: the `options` table from the extractor's config section.
+  -- - `ctx.response`: the HTTP response the page was retrieved from (`nil` if
+  --   the page was supplied directly when previewing), with the fields:
+  --   - `status`: the HTTP status code.
+  --   - `url`: the final URL after redirects, as a `Url`. Relative links in
+  --     returned entries are resolved against it.
+  --   - `headers`: a table of header values keyed by lowercase header names.
+  -- - `ctx.channel`: an empty table where the script can set the feed's
+  --   `title`, `description`, and `link` (resolved against the page URL) as
+  --   found on the page. They are used in place of the feed name and the
+  --   request URL, unless the feed config sets them explicitly. Only the values
+  --   set while extracting the first page are kept.
 
   -- The main use for the source is to pass it directly to `feedgen.parseHtml`.
   -- It parses the source (or a plain string) as an HTML document, and does so
@@ -59,9 +241,11 @@ function extract(source)
   -- broken websites (and the majority of websites are such).
   local html = feedgen.parseHtml(source)
 
-  -- `feedgen.parseHtml` returns a handle to the HTML DOM. The handle has two
-  -- methods:
+  -- `feedgen.parseHtml` returns a handle to the HTML DOM. The handle has the
+  -- following methods:
   -- - `html:select`: selects elements matching a CSS selector (see below).
+  -- - `html:selectFirst`: returns the first element matching a CSS selector
+  --   (or `nil` if there isn't one).
   -- - `html:root`: returns a reference to the root element (`<html>`).
   --
   -- Note that the DOM is kept in memory as long as a reference to any DOM node
@@ -81,7 +265,16 @@ function extract(source)
   -- The `:select` method returns an iterator — a callable that returns the next
   -- result for each successive call, or `nil` once results are exhausted. You
   -- can call it directly (for example, if you only need the first matching
-  -- element), or use it in the generic `for` loop:
+  -- element), or use it in the generic `for` loop.
+  --
+  -- All iterators also provide:
+  -- - `iter:collect()`: consumes the remaining results and returns them as a
+  --   sequence (`element:attrs()` produces a table mapping attribute names to
+  --   values instead).
+  -- - the `__len` metamethod: `#iter` is the number of remaining results (the
+  --   iterator is not advanced).
+  --
+  -- Here the iterator is used in a `for` loop:
   for tt in html:select("#content > p:first-of-type > tt") do
     -- The Debian news webpage, which I'm using in this example, contains a <p>
     -- element with entries laid out sequentially as follows:
@@ -173,6 +366,13 @@ function extract(source)
     --
     -- - `element:text`: returns an iterator over the element's descendant text
     --   nodes' content (the callable returns strings, not node references).
+    -- - `element:innerText`: returns the element's text as it would be
+    --   rendered: whitespace is collapsed, block elements are put on separate
+    --   lines, paragraphs are separated by blank lines, `<br>` breaks lines,
+    --   and the contents of <script> and <style> are skipped.
+    -- - `element:prevElementSibling`, `element:nextElementSibling`: return the
+    --   previous/next sibling that is an element (like `node:prevSibling` and
+    --   `node:nextSibling`, but skip over non-element siblings).
     -- - `element:childElements`: returns an iterator over the element's child
     --   elements (like `element:childNodes`, but skips over non-element
     --   children).
@@ -182,6 +382,13 @@ function extract(source)
     --
     -- - `element:select`: returns an iterator over the element's descendant
     --   elements that match a CSS selector.
+    -- - `element:selectFirst`: returns the first descendant element matching
+    --   a CSS selector (or `nil` if there isn't one).
+    -- - `element:matches`: returns `true` if the element itself matches a CSS
+    --   selector.
+    -- - `element:closest`: returns the nearest element matching a CSS selector,
+    --   starting with the element itself and walking up its ancestors (or
+    --   `nil` if there isn't one).
     --
     -- - the `__tostring` metamethod returns a concatenation of the values of
     --   the element's descendant text nodes ("hey there" for <span>hey
@@ -193,17 +400,17 @@ function extract(source)
     -- - the `__len` and `__tostring` metamethods provide access to the PI's
     --   data.
 
-    local strong = tt:nextSibling()
+    local strong = tt:nextElementSibling()
 
-    while strong:type() ~= "element" or strong:name() ~= "strong" do
-      strong = strong:nextSibling()
+    while strong:name() ~= "strong" do
+      strong = strong:nextElementSibling()
     end
 
     assert(strong:nextSibling():type() == "element")
     assert(strong:nextSibling():name() == "br")
 
     -- This finds the first matching element.
-    local inner = strong:select("a")()
+    local inner = strong:selectFirst("a")
 
     local day, month, year = tostring(tt):match("(%d+) (%w+) (%d+)")
     local day = tonumber(day)
@@ -226,6 +433,10 @@ function extract(source)
       -- The description. Required; may be empty.
       description = title,
 
+      -- The full HTML body of the entry, emitted as `content:encoded` in RSS
+      -- feeds (the description serves as a summary). Optional.
+      -- content = "<p>...</p>",
+
       -- The entry's URL. Required; must be valid. Relative URLs are resolved
       -- relative to the source page's URL.
       url = url,
@@ -245,7 +456,8 @@ function extract(source)
         second = 0,
 
         -- The date must include timezone information, either via `utcOffset` or
-        -- `tz`. If both are provided, the `utcOffset` field is ignored.
+        -- `tz`, unless the feed has a `timezone`, which is used if neither is
+        -- provided. If both are provided, the `utcOffset` field is ignored.
         --
         -- The `utcOffset` field must contain an integer offset from UTC in
         -- minutes.
@@ -257,8 +469,48 @@ function extract(source)
 
         -- Other fields are ignored.
       },
+
+      -- A preview image URL. Relative URLs are resolved like `url`. Optional.
+      -- image = "thumbnail.jpg",
+
+      -- The URL of the comments page. Relative URLs are resolved like `url`.
+      -- Optional.
+      -- comments = "comments.html",
+
+      -- The names of the entry's categories (tags). Empty names are ignored.
+      -- Optional.
+      -- categories = { "announcements", "security" },
+
+      -- A custom GUID. By default, the GUID is generated from the feed name and
+      -- `id`. Optional.
+      -- guid = "debian-news:" .. url,
+
+      -- Whether the GUID (`guid` if set, or `url` otherwise) is the URL of the
+      -- entry. Defaults to `false`.
+      -- guidIsPermalink = true,
+
+      -- Extra elements added to RSS entries, keyed by their names. The names
+      -- must have a namespace prefix declared in the feed's `namespaces`
+      -- config option; other elements are skipped. Optional.
+      -- extra = { ["dc:creator"] = "Debian" },
+
+      -- A media file attached to the entry (e.g., a podcast episode). Optional.
+      -- enclosure = {
+      --   -- The file's URL. Required. Relative URLs are resolved like `url`.
+      --   url = "episode-1.mp3",
+      --
+      --   -- The MIME type. Required. (`type` is accepted as an alias.)
+      --   mimeType = "audio/mpeg",
+      --
+      --   -- The size of the file in bytes. Optional.
+      --   length = 12345678,
+      -- },
     })
   end
 
+  -- Optionally, the function can return a second value: the URL of the next
+  -- page of the listing (relative URLs are resolved like entry URLs). If the
+  -- feed's `max-pages` option allows, the page is fetched and passed to
+  -- `extract` as well, and the entries of all pages are stored together.
   return entries
 end
@@ -0,0 +1,3 @@
+ALTER TABLE entries ADD COLUMN enclosure_url TEXT;
+ALTER TABLE entries ADD COLUMN enclosure_type TEXT;
+ALTER TABLE entries ADD COLUMN enclosure_length INTEGER;
@@ -0,0 +1,2 @@
+-- A JSON object mapping qualified element names to their text.
+ALTER TABLE entries ADD COLUMN extra TEXT;
@@ -0,0 +1,9 @@
+-- Images served through `/img/:hash`, keyed by the hash of their URL.
+-- The contents are filled in when the image is first requested.
+CREATE TABLE images (
+    hash TEXT PRIMARY KEY,
+    url TEXT NOT NULL,
+    content_type TEXT,
+    data BLOB,
+    fetched INTEGER
+);
@@ -0,0 +1,3 @@
+ALTER TABLE entries ADD COLUMN comments_url TEXT;
+ALTER TABLE entries ADD COLUMN guid TEXT;
+ALTER TABLE entries ADD COLUMN guid_is_permalink INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,8 @@
+-- Values stored by Lua extractors with `feedgen.kv.set`, scoped to the feed.
+CREATE TABLE kv (
+    feed_name TEXT NOT NULL,
+    key TEXT NOT NULL,
+    value TEXT NOT NULL,
+
+    PRIMARY KEY (feed_name, key)
+);
@@ -0,0 +1,2 @@
+-- A JSON array of category names.
+ALTER TABLE entries ADD COLUMN categories TEXT;
@@ -0,0 +1,5 @@
+-- A hash of the entry's normalized title and text, used to find entries republished under a
+-- different id.
+ALTER TABLE entries ADD COLUMN content_hash TEXT;
+
+CREATE INDEX entries_content_hash ON entries (feed_id, content_hash);
@@ -0,0 +1,10 @@
+-- The first source page retrieved by the most recent successful update of each feed, replayed by
+-- `feedgen verify`.
+CREATE TABLE responses (
+    feed_id INTEGER PRIMARY KEY REFERENCES feeds (id) ON DELETE CASCADE,
+    url TEXT NOT NULL,
+    status INTEGER NOT NULL,
+    headers TEXT NOT NULL,
+    body TEXT NOT NULL,
+    fetched INTEGER NOT NULL
+);
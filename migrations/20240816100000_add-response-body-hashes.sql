@@ -0,0 +1,3 @@
+-- A hash of the stored source page along with the feed's config, used to skip extracting a page
+-- that has not changed since.
+ALTER TABLE responses ADD COLUMN body_hash TEXT;
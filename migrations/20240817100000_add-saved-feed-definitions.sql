@@ -0,0 +1,6 @@
+-- Feed definitions (as JSON) saved on the admin page. They are used at startup instead of the
+-- config file's definitions of the same feeds.
+CREATE TABLE saved_feeds (
+    name TEXT PRIMARY KEY NOT NULL,
+    definition TEXT NOT NULL
+);
@@ -0,0 +1,26 @@
+-- Images served through `/img/:feed/:hash` are registered per feed, so that they're fetched with
+-- the feed's HTTP client and served with its access token. The images registered before are kept
+-- for the feeds whose entries refer to them.
+CREATE TABLE feed_images (
+    feed_id INTEGER NOT NULL REFERENCES feeds (id) ON DELETE CASCADE,
+    hash TEXT NOT NULL,
+    url TEXT NOT NULL,
+    content_type TEXT,
+    data BLOB,
+    fetched INTEGER,
+
+    PRIMARY KEY (feed_id, hash)
+);
+
+INSERT
+INTO feed_images (feed_id, hash, url, content_type, data, fetched)
+SELECT DISTINCT entries.feed_id, images.hash, images.url, images.content_type, images.data, images.fetched
+FROM images
+  JOIN entries ON (
+    entries.image_url = images.url
+    OR instr(entries.description || coalesce(entries.content, ''), images.url) > 0
+    OR instr(entries.description || coalesce(entries.content, ''), replace(images.url, '&', '&amp;')) > 0
+  );
+
+DROP TABLE images;
+ALTER TABLE feed_images RENAME TO images;
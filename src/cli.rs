@@ -20,8 +20,10 @@ pub struct Args {
     pub config_path: Option<PathBuf>,
 
     /// RSS feed server address to bind to.
-    #[arg(long, env = "FEEDGEN_BIND_ADDR")]
-    pub bind_addr: Option<String>,
+    ///
+    /// Can be given several times (or as a comma-separated list) to listen on several addresses.
+    #[arg(long, env = "FEEDGEN_BIND_ADDR", value_delimiter = ',')]
+    pub bind_addr: Vec<String>,
 
     /// Path to the database file.
     #[arg(long, env = "FEEDGEN_DB", value_hint(ValueHint::FilePath))]
@@ -30,6 +32,43 @@ pub struct Args {
     /// Path to the cache directory.
     #[arg(long, env = "FEEDGEN_CACHE_DIR", value_hint(ValueHint::DirPath))]
     pub cache_dir: Option<PathBuf>,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(clap::Subcommand, Debug, Clone)]
+pub enum Command {
+    /// Open an interactive Lua session with the Feedgen API loaded.
+    ///
+    /// The page is available as the global `page` (a parsed HTML document), along with `source`
+    /// and `ctx` (the arguments `extract` would receive).
+    Lua(LuaArgs),
+
+    /// Replay the source page of each feed's latest update through its extractor.
+    ///
+    /// Reports the number of extracted entries and how they differ from the stored ones. Fails if
+    /// the extractor of any feed fails or extracts fewer entries than required.
+    Verify(VerifyArgs),
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct LuaArgs {
+    /// The feed to take the extractor script and options from.
+    ///
+    /// Unless `--input` is given, the feed's page is fetched.
+    #[arg(long)]
+    pub feed: Option<String>,
+
+    /// Path to an HTML file to load as the page.
+    #[arg(long, value_hint(ValueHint::FilePath))]
+    pub input: Option<PathBuf>,
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct VerifyArgs {
+    /// The feeds to verify (all of them by default).
+    pub feeds: Vec<String>,
 }
 
 impl Args {
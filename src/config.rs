@@ -1,20 +1,38 @@
 mod types;
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::fs::File;
 use std::io::{self, Read};
-use std::path::{Path, PathBuf};
+use std::num::NonZeroUsize;
+use std::path::{Component, Path, PathBuf};
 
-use anyhow::{anyhow, Context, Result};
+use anyhow::{anyhow, bail, Context, Result};
 use reqwest::Url;
-use serde::Deserialize;
-use tracing::{debug, info};
+use serde::{Deserialize, Serialize};
 use take_mut::take;
+use tracing::{debug, info};
 
-use crate::xpath::XPath;
+use crate::css::{CssQuery, CssSelector};
+use crate::jsonpath::JsonPath;
+use crate::reldate::Locale;
+use crate::xpath::XPathList;
 
 pub use self::types::*;
 
+/// Fails unless `path` is inside `dir` (where it cannot escape with `..`).
+pub fn check_within(path: &Path, dir: &Path) -> Result<()> {
+    let is_within = path.strip_prefix(dir).is_ok_and(|rest| {
+        rest.components()
+            .all(|component| matches!(component, Component::Normal(_) | Component::CurDir))
+    });
+
+    if !is_within {
+        bail!("`{}` is outside of `{}`", path.display(), dir.display());
+    }
+
+    Ok(())
+}
+
 fn default_fetch_interval() -> Duration {
     Config::default().fetch_interval
 }
@@ -23,19 +41,84 @@ fn default_max_initial_fetch_sleep() -> Duration {
     Config::default().max_initial_fetch_sleep
 }
 
+fn default_max_concurrent_fetches() -> NonZeroUsize {
+    Config::default().max_concurrent_fetches
+}
+
+fn default_max_entries() -> usize {
+    Config::default().max_entries
+}
+
+fn default_health_stale_factor() -> u32 {
+    Config::default().health_stale_factor
+}
+
 #[derive(Deserialize, Debug, Clone)]
 #[serde(rename_all = "kebab-case", deny_unknown_fields)]
 pub struct Config {
-    pub bind_addr: String,
+    pub bind_addr: BindAddrs,
+
+    /// The public URL Feedgen is reachable at (if served under a path prefix or behind a proxy).
+    pub base_url: Option<Url>,
+
     pub db_path: PathBuf,
     pub cache_dir: Option<PathBuf>,
+
+    /// The proxy HTTP requests are sent through (`http://`, `https://`, or `socks5://`).
+    pub proxy: Option<Url>,
+
     pub feeds: HashMap<String, Feed>,
 
+    #[serde(default)]
+    pub aggregates: HashMap<String, Aggregate>,
+
     #[serde(default = "default_fetch_interval")]
     pub fetch_interval: Duration,
 
+    /// A daily period during which scheduled updates are deferred.
+    pub no_fetch_between: Option<TimeWindow>,
+
     #[serde(default = "default_max_initial_fetch_sleep")]
     pub max_initial_fetch_sleep: Duration,
+
+    #[serde(default = "default_max_concurrent_fetches")]
+    pub max_concurrent_fetches: NonZeroUsize,
+
+    /// The number of entries included in a feed.
+    #[serde(default = "default_max_entries")]
+    pub max_entries: usize,
+
+    #[serde(default = "default_health_stale_factor")]
+    pub health_stale_factor: u32,
+
+    /// Whether to drop extracted entries that fail validation instead of only logging the problems.
+    #[serde(default)]
+    pub strict_output: bool,
+
+    #[serde(default)]
+    pub auth: AuthConfig,
+
+    #[serde(default)]
+    pub rate_limit: RateLimitConfig,
+
+    #[serde(default)]
+    pub cors: CorsConfig,
+
+    pub access_log: Option<AccessLogConfig>,
+
+    #[serde(default)]
+    pub lua: LuaConfig,
+
+    pub tls_cert: Option<PathBuf>,
+    pub tls_key: Option<PathBuf>,
+
+    /// URLs notified about new entries of every feed.
+    #[serde(default)]
+    pub webhooks: Vec<Url>,
+
+    /// The directory relative paths are resolved against.
+    #[serde(skip)]
+    pub config_dir: PathBuf,
 }
 
 impl Config {
@@ -46,7 +129,10 @@ impl Config {
             }
         }
 
-        set_if_some(&mut self.bind_addr, args.bind_addr);
+        if !args.bind_addr.is_empty() {
+            self.bind_addr = args.bind_addr.into();
+        }
+
         set_if_some(&mut self.db_path, args.db_path);
         set_if_some(&mut self.cache_dir, args.cache_dir.map(Some));
     }
@@ -60,13 +146,36 @@ impl Config {
                 feed.resolve_relative_paths(config_dir);
             }
 
+            this.lua.resolve_relative_paths(config_dir);
+
             Self {
                 bind_addr: this.bind_addr,
+                base_url: this.base_url,
                 db_path: config_dir.join(&this.db_path),
                 cache_dir: this.cache_dir.map(|cache_dir| config_dir.join(cache_dir)),
+                proxy: this.proxy,
                 feeds: this.feeds,
+                aggregates: this.aggregates,
                 fetch_interval: this.fetch_interval,
+                no_fetch_between: this.no_fetch_between,
                 max_initial_fetch_sleep: this.max_initial_fetch_sleep,
+                max_concurrent_fetches: this.max_concurrent_fetches,
+                max_entries: this.max_entries,
+                health_stale_factor: this.health_stale_factor,
+                strict_output: this.strict_output,
+                auth: this.auth,
+                rate_limit: this.rate_limit,
+                cors: this.cors,
+                access_log: this.access_log.map(|mut access_log| {
+                    access_log.path = config_dir.join(access_log.path);
+
+                    access_log
+                }),
+                lua: this.lua,
+                tls_cert: this.tls_cert.map(|path| config_dir.join(path)),
+                tls_key: this.tls_key.map(|path| config_dir.join(path)),
+                webhooks: this.webhooks,
+                config_dir: config_dir.into(),
             }
         })
     }
@@ -75,29 +184,310 @@ impl Config {
 impl Default for Config {
     fn default() -> Self {
         Config {
-            bind_addr: "127.0.0.1:20654".into(),
+            bind_addr: vec!["127.0.0.1:20654".into()].into(),
+            base_url: None,
             db_path: "./feedgen.sqlite3".into(),
             cache_dir: None,
+            proxy: None,
             fetch_interval: Duration::from_secs(7200),
+            no_fetch_between: None,
             max_initial_fetch_sleep: Duration::from_secs(45),
+            max_concurrent_fetches: NonZeroUsize::new(4).unwrap(),
+            max_entries: 100,
+            health_stale_factor: 3,
+            strict_output: false,
+            auth: Default::default(),
+            rate_limit: Default::default(),
+            cors: Default::default(),
+            access_log: None,
+            lua: Default::default(),
+            tls_cert: None,
+            tls_key: None,
+            webhooks: Default::default(),
+            config_dir: ".".into(),
             feeds: Default::default(),
+            aggregates: Default::default(),
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct AuthConfig {
+    pub bearer_token: Option<String>,
+    pub username: Option<String>,
+    pub password: Option<String>,
+
+    /// Whether to require authentication for reading feeds as well.
+    #[serde(default)]
+    pub protect_feeds: bool,
+}
+
+impl AuthConfig {
+    pub fn is_enabled(&self) -> bool {
+        self.bearer_token.is_some() || self.username.is_some()
+    }
+
+    /// Fails on settings that would leave the server open or lock everyone out.
+    fn check(&self) -> Result<()> {
+        match (&self.username, &self.password) {
+            (Some(_), None) => bail!("`username` is set without `password`"),
+            (None, Some(_)) => bail!("`password` is set without `username`"),
+            _ => {}
+        }
+
+        if matches!(&self.bearer_token, Some(token) if token.trim().is_empty()) {
+            bail!("`bearer-token` is empty");
+        }
+
+        Ok(())
+    }
+}
+
+fn default_admin_requests_per_minute() -> Option<u32> {
+    RateLimitConfig::default().admin_requests_per_minute
+}
+
+/// Per-client-IP request limits.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct RateLimitConfig {
+    /// The limit for all routes.
+    pub requests_per_minute: Option<u32>,
+
+    /// The limit for routes that trigger updates or expose the configuration.
+    #[serde(default = "default_admin_requests_per_minute")]
+    pub admin_requests_per_minute: Option<u32>,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self {
+            requests_per_minute: None,
+            admin_requests_per_minute: Some(10),
+        }
+    }
+}
+
+fn default_cors_allowed_methods() -> Vec<String> {
+    CorsConfig::default().allowed_methods
+}
+
+/// Cross-origin resource sharing settings.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct CorsConfig {
+    /// Origins allowed to make cross-origin requests (`*` allows any). CORS is disabled if empty.
+    #[serde(default)]
+    pub allowed_origins: Vec<String>,
+
+    #[serde(default = "default_cors_allowed_methods")]
+    pub allowed_methods: Vec<String>,
+}
+
+impl Default for CorsConfig {
+    fn default() -> Self {
+        Self {
+            allowed_origins: vec![],
+            allowed_methods: vec!["GET".into(), "HEAD".into()],
+        }
+    }
+}
+
+/// Settings shared by all Lua extractors.
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct LuaConfig {
+    /// Directories `require` looks up modules in. Modules can't be loaded from anywhere else.
+    #[serde(default)]
+    pub path: Vec<PathBuf>,
+
+    /// Scripts run in every extractor VM before the extractor script. Their return values are
+    /// stored in globals named after the files.
+    #[serde(default)]
+    pub preload: Vec<PathBuf>,
+
+    /// The memory limit of each extractor's VM. Unlimited if unset.
+    pub max_memory: Option<ByteSize>,
+
+    /// The Lua runtime the scripts are written for. Feedgen refuses to load them if it was built
+    /// with a different one.
+    pub runtime: Option<LuaRuntime>,
+}
+
+/// A Lua implementation Feedgen can be built with (selected by a cargo feature of the same name).
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum LuaRuntime {
+    Lua54,
+    #[serde(rename = "luajit")]
+    LuaJit,
+    Luau,
+}
+
+impl LuaRuntime {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Lua54 => "lua54",
+            Self::LuaJit => "luajit",
+            Self::Luau => "luau",
         }
     }
 }
 
+impl LuaConfig {
+    pub fn resolve_relative_paths(&mut self, config_dir: impl AsRef<Path>) {
+        let config_dir = config_dir.as_ref();
+
+        take(self, |this| Self {
+            path: this.path.iter().map(|dir| config_dir.join(dir)).collect(),
+            preload: this
+                .preload
+                .iter()
+                .map(|path| config_dir.join(path))
+                .collect(),
+            max_memory: this.max_memory,
+            runtime: this.runtime,
+        })
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct AccessLogConfig {
+    pub path: PathBuf,
+
+    /// The format of a log line with `{field}` placeholders.
+    pub format: Option<String>,
+}
+
 fn default_feed_enabled() -> bool {
     true
 }
 
-#[derive(Deserialize, Debug, Clone)]
+fn default_rewrite_relative_urls() -> bool {
+    true
+}
+
+fn default_sanitize_html() -> bool {
+    true
+}
+
+fn default_max_pages() -> usize {
+    1
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
 #[serde(rename_all = "kebab-case", deny_unknown_fields)]
 pub struct Feed {
     #[serde(default = "default_feed_enabled")]
     pub enabled: bool,
 
     pub request_url: Url,
+
+    /// Additional HTTP headers sent with the feed's requests (e.g., `Accept-Language`).
+    #[serde(default)]
+    pub headers: HttpHeaders,
+
+    /// Cookies sent with the feed's requests to the host of `request_url`, keyed by their names.
+    #[serde(default)]
+    pub cookies: BTreeMap<String, String>,
+
+    /// A file the feed's cookies are loaded from at startup and saved to after every update.
+    pub cookie_file: Option<PathBuf>,
+
+    /// The credentials sent in the `Authorization` header with the feed's requests to the origin
+    /// of `request_url`.
+    pub credentials: Option<Credentials>,
+
+    /// Overrides the global `proxy`.
+    pub proxy: Option<Url>,
+
     pub extractor: ExtractorConfig,
     pub fetch_interval: Option<Duration>,
+
+    /// If set, the fetch interval is adjusted to how often new entries appear.
+    pub adaptive_fetch_interval: Option<AdaptiveFetchInterval>,
+
+    /// Overrides the global `no_fetch_between`.
+    pub no_fetch_between: Option<TimeWindow>,
+
+    /// Whether to make the URLs in descriptions absolute.
+    #[serde(default = "default_rewrite_relative_urls")]
+    pub rewrite_relative_urls: bool,
+
+    /// Whether to remove scripts, event handlers, and other unsafe markup from descriptions.
+    #[serde(default = "default_sanitize_html")]
+    pub sanitize_html: bool,
+
+    /// Whether to remove `utm_*`, `fbclid`, and `tracking_params` from entry URLs.
+    #[serde(default)]
+    pub strip_tracking_params: bool,
+
+    /// Additional query parameters removed from entry URLs.
+    #[serde(default)]
+    pub tracking_params: Vec<String>,
+
+    /// Transformations applied to extracted entries in order, after the built-in ones.
+    #[serde(default)]
+    pub pipeline: Vec<PipelineStage>,
+
+    /// If set, the page of each new entry is retrieved to extract its full content.
+    pub full_content: Option<FullContentConfig>,
+
+    /// The timezone of extracted datetimes that have no UTC offset.
+    pub timezone: Option<Timezone>,
+
+    /// The kind of document the source page must be, overriding the one the extractor expects.
+    pub expected_content: Option<ContentKind>,
+
+    /// What to do if some entries or pages could not be extracted, or no entries were found.
+    #[serde(default)]
+    pub on_extraction_failure: ExtractionFailurePolicy,
+
+    /// The minimum number of entries an update must extract. If fewer are extracted, the update
+    /// fails and the stored entries are kept.
+    #[serde(default)]
+    pub min_entries: usize,
+
+    /// Overrides the global `strict_output`.
+    pub strict_output: Option<bool>,
+
+    /// Whether to serve the images of entries through the `/img/:hash` route.
+    #[serde(default)]
+    pub proxy_images: bool,
+
+    /// The maximum number of pages fetched per update if the extractor returns a next page.
+    #[serde(default = "default_max_pages")]
+    pub max_pages: usize,
+
+    /// Whether to skip extracting the source page if it has not changed since the last update.
+    /// Defaults to whether the extractor only reads the source page.
+    pub skip_unchanged: Option<bool>,
+
+    #[serde(default)]
+    pub priority: i32,
+
+    pub max_entries: Option<usize>,
+    pub group: Option<String>,
+
+    /// If set, the feed is private and must be requested with `?token=<token>`.
+    pub token: Option<String>,
+
+    // generated channel metadata.
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub language: Option<String>,
+    pub link: Option<Url>,
+    pub copyright: Option<String>,
+
+    /// XML namespaces of extra entry elements, keyed by their prefixes.
+    #[serde(default)]
+    pub namespaces: BTreeMap<String, String>,
+
+    /// URLs notified about new entries of this feed (in addition to the global ones).
+    #[serde(default)]
+    pub webhooks: Vec<Url>,
 }
 
 impl Feed {
@@ -110,18 +500,320 @@ impl Feed {
             Self {
                 enabled: this.enabled,
                 request_url: this.request_url,
+                headers: this.headers,
+                cookies: this.cookies,
+                cookie_file: this.cookie_file.map(|path| config_dir.join(path)),
+                credentials: this
+                    .credentials
+                    .map(|credentials| credentials.resolve_relative_paths(config_dir)),
+                proxy: this.proxy,
                 extractor: this.extractor,
                 fetch_interval: this.fetch_interval,
+                adaptive_fetch_interval: this.adaptive_fetch_interval,
+                no_fetch_between: this.no_fetch_between,
+                rewrite_relative_urls: this.rewrite_relative_urls,
+                sanitize_html: this.sanitize_html,
+                strip_tracking_params: this.strip_tracking_params,
+                tracking_params: this.tracking_params,
+                pipeline: this.pipeline,
+                full_content: this.full_content,
+                timezone: this.timezone,
+                expected_content: this.expected_content,
+                on_extraction_failure: this.on_extraction_failure,
+                min_entries: this.min_entries,
+                strict_output: this.strict_output,
+                proxy_images: this.proxy_images,
+                max_pages: this.max_pages,
+                skip_unchanged: this.skip_unchanged,
+                priority: this.priority,
+                max_entries: this.max_entries,
+                group: this.group,
+                token: this.token,
+                title: this.title,
+                description: this.description,
+                language: this.language,
+                link: this.link,
+                copyright: this.copyright,
+                namespaces: this.namespaces,
+                webhooks: this.webhooks,
             }
         })
     }
+
+    /// The kind of document the source page must be.
+    pub fn content_kind(&self) -> ContentKind {
+        self.expected_content
+            .unwrap_or(self.extractor.content_kind())
+    }
+
+    /// Serializes the feed with its secrets replaced by [`REDACTED`]: the access token, the
+    /// credentials, cookie values, values of headers that may hold secrets, and a proxy URL with a
+    /// password.
+    pub fn to_redacted_json(&self) -> Result<serde_json::Value> {
+        let mut value =
+            serde_json::to_value(self).context("could not serialize the feed config")?;
+        let fields = value
+            .as_object_mut()
+            .context("the feed config is not serialized as an object")?;
+        let redacted = || serde_json::Value::from(REDACTED);
+
+        if self.token.is_some() {
+            fields.insert("token".into(), redacted());
+        }
+
+        if self.credentials.is_some() {
+            fields.insert("credentials".into(), redacted());
+        }
+
+        if self
+            .proxy
+            .as_ref()
+            .is_some_and(|proxy| proxy.password().is_some())
+        {
+            fields.insert("proxy".into(), redacted());
+        }
+
+        if let Some(cookies) = fields.get_mut("cookies").and_then(|v| v.as_object_mut()) {
+            for value in cookies.values_mut() {
+                *value = redacted();
+            }
+        }
+
+        if let Some(headers) = fields.get_mut("headers").and_then(|v| v.as_object_mut()) {
+            for (name, value) in headers {
+                if is_sensitive_header(name) {
+                    *value = redacted();
+                }
+            }
+        }
+
+        Ok(value)
+    }
 }
 
-#[derive(Deserialize, Debug, Clone)]
+/// Replaces the secrets in feed configs served by the API.
+pub const REDACTED: &str = "<redacted>";
+
+/// Replaces the [`REDACTED`] values in a feed definition (one retrieved with
+/// [`Feed::to_redacted_json`] and edited) with the values at the same place in `original`.
+pub fn restore_redacted(
+    value: &mut serde_json::Value,
+    original: Option<&serde_json::Value>,
+    path: &str,
+) -> Result<()> {
+    match value {
+        serde_json::Value::String(s) if s == REDACTED => {
+            *value = original
+                .filter(|original| !original.is_null())
+                .cloned()
+                .ok_or_else(|| anyhow!("`{path}` is redacted, but there is no value to restore"))?;
+        }
+
+        serde_json::Value::Object(fields) => {
+            for (key, field) in fields {
+                let original = original.and_then(|original| original.get(key));
+                let path = if path.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{path}.{key}")
+                };
+
+                restore_redacted(field, original, &path)?;
+            }
+        }
+
+        _ => {}
+    }
+
+    Ok(())
+}
+
+/// Whether the value of an HTTP header may be a secret (e.g., `Authorization` or `X-Api-Key`).
+fn is_sensitive_header(name: &str) -> bool {
+    let name = name.to_ascii_lowercase();
+
+    ["auth", "cookie", "token", "key", "secret", "session"]
+        .iter()
+        .any(|part| name.contains(part))
+}
+
+/// HTTP authentication credentials.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(tag = "kind", rename_all = "kebab-case", deny_unknown_fields)]
+pub enum Credentials {
+    Basic {
+        username: String,
+        password: Option<Secret>,
+    },
+
+    Bearer {
+        token: Secret,
+    },
+}
+
+impl Credentials {
+    fn resolve_relative_paths(self, config_dir: &Path) -> Self {
+        match self {
+            Self::Basic { username, password } => Self::Basic {
+                username,
+                password: password.map(|password| password.resolve_relative_paths(config_dir)),
+            },
+
+            Self::Bearer { token } => Self::Bearer {
+                token: token.resolve_relative_paths(config_dir),
+            },
+        }
+    }
+}
+
+/// A value kept out of the config file, written as `{ env = "NAME" }` or `{ file = "path" }`.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub enum Secret {
+    /// The value of an environment variable.
+    Env(String),
+
+    /// The contents of a file, without the trailing newline.
+    File(PathBuf),
+}
+
+impl Secret {
+    fn resolve_relative_paths(self, config_dir: &Path) -> Self {
+        match self {
+            Self::Env(name) => Self::Env(name),
+            Self::File(path) => Self::File(config_dir.join(path)),
+        }
+    }
+
+    pub fn read(&self) -> Result<String> {
+        match self {
+            Self::Env(name) => std::env::var(name)
+                .with_context(|| anyhow!("could not read the environment variable `{name}`")),
+
+            Self::File(path) => {
+                let mut value = std::fs::read_to_string(path)
+                    .with_context(|| anyhow!("could not read `{}`", path.display()))?;
+                value.truncate(value.trim_end_matches(['\r', '\n']).len());
+
+                Ok(value)
+            }
+        }
+    }
+}
+
+/// The bounds of a fetch interval that is adjusted to how often new entries appear.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct AdaptiveFetchInterval {
+    pub min: Duration,
+    pub max: Duration,
+}
+
+/// How the full content of an entry is extracted from its page.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(tag = "kind", rename_all = "kebab-case", deny_unknown_fields)]
+pub enum FullContentConfig {
+    /// The inner HTML of the first element matching the selector.
+    Css { selector: CssSelector },
+
+    /// The HTML of the nodes selected by the first expression that selects any.
+    #[serde(rename = "xpath")]
+    XPath { xpath: XPathList },
+
+    /// The main content as found by a readability-style heuristic.
+    Readability,
+}
+
+/// A step of a feed's entry processing pipeline.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(tag = "kind", rename_all = "kebab-case", deny_unknown_fields)]
+pub enum PipelineStage {
+    /// Keeps the entries matching every `include_*` rule and none of the `exclude_*` rules.
+    ///
+    /// A category rule matches if any of the entry's categories does.
+    #[serde(rename_all = "kebab-case")]
+    Filter {
+        include_title: Option<TextMatcher>,
+        exclude_title: Option<TextMatcher>,
+        include_url: Option<TextMatcher>,
+        exclude_url: Option<TextMatcher>,
+        include_author: Option<TextMatcher>,
+        exclude_author: Option<TextMatcher>,
+        include_category: Option<TextMatcher>,
+        exclude_category: Option<TextMatcher>,
+    },
+
+    /// Replaces the matches of `pattern` in `field` (see `Regex::replace_all` for the syntax of
+    /// `replacement`).
+    Rewrite {
+        field: EntryField,
+        pattern: RegexPattern,
+        replacement: String,
+    },
+
+    /// Sets `field` to the result of rendering `template` with the entry's fields.
+    Template {
+        field: EntryField,
+        template: HandlebarsTemplate,
+    },
+
+    /// Drops the entries whose `by` field repeats that of an earlier entry.
+    Dedup {
+        #[serde(default = "default_dedup_field")]
+        by: EntryField,
+    },
+
+    /// Drops the entries whose title and text (ignoring markup, case, and whitespace) match those
+    /// of an entry on the same page or an entry stored before, even if the ids differ.
+    DedupContent,
+
+    /// Removes unsafe markup from descriptions and contents.
+    Sanitize,
+}
+
+fn default_dedup_field() -> EntryField {
+    EntryField::Url
+}
+
+/// An entry field that pipeline stages operate on. Missing fields are treated as empty strings.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum EntryField {
+    Id,
+    Title,
+    Description,
+    Content,
+    Url,
+    Author,
+}
+
+/// A virtual feed that combines entries from several feeds.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Aggregate {
+    pub feeds: Vec<String>,
+    pub title: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
 #[serde(tag = "kind", rename_all = "kebab-case", deny_unknown_fields)]
 pub enum ExtractorConfig {
     #[serde(rename = "xpath")]
-    XPath(XPathExtractorConfig),
+    XPath(Box<XPathExtractorConfig>),
+
+    Css(Box<CssExtractorConfig>),
+
+    Json(Box<JsonExtractorConfig>),
+
+    Regex(RegexExtractorConfig),
+
+    Feed(FeedExtractorConfig),
+
+    Watch(WatchExtractorConfig),
+
+    Sitemap(SitemapExtractorConfig),
+
+    Metadata(MetadataExtractorConfig),
 
     Lua(LuaExtractorConfig),
 }
@@ -132,43 +824,414 @@ impl ExtractorConfig {
 
         match self {
             Self::XPath(cfg) => cfg.resolve_relative_paths(config_dir),
+            Self::Css(cfg) => cfg.resolve_relative_paths(config_dir),
+            Self::Json(cfg) => cfg.resolve_relative_paths(config_dir),
+            Self::Regex(cfg) => cfg.resolve_relative_paths(config_dir),
+            Self::Feed(_) | Self::Watch(_) | Self::Sitemap(_) | Self::Metadata(_) => {}
             Self::Lua(cfg) => cfg.resolve_relative_paths(config_dir),
         }
     }
+
+    /// Fails if the extractor runs a Lua script outside `config_dir`.
+    pub fn check_file_paths(&self, config_dir: &Path) -> Result<()> {
+        if let Self::Lua(cfg) = self {
+            check_within(&cfg.path, config_dir).context("the Lua script is not allowed")?;
+        }
+
+        Ok(())
+    }
+
+    /// The kind of document the extractor parses.
+    pub fn content_kind(&self) -> ContentKind {
+        match self {
+            Self::XPath(_) | Self::Css(_) | Self::Watch(_) | Self::Metadata(_) => ContentKind::Html,
+            Self::Json(_) => ContentKind::Json,
+            Self::Feed(_) | Self::Sitemap(_) => ContentKind::Xml,
+            Self::Regex(_) | Self::Lua(_) => ContentKind::Any,
+        }
+    }
+
+    /// Whether the extractor may retrieve pages other than the source page (so that its results
+    /// may change even if the source page does not).
+    pub fn reads_other_pages(&self) -> bool {
+        matches!(self, Self::Sitemap(_) | Self::Lua(_))
+    }
 }
 
-#[derive(Deserialize, Debug, Clone)]
+/// The kind of document a source page is, as told by its `Content-Type`.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum ContentKind {
+    /// `text/html` or `application/xhtml+xml`.
+    Html,
+
+    /// `text/xml`, `application/xml`, or an `+xml` type (e.g., `application/rss+xml`).
+    Xml,
+
+    /// `application/json` or a `+json` type (e.g., `application/feed+json`).
+    Json,
+
+    /// Any type: the `Content-Type` is not checked.
+    Any,
+}
+
+impl ContentKind {
+    /// Whether a MIME type (without parameters, lowercase) is of this kind.
+    pub fn matches(self, mime_type: &str) -> bool {
+        let subtype = mime_type.split_once('/').map_or("", |(_, subtype)| subtype);
+
+        match self {
+            Self::Html => matches!(mime_type, "text/html" | "application/xhtml+xml"),
+            Self::Xml => subtype == "xml" || subtype.ends_with("+xml"),
+            Self::Json => subtype == "json" || subtype.ends_with("+json"),
+            Self::Any => true,
+        }
+    }
+}
+
+/// The version of XPath the expressions of an extractor are written in.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum XPathVersion {
+    #[default]
+    #[serde(rename = "1.0")]
+    V1_0,
+
+    /// Needs the `xpath3` feature.
+    #[serde(rename = "3.1")]
+    V3_1,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
 #[serde(rename_all = "kebab-case", deny_unknown_fields)]
 pub struct XPathExtractorConfig {
-    pub entry: XPath,
-    pub id: XPath,
-    pub title: XPath,
-    pub description: XPath,
-    pub url: XPath,
-    pub author: Option<XPath>,
-    pub pub_date: Option<XPath>,
-    pub pub_date_format: Option<DateTimeFormat>,
+    #[serde(default)]
+    pub xpath_version: XPathVersion,
+    pub entry: XPathList,
+    pub id: XPathList,
+    pub title: XPathList,
+    pub description: XPathList,
+    pub content: Option<XPathList>,
+    pub url: XPathList,
+    pub author: Option<XPathList>,
+    pub pub_date: Option<XPathList>,
+    pub pub_date_format: Option<DateTimeFormats>,
+
+    /// Languages of relative dates ("5 min ago") accepted if `pub_date` doesn't match the format.
+    #[serde(default)]
+    pub relative_date_locales: Vec<Locale>,
+    pub enclosure_url: Option<XPathList>,
+    pub enclosure_type: Option<XPathList>,
+    pub enclosure_length: Option<XPathList>,
+    pub image: Option<XPathList>,
+    pub comments: Option<XPathList>,
+
+    /// Returns the entry's categories, one per node (or a single one if the result is a string).
+    pub categories: Option<XPathList>,
+    pub guid: Option<XPathList>,
+
+    /// Whether the GUID (the result of `guid` or, if unset, the entry URL) is the entry's URL.
+    #[serde(default)]
+    pub guid_is_permalink: bool,
+
+    /// XPath expressions returning the text of extra entry elements, keyed by the elements'
+    /// qualified names.
+    #[serde(default)]
+    pub extra: BTreeMap<String, XPathList>,
 }
 
 impl XPathExtractorConfig {
     pub fn resolve_relative_paths(&mut self, _config_dir: impl AsRef<Path>) {
         take(self, |this| Self {
+            xpath_version: this.xpath_version,
             entry: this.entry,
             id: this.id,
             title: this.title,
             description: this.description,
+            content: this.content,
             url: this.url,
             author: this.author,
             pub_date: this.pub_date,
             pub_date_format: this.pub_date_format,
+            relative_date_locales: this.relative_date_locales,
+            enclosure_url: this.enclosure_url,
+            enclosure_type: this.enclosure_type,
+            enclosure_length: this.enclosure_length,
+            image: this.image,
+            comments: this.comments,
+            categories: this.categories,
+            guid: this.guid,
+            guid_is_permalink: this.guid_is_permalink,
+            extra: this.extra,
         })
     }
 }
 
-#[derive(Deserialize, Debug, Clone)]
+/// Configures an extractor that uses CSS selectors (see [`CssQuery`] for the field syntax).
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct CssExtractorConfig {
+    pub entry: CssSelector,
+    pub id: CssQuery,
+    pub title: CssQuery,
+    pub description: CssQuery,
+
+    /// Returns the inner HTML of the matched element unless an attribute is given.
+    pub content: Option<CssQuery>,
+    pub url: CssQuery,
+    pub author: Option<CssQuery>,
+    pub pub_date: Option<CssQuery>,
+    pub pub_date_format: Option<DateTimeFormats>,
+
+    /// Languages of relative dates ("5 min ago") accepted if `pub_date` doesn't match the format.
+    #[serde(default)]
+    pub relative_date_locales: Vec<Locale>,
+    pub enclosure_url: Option<CssQuery>,
+    pub enclosure_type: Option<CssQuery>,
+    pub enclosure_length: Option<CssQuery>,
+    pub image: Option<CssQuery>,
+    pub comments: Option<CssQuery>,
+
+    /// Returns the entry's categories, one per matched element.
+    pub categories: Option<CssQuery>,
+    pub guid: Option<CssQuery>,
+
+    /// Whether the GUID (the result of `guid` or, if unset, the entry URL) is the entry's URL.
+    #[serde(default)]
+    pub guid_is_permalink: bool,
+
+    /// Queries returning the text of extra entry elements, keyed by the elements' qualified names.
+    #[serde(default)]
+    pub extra: BTreeMap<String, CssQuery>,
+}
+
+impl CssExtractorConfig {
+    pub fn resolve_relative_paths(&mut self, _config_dir: impl AsRef<Path>) {
+        take(self, |this| Self {
+            entry: this.entry,
+            id: this.id,
+            title: this.title,
+            description: this.description,
+            content: this.content,
+            url: this.url,
+            author: this.author,
+            pub_date: this.pub_date,
+            pub_date_format: this.pub_date_format,
+            relative_date_locales: this.relative_date_locales,
+            enclosure_url: this.enclosure_url,
+            enclosure_type: this.enclosure_type,
+            enclosure_length: this.enclosure_length,
+            image: this.image,
+            comments: this.comments,
+            categories: this.categories,
+            guid: this.guid,
+            guid_is_permalink: this.guid_is_permalink,
+            extra: this.extra,
+        })
+    }
+}
+
+/// Configures an extractor for JSON responses. The field expressions are evaluated relative to each
+/// entry (`$` being the entry value).
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct JsonExtractorConfig {
+    pub entry: JsonPath,
+    pub id: JsonPath,
+    pub title: JsonPath,
+    pub description: JsonPath,
+    pub content: Option<JsonPath>,
+    pub url: JsonPath,
+    pub author: Option<JsonPath>,
+
+    /// Returns a date string or a Unix timestamp.
+    pub pub_date: Option<JsonPath>,
+    pub pub_date_format: Option<DateTimeFormats>,
+
+    /// Languages of relative dates ("5 min ago") accepted if `pub_date` doesn't match the format.
+    #[serde(default)]
+    pub relative_date_locales: Vec<Locale>,
+    pub enclosure_url: Option<JsonPath>,
+    pub enclosure_type: Option<JsonPath>,
+    pub enclosure_length: Option<JsonPath>,
+    pub image: Option<JsonPath>,
+    pub comments: Option<JsonPath>,
+
+    /// Returns the entry's categories, one per node (arrays are flattened).
+    pub categories: Option<JsonPath>,
+    pub guid: Option<JsonPath>,
+
+    /// Whether the GUID (the result of `guid` or, if unset, the entry URL) is the entry's URL.
+    #[serde(default)]
+    pub guid_is_permalink: bool,
+
+    /// Expressions returning the text of extra entry elements, keyed by the elements' qualified
+    /// names.
+    #[serde(default)]
+    pub extra: BTreeMap<String, JsonPath>,
+}
+
+impl JsonExtractorConfig {
+    pub fn resolve_relative_paths(&mut self, _config_dir: impl AsRef<Path>) {
+        take(self, |this| Self {
+            entry: this.entry,
+            id: this.id,
+            title: this.title,
+            description: this.description,
+            content: this.content,
+            url: this.url,
+            author: this.author,
+            pub_date: this.pub_date,
+            pub_date_format: this.pub_date_format,
+            relative_date_locales: this.relative_date_locales,
+            enclosure_url: this.enclosure_url,
+            enclosure_type: this.enclosure_type,
+            enclosure_length: this.enclosure_length,
+            image: this.image,
+            comments: this.comments,
+            categories: this.categories,
+            guid: this.guid,
+            guid_is_permalink: this.guid_is_permalink,
+            extra: this.extra,
+        })
+    }
+}
+
+/// Configures an extractor that matches entries with a regular expression.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct RegexExtractorConfig {
+    /// Matches a single entry. Named capture groups (`title`, `url`, `pub_date`, etc.) provide the
+    /// entry fields.
+    pub entry: RegexPattern,
+    pub pub_date_format: Option<DateTimeFormats>,
+
+    /// Languages of relative dates ("5 min ago") accepted if `pub_date` doesn't match the format.
+    #[serde(default)]
+    pub relative_date_locales: Vec<Locale>,
+
+    /// Whether the GUID (the `guid` group or, if missing, the entry URL) is the entry's URL.
+    #[serde(default)]
+    pub guid_is_permalink: bool,
+}
+
+impl RegexExtractorConfig {
+    pub fn resolve_relative_paths(&mut self, _config_dir: impl AsRef<Path>) {
+        take(self, |this| Self {
+            entry: this.entry,
+            pub_date_format: this.pub_date_format,
+            relative_date_locales: this.relative_date_locales,
+            guid_is_permalink: this.guid_is_permalink,
+        })
+    }
+}
+
+/// Configures an extractor that re-emits the entries of an RSS or Atom feed.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct FeedExtractorConfig {}
+
+/// Configures an extractor that emits an entry whenever a region of the page changes.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct WatchExtractorConfig {
+    /// The watched region (the page body if unset).
+    pub selector: Option<CssSelector>,
+
+    #[serde(default)]
+    pub mode: WatchMode,
+
+    /// The title of the entries (the page title if unset).
+    pub title: Option<String>,
+}
+
+/// What the entries of a watched page contain.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum WatchMode {
+    /// The lines removed and added since the previous version.
+    #[default]
+    Diff,
+
+    /// The new version of the region.
+    Content,
+}
+
+/// What to do with the results of an update if some entries or pages could not be extracted, or
+/// no entries were found.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum ExtractionFailurePolicy {
+    /// Fail the update, keeping the stored entries.
+    Fail,
+
+    /// Store nothing and report the failure, without the update counting as failed.
+    Skip,
+
+    /// Store the entries that were extracted.
+    #[default]
+    Accept,
+}
+
+/// Configures an extractor that emits the URLs of a sitemap modified since the previous update.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct SitemapExtractorConfig {
+    /// The maximum number of sitemaps retrieved per update, including the one at `request_url`.
+    #[serde(default = "default_sitemap_max_sitemaps")]
+    pub max_sitemaps: usize,
+
+    /// URLs modified longer ago than this are skipped, even on the first update.
+    pub max_age: Option<Duration>,
+}
+
+fn default_sitemap_max_sitemaps() -> usize {
+    50
+}
+
+/// Configures an extractor that emits the articles described by a page's JSON-LD, microdata, or
+/// OpenGraph metadata.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct MetadataExtractorConfig {}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
 #[serde(rename_all = "kebab-case", deny_unknown_fields)]
 pub struct LuaExtractorConfig {
     pub path: PathBuf,
+
+    /// The minimum time between requests made with `feedgen.fetch`.
+    #[serde(default = "default_lua_request_delay")]
+    pub request_delay: Duration,
+
+    /// The time limit for a request made with `feedgen.fetch`.
+    #[serde(default = "default_lua_request_timeout")]
+    pub request_timeout: Duration,
+
+    /// How long the script may run during an update (including the time spent in `feedgen.fetch`).
+    #[serde(default = "default_lua_time_limit")]
+    pub time_limit: Duration,
+
+    /// The number of VM instructions the script may execute during an update. Unlimited if unset.
+    pub instruction_limit: Option<u64>,
+
+    /// Overrides the global `max_memory` of Lua VMs.
+    pub max_memory: Option<ByteSize>,
+
+    /// Arbitrary values passed to the script's `extract` function.
+    #[serde(default)]
+    pub options: BTreeMap<String, toml::Value>,
+}
+
+fn default_lua_request_delay() -> Duration {
+    Duration::from_secs(1)
+}
+
+fn default_lua_request_timeout() -> Duration {
+    Duration::from_secs(30)
+}
+
+fn default_lua_time_limit() -> Duration {
+    Duration::from_secs(300)
 }
 
 impl LuaExtractorConfig {
@@ -177,6 +1240,12 @@ impl LuaExtractorConfig {
 
         take(self, |this| Self {
             path: config_dir.join(this.path),
+            request_delay: this.request_delay,
+            request_timeout: this.request_timeout,
+            time_limit: this.time_limit,
+            instruction_limit: this.instruction_limit,
+            max_memory: this.max_memory,
+            options: this.options,
         })
     }
 }
@@ -212,6 +1281,10 @@ pub fn load(search_paths: &[PathBuf]) -> Result<Config> {
         let mut cfg: Config = toml::from_str(&contents)
             .with_context(|| anyhow!("could not load the config file `{}`", path.display()))?;
 
+        cfg.auth
+            .check()
+            .with_context(|| anyhow!("the `[auth]` section of `{}` is invalid", path.display()))?;
+
         if let Some(parent) = path.parent() {
             cfg.resolve_relative_paths(parent);
         }
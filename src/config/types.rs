@@ -2,9 +2,17 @@ use std::fmt;
 use std::sync::OnceLock;
 
 use regex_lite::{Regex, RegexBuilder};
-use serde::de::Visitor;
-use serde::{Deserialize, Deserializer};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use serde::de::{Error as _, MapAccess, SeqAccess, Visitor};
+use serde::ser::SerializeMap;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use time::format_description::well_known::{Rfc2822, Rfc3339};
 use time::format_description::{self, OwnedFormatItem};
+use time::{Date, OffsetDateTime, PrimitiveDateTime, Time, UtcOffset};
+use time_tz::{
+    timezones, OffsetDateTimeExt, OffsetResult, PrimitiveDateTimeExt, TimeZone as _, Tz,
+};
+use tracing::warn;
 
 #[derive(Debug, Clone, Copy)]
 pub struct Duration(std::time::Duration);
@@ -100,7 +108,16 @@ impl<'de> Deserialize<'de> for Duration {
             }
         }
 
-        deserializer.deserialize_str(DurationVisitor)
+        deserializer.deserialize_any(DurationVisitor)
+    }
+}
+
+impl Serialize for Duration {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_u64(self.0.as_secs())
     }
 }
 
@@ -116,12 +133,165 @@ impl From<Duration> for std::time::Duration {
     }
 }
 
+/// A size in bytes, written either as an integer or as a string with a unit (`64MB`).
+///
+/// Units are powers of 1024: `KB` and `KiB` both mean 1024 bytes.
+#[derive(Debug, Clone, Copy)]
+pub struct ByteSize(u64);
+
+impl ByteSize {
+    pub fn bytes(self) -> u64 {
+        self.0
+    }
+}
+
+impl<'de> Deserialize<'de> for ByteSize {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct ByteSizeVisitor;
+
+        impl<'de> Visitor<'de> for ByteSizeVisitor {
+            type Value = ByteSize;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                write!(formatter, "a size in bytes")
+            }
+
+            fn visit_i64<E>(self, v: i64) -> Result<Self::Value, E>
+            where
+                E: serde::de::Error,
+            {
+                self.visit_u64(v.try_into().map_err(E::custom)?)
+            }
+
+            fn visit_u64<E>(self, v: u64) -> Result<Self::Value, E>
+            where
+                E: serde::de::Error,
+            {
+                Ok(ByteSize(v))
+            }
+
+            fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+            where
+                E: serde::de::Error,
+            {
+                use serde::de::Unexpected;
+
+                static REGEXP: OnceLock<Regex> = OnceLock::new();
+
+                let regexp = REGEXP.get_or_init(|| {
+                    RegexBuilder::new(r"^(?<count>\d+)\s*(?<unit>[kmg]?)(?:i?b)?$")
+                        .case_insensitive(true)
+                        .build()
+                        .unwrap()
+                });
+                let Some(captures) = regexp.captures(v.trim()) else {
+                    return Err(E::invalid_value(Unexpected::Str(v), &"a size in bytes"));
+                };
+
+                let count = captures["count"]
+                    .parse::<u64>()
+                    .map_err(|e| E::custom(format!("could not parse the size `{v}`: {e}")))?;
+                let multiplier: u64 = match captures["unit"].to_ascii_lowercase().as_str() {
+                    "k" => 1 << 10,
+                    "m" => 1 << 20,
+                    "g" => 1 << 30,
+                    _ => 1,
+                };
+
+                count
+                    .checked_mul(multiplier)
+                    .map(ByteSize)
+                    .ok_or_else(|| E::custom(format!("size `{v}` is too large")))
+            }
+        }
+
+        deserializer.deserialize_any(ByteSizeVisitor)
+    }
+}
+
+impl Serialize for ByteSize {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_u64(self.0)
+    }
+}
+
+/// One or several addresses to bind the HTTP server to.
 #[derive(Debug, Clone)]
-pub struct DateTimeFormat(OwnedFormatItem);
+pub struct BindAddrs(Vec<String>);
+
+impl BindAddrs {
+    pub fn as_slice(&self) -> &[String] {
+        &self.0
+    }
+}
+
+impl From<Vec<String>> for BindAddrs {
+    fn from(addrs: Vec<String>) -> Self {
+        Self(addrs)
+    }
+}
+
+impl<'de> Deserialize<'de> for BindAddrs {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct BindAddrsVisitor;
+
+        impl<'de> Visitor<'de> for BindAddrsVisitor {
+            type Value = BindAddrs;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                write!(formatter, "an address or a list of addresses")
+            }
+
+            fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+            where
+                E: serde::de::Error,
+            {
+                Ok(BindAddrs(vec![v.into()]))
+            }
+
+            fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+            where
+                A: SeqAccess<'de>,
+            {
+                let mut addrs = Vec::new();
+
+                while let Some(addr) = seq.next_element()? {
+                    addrs.push(addr);
+                }
+
+                if addrs.is_empty() {
+                    return Err(A::Error::invalid_length(0, &self));
+                }
+
+                Ok(BindAddrs(addrs))
+            }
+        }
+
+        deserializer.deserialize_any(BindAddrsVisitor)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct DateTimeFormat {
+    src: String,
+    format: OwnedFormatItem,
+}
 
 impl DateTimeFormat {
-    pub fn into_inner(self) -> OwnedFormatItem {
-        self.0
+    fn new(s: &str) -> Result<Self, time::error::InvalidFormatDescription> {
+        format_description::parse_owned::<2>(s).map(|format| DateTimeFormat {
+            src: s.into(),
+            format,
+        })
     }
 }
 
@@ -143,12 +313,583 @@ impl<'de> Deserialize<'de> for DateTimeFormat {
             where
                 E: serde::de::Error,
             {
-                format_description::parse_owned::<2>(s)
-                    .map(DateTimeFormat)
-                    .map_err(E::custom)
+                DateTimeFormat::new(s).map_err(E::custom)
             }
         }
 
         deserializer.deserialize_str(DateTimeFormatVisitor)
     }
 }
+
+impl Serialize for DateTimeFormat {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(&self.src)
+    }
+}
+
+/// One or several datetime formats, written as a string or an array of strings.
+///
+/// The formats are tried in order, followed by RFC 3339 and RFC 2822.
+#[derive(Debug, Clone, Default)]
+pub struct DateTimeFormats(Vec<DateTimeFormat>);
+
+impl DateTimeFormats {
+    /// Parses the datetime with the first format that accepts it.
+    ///
+    /// If `tz` is set, datetimes without a UTC offset (or dates without a time, which are set to
+    /// midnight) are also accepted and assumed to be in that timezone.
+    ///
+    /// If no format accepts the datetime, returns the error for the first format.
+    pub fn parse(
+        &self,
+        s: &str,
+        tz: Option<Timezone>,
+    ) -> Result<OffsetDateTime, time::error::Parse> {
+        let mut first_err = None;
+
+        for fmt in &self.0 {
+            match OffsetDateTime::parse(s, &fmt.format) {
+                Ok(datetime) => return Ok(datetime),
+                Err(e) => {
+                    first_err.get_or_insert(e);
+                }
+            }
+        }
+
+        let err = match OffsetDateTime::parse(s, &Rfc3339)
+            .or_else(|_| OffsetDateTime::parse(s, &Rfc2822))
+        {
+            Ok(datetime) => return Ok(datetime),
+            Err(e) => first_err.unwrap_or(e),
+        };
+
+        let Some(tz) = tz else {
+            return Err(err);
+        };
+
+        self.0
+            .iter()
+            .find_map(|fmt| {
+                PrimitiveDateTime::parse(s, &fmt.format).ok().or_else(|| {
+                    Date::parse(s, &fmt.format)
+                        .ok()
+                        .map(|date| date.with_time(Time::MIDNIGHT))
+                })
+            })
+            .and_then(|datetime| tz.assume(datetime))
+            .ok_or(err)
+    }
+}
+
+impl<'de> Deserialize<'de> for DateTimeFormats {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct DateTimeFormatsVisitor;
+
+        impl<'de> Visitor<'de> for DateTimeFormatsVisitor {
+            type Value = DateTimeFormats;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                write!(
+                    formatter,
+                    "a datetime format or a non-empty array of datetime formats"
+                )
+            }
+
+            fn visit_str<E>(self, s: &str) -> Result<Self::Value, E>
+            where
+                E: serde::de::Error,
+            {
+                DateTimeFormat::new(s)
+                    .map(|format| DateTimeFormats(vec![format]))
+                    .map_err(E::custom)
+            }
+
+            fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+            where
+                A: SeqAccess<'de>,
+            {
+                let mut formats = vec![];
+
+                while let Some(format) = seq.next_element()? {
+                    formats.push(format);
+                }
+
+                if formats.is_empty() {
+                    return Err(A::Error::invalid_length(0, &self));
+                }
+
+                Ok(DateTimeFormats(formats))
+            }
+        }
+
+        deserializer.deserialize_any(DateTimeFormatsVisitor)
+    }
+}
+
+impl Serialize for DateTimeFormats {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match &*self.0 {
+            [format] => format.serialize(serializer),
+            formats => formats.serialize(serializer),
+        }
+    }
+}
+
+/// An IANA timezone, written as its name (e.g., `Europe/Berlin`).
+#[derive(Clone, Copy)]
+pub struct Timezone(&'static Tz);
+
+impl Timezone {
+    pub fn from_name(name: &str) -> Option<Self> {
+        timezones::get_by_name(name).map(Self)
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.0.name()
+    }
+
+    /// Converts a local datetime in this timezone to one with a UTC offset.
+    ///
+    /// A datetime repeated by a DST transition resolves to its earlier occurrence; one skipped by
+    /// a transition doesn't exist, and `None` is returned.
+    pub fn assume(&self, datetime: PrimitiveDateTime) -> Option<OffsetDateTime> {
+        match datetime.assume_timezone(self.0) {
+            OffsetResult::Some(datetime) => Some(datetime),
+
+            OffsetResult::Ambiguous(lhs, rhs) => {
+                warn!(
+                    "Datetime {datetime} is ambiguous in the timezone `{}`: \
+                        could be {lhs} or {rhs}; picking the former",
+                    self.name(),
+                );
+
+                Some(lhs)
+            }
+
+            OffsetResult::None => None,
+        }
+    }
+}
+
+impl fmt::Debug for Timezone {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Timezone").field(&self.name()).finish()
+    }
+}
+
+impl<'de> Deserialize<'de> for Timezone {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct TimezoneVisitor;
+
+        impl<'de> Visitor<'de> for TimezoneVisitor {
+            type Value = Timezone;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                write!(formatter, "an IANA timezone name")
+            }
+
+            fn visit_str<E>(self, s: &str) -> Result<Self::Value, E>
+            where
+                E: serde::de::Error,
+            {
+                Timezone::from_name(s).ok_or_else(|| E::custom(format!("unknown timezone `{s}`")))
+            }
+        }
+
+        deserializer.deserialize_str(TimezoneVisitor)
+    }
+}
+
+impl Serialize for Timezone {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(self.name())
+    }
+}
+
+/// A daily period of wall-clock time, written as `HH:MM-HH:MM` optionally followed by a timezone
+/// (e.g., `23:00-07:00 Europe/Berlin`). The times are in UTC if the timezone is omitted.
+///
+/// A period whose end precedes its start spans midnight.
+#[derive(Debug, Clone, Copy)]
+pub struct TimeWindow {
+    start: Time,
+    end: Time,
+    timezone: Option<Timezone>,
+}
+
+impl TimeWindow {
+    /// If `datetime` falls within the window, returns how long it is until the window ends.
+    pub fn remaining(&self, datetime: OffsetDateTime) -> Option<time::Duration> {
+        let local = match self.timezone {
+            Some(tz) => datetime.to_timezone(tz.0).time(),
+            None => datetime.to_offset(UtcOffset::UTC).time(),
+        };
+
+        let within = if self.start < self.end {
+            self.start <= local && local < self.end
+        } else {
+            self.start <= local || local < self.end
+        };
+
+        if !within {
+            return None;
+        }
+
+        let remaining = self.end - local;
+
+        // DST transitions within the window are not accounted for.
+        Some(if remaining.is_negative() {
+            remaining + time::Duration::DAY
+        } else {
+            remaining
+        })
+    }
+}
+
+impl fmt::Display for TimeWindow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:02}:{:02}-{:02}:{:02}",
+            self.start.hour(),
+            self.start.minute(),
+            self.end.hour(),
+            self.end.minute(),
+        )?;
+
+        if let Some(tz) = self.timezone {
+            write!(f, " {}", tz.name())?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<'de> Deserialize<'de> for TimeWindow {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct TimeWindowVisitor;
+
+        impl<'de> Visitor<'de> for TimeWindowVisitor {
+            type Value = TimeWindow;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                write!(formatter, "a time window (e.g., `23:00-07:00`)")
+            }
+
+            fn visit_str<E>(self, s: &str) -> Result<Self::Value, E>
+            where
+                E: serde::de::Error,
+            {
+                let parse_time = |time: &str| {
+                    let (hour, minute) = time.trim().split_once(':')?;
+
+                    Time::from_hms(hour.parse().ok()?, minute.parse().ok()?, 0).ok()
+                };
+
+                let (range, timezone) = match s.trim().split_once(char::is_whitespace) {
+                    Some((range, tz)) => (range, Some(tz.trim())),
+                    None => (s.trim(), None),
+                };
+
+                let (start, end) = range
+                    .split_once('-')
+                    .and_then(|(start, end)| Some((parse_time(start)?, parse_time(end)?)))
+                    .ok_or_else(|| E::custom(format!("malformed time window `{s}`")))?;
+
+                if start == end {
+                    return Err(E::custom(format!("the time window `{s}` is empty")));
+                }
+
+                let timezone = timezone
+                    .map(|tz| {
+                        Timezone::from_name(tz)
+                            .ok_or_else(|| E::custom(format!("unknown timezone `{tz}`")))
+                    })
+                    .transpose()?;
+
+                Ok(TimeWindow {
+                    start,
+                    end,
+                    timezone,
+                })
+            }
+        }
+
+        deserializer.deserialize_str(TimeWindowVisitor)
+    }
+}
+
+impl Serialize for TimeWindow {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
+
+/// A regular expression, written as a string.
+#[derive(Debug, Clone)]
+pub struct RegexPattern(Regex);
+
+impl RegexPattern {
+    pub fn as_regex(&self) -> &Regex {
+        &self.0
+    }
+}
+
+impl<'de> Deserialize<'de> for RegexPattern {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct RegexPatternVisitor;
+
+        impl<'de> Visitor<'de> for RegexPatternVisitor {
+            type Value = RegexPattern;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                write!(formatter, "a regular expression")
+            }
+
+            fn visit_str<E>(self, s: &str) -> Result<Self::Value, E>
+            where
+                E: serde::de::Error,
+            {
+                Regex::new(s).map(RegexPattern).map_err(E::custom)
+            }
+        }
+
+        deserializer.deserialize_str(RegexPatternVisitor)
+    }
+}
+
+impl Serialize for RegexPattern {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(self.0.as_str())
+    }
+}
+
+/// A rule matching a string: either a regular expression (written as a string) or a plain
+/// substring (written as `{ contains = "..." }`).
+#[derive(Debug, Clone)]
+pub enum TextMatcher {
+    Regex(Box<RegexPattern>),
+    Substring(String),
+}
+
+impl TextMatcher {
+    pub fn is_match(&self, s: &str) -> bool {
+        match self {
+            Self::Regex(pattern) => pattern.as_regex().is_match(s),
+            Self::Substring(substring) => s.contains(substring.as_str()),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for TextMatcher {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct TextMatcherVisitor;
+
+        impl<'de> Visitor<'de> for TextMatcherVisitor {
+            type Value = TextMatcher;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                write!(
+                    formatter,
+                    "a regular expression or a table with a `contains` key"
+                )
+            }
+
+            fn visit_str<E>(self, s: &str) -> Result<Self::Value, E>
+            where
+                E: serde::de::Error,
+            {
+                Regex::new(s)
+                    .map(|regex| TextMatcher::Regex(Box::new(RegexPattern(regex))))
+                    .map_err(E::custom)
+            }
+
+            fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+            where
+                A: MapAccess<'de>,
+            {
+                let mut substring = None;
+
+                while let Some(key) = map.next_key::<String>()? {
+                    match key.as_str() {
+                        "contains" if substring.is_none() => substring = Some(map.next_value()?),
+                        "contains" => return Err(A::Error::duplicate_field("contains")),
+                        _ => return Err(A::Error::unknown_field(&key, &["contains"])),
+                    }
+                }
+
+                substring
+                    .map(TextMatcher::Substring)
+                    .ok_or_else(|| A::Error::missing_field("contains"))
+            }
+        }
+
+        deserializer.deserialize_any(TextMatcherVisitor)
+    }
+}
+
+impl Serialize for TextMatcher {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match self {
+            Self::Regex(pattern) => pattern.serialize(serializer),
+
+            Self::Substring(substring) => {
+                let mut map = serializer.serialize_map(Some(1))?;
+                map.serialize_entry("contains", substring)?;
+                map.end()
+            }
+        }
+    }
+}
+
+/// A Handlebars template, checked for syntax errors when the config is loaded.
+#[derive(Debug, Clone)]
+pub struct HandlebarsTemplate(String);
+
+impl HandlebarsTemplate {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl<'de> Deserialize<'de> for HandlebarsTemplate {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct HandlebarsTemplateVisitor;
+
+        impl<'de> Visitor<'de> for HandlebarsTemplateVisitor {
+            type Value = HandlebarsTemplate;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                write!(formatter, "a Handlebars template")
+            }
+
+            fn visit_str<E>(self, s: &str) -> Result<Self::Value, E>
+            where
+                E: serde::de::Error,
+            {
+                handlebars::Template::compile(s)
+                    .map(|_| HandlebarsTemplate(s.into()))
+                    .map_err(E::custom)
+            }
+        }
+
+        deserializer.deserialize_str(HandlebarsTemplateVisitor)
+    }
+}
+
+impl Serialize for HandlebarsTemplate {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+/// HTTP request headers, written as a table of header names and values.
+#[derive(Debug, Clone, Default)]
+pub struct HttpHeaders(HeaderMap);
+
+impl HttpHeaders {
+    pub fn as_header_map(&self) -> &HeaderMap {
+        &self.0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl<'de> Deserialize<'de> for HttpHeaders {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct HttpHeadersVisitor;
+
+        impl<'de> Visitor<'de> for HttpHeadersVisitor {
+            type Value = HttpHeaders;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                write!(formatter, "a table of HTTP header names and values")
+            }
+
+            fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+            where
+                A: MapAccess<'de>,
+            {
+                let mut headers = HeaderMap::new();
+
+                while let Some((name, value)) = map.next_entry::<String, String>()? {
+                    let name = HeaderName::from_bytes(name.as_bytes()).map_err(|_| {
+                        A::Error::custom(format!("invalid HTTP header name `{name}`"))
+                    })?;
+                    let value = HeaderValue::from_str(&value).map_err(|_| {
+                        A::Error::custom(format!("invalid value of the HTTP header `{name}`"))
+                    })?;
+
+                    if headers.insert(name.clone(), value).is_some() {
+                        return Err(A::Error::custom(format!(
+                            "the HTTP header `{name}` is given more than once"
+                        )));
+                    }
+                }
+
+                Ok(HttpHeaders(headers))
+            }
+        }
+
+        deserializer.deserialize_map(HttpHeadersVisitor)
+    }
+}
+
+impl Serialize for HttpHeaders {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut map = serializer.serialize_map(Some(self.0.len()))?;
+
+        for (name, value) in &self.0 {
+            map.serialize_entry(name.as_str(), &String::from_utf8_lossy(value.as_bytes()))?;
+        }
+
+        map.end()
+    }
+}
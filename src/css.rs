@@ -0,0 +1,216 @@
+use std::fmt;
+use std::fmt::Formatter;
+use std::ops::Deref;
+
+use anyhow::{anyhow, Result};
+use scraper::{ElementRef, Selector};
+use serde::de::Visitor;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// A CSS selector, written in configs as a string.
+#[derive(Debug, Clone)]
+pub struct CssSelector {
+    src: String,
+    selector: Selector,
+}
+
+impl CssSelector {
+    pub fn new(s: String) -> Result<Self> {
+        let selector =
+            Selector::parse(&s).map_err(|e| anyhow!("could not parse the CSS selector: {e}"))?;
+
+        Ok(Self { src: s, selector })
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.src
+    }
+}
+
+impl Deref for CssSelector {
+    type Target = Selector;
+
+    fn deref(&self) -> &Selector {
+        &self.selector
+    }
+}
+
+impl<'de> Deserialize<'de> for CssSelector {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct CssSelectorVisitor;
+
+        impl<'de> Visitor<'de> for CssSelectorVisitor {
+            type Value = CssSelector;
+
+            fn expecting(&self, formatter: &mut Formatter<'_>) -> fmt::Result {
+                write!(formatter, "a CSS selector")
+            }
+
+            fn visit_string<E>(self, v: String) -> Result<Self::Value, E>
+            where
+                E: serde::de::Error,
+            {
+                CssSelector::new(v).map_err(|e| E::custom(format!("{e:#}")))
+            }
+
+            fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+            where
+                E: serde::de::Error,
+            {
+                self.visit_string(v.into())
+            }
+        }
+
+        deserializer.deserialize_string(CssSelectorVisitor)
+    }
+}
+
+impl Serialize for CssSelector {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+/// A CSS selector optionally followed by an attribute name (`h2 a @href`).
+///
+/// The selector is applied to an entry element and picks the first matching descendant; if it's
+/// omitted (`@href`), the entry element itself is used. The value of the query is the attribute
+/// of that element if the name is given, or its text otherwise.
+#[derive(Debug, Clone)]
+pub struct CssQuery {
+    src: String,
+    selector: Option<Selector>,
+    attr: Option<String>,
+}
+
+impl CssQuery {
+    pub fn new(s: String) -> Result<Self> {
+        let (selector, attr) = match s.trim_end().rsplit_once(|c: char| c.is_ascii_whitespace()) {
+            Some((selector, attr)) if attr.starts_with('@') => (selector, Some(&attr[1..])),
+            None if s.trim().starts_with('@') => ("", Some(&s.trim()[1..])),
+            _ => (s.as_str(), None),
+        };
+
+        if attr.is_some_and(str::is_empty) {
+            return Err(anyhow!("the attribute name after `@` is missing"));
+        }
+
+        let selector = if selector.trim().is_empty() {
+            if attr.is_none() {
+                return Err(anyhow!("the query is empty"));
+            }
+
+            None
+        } else {
+            Some(
+                Selector::parse(selector)
+                    .map_err(|e| anyhow!("could not parse the CSS selector: {e}"))?,
+            )
+        };
+        let attr = attr.map(str::to_owned);
+
+        Ok(Self {
+            src: s,
+            selector,
+            attr,
+        })
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.src
+    }
+
+    /// Returns the elements the query applies to.
+    fn elements<'a>(
+        &'a self,
+        element: ElementRef<'a>,
+    ) -> Box<dyn Iterator<Item = ElementRef<'a>> + 'a> {
+        match &self.selector {
+            Some(selector) => Box::new(element.select(selector)),
+            None => Box::new(std::iter::once(element)),
+        }
+    }
+
+    fn value_of(&self, element: ElementRef<'_>) -> Option<String> {
+        match &self.attr {
+            Some(attr) => element.value().attr(attr).map(str::to_owned),
+            None => Some(element.text().collect()),
+        }
+    }
+
+    /// Evaluates the query relative to `element`, returning `None` if nothing matches.
+    pub fn evaluate(&self, element: ElementRef<'_>) -> Option<String> {
+        self.elements(element)
+            .next()
+            .and_then(|element| self.value_of(element))
+    }
+
+    /// Like [`evaluate`](Self::evaluate), but returns the inner HTML of the matched element if
+    /// no attribute is given.
+    pub fn evaluate_html(&self, element: ElementRef<'_>) -> Option<String> {
+        let element = self.elements(element).next()?;
+
+        match &self.attr {
+            Some(_) => self.value_of(element),
+            None => Some(element.inner_html()),
+        }
+    }
+
+    /// Evaluates the query for every matching element.
+    pub fn evaluate_all(&self, element: ElementRef<'_>) -> Vec<String> {
+        self.elements(element)
+            .filter_map(|element| self.value_of(element))
+            .collect()
+    }
+}
+
+impl<'de> Deserialize<'de> for CssQuery {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct CssQueryVisitor;
+
+        impl<'de> Visitor<'de> for CssQueryVisitor {
+            type Value = CssQuery;
+
+            fn expecting(&self, formatter: &mut Formatter<'_>) -> fmt::Result {
+                write!(
+                    formatter,
+                    "a CSS selector optionally followed by an attribute name"
+                )
+            }
+
+            fn visit_string<E>(self, v: String) -> Result<Self::Value, E>
+            where
+                E: serde::de::Error,
+            {
+                CssQuery::new(v).map_err(|e| E::custom(format!("{e:#}")))
+            }
+
+            fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+            where
+                E: serde::de::Error,
+            {
+                self.visit_string(v.into())
+            }
+        }
+
+        deserializer.deserialize_string(CssQueryVisitor)
+    }
+}
+
+impl Serialize for CssQuery {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(self.as_str())
+    }
+}
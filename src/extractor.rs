@@ -1,37 +1,335 @@
+mod css;
+mod feed;
+mod json;
 mod lua;
+mod metadata;
+mod regex;
+mod sitemap;
+mod watch;
 mod xpath;
 
-use anyhow::Result;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::Arc;
+
+use anyhow::{bail, Result};
+use reqwest::header::HeaderMap;
 use reqwest::Url;
+use reqwest_middleware::ClientWithMiddleware;
+use serde::Serialize;
 use time::OffsetDateTime;
+use tokio_util::sync::CancellationToken;
 
-pub use lua::LuaExtractor;
-pub use xpath::XPathExtractor;
+use crate::config::{ContentKind, Timezone};
+use crate::storage::Storage;
 
-#[derive(Debug, Clone)]
+pub use css::CssExtractor;
+pub use feed::FeedExtractor;
+pub use json::JsonExtractor;
+pub use lua::{run_repl as run_lua_repl, LuaExtractor};
+pub use metadata::MetadataExtractor;
+pub use regex::RegexExtractor;
+pub use sitemap::SitemapExtractor;
+pub use watch::WatchExtractor;
+pub use xpath::{select_html as select_html_by_xpath, XPathExtractor};
+
+#[derive(Serialize, Debug, Clone)]
 pub struct Entry {
     pub id: String,
     pub title: String,
     pub description: String,
+
+    /// The full HTML body of the entry (`description` being a summary).
+    pub content: Option<String>,
+
     pub url: Url,
     pub author: Option<String>,
+
+    #[serde(with = "time::serde::rfc3339::option")]
     pub pub_date: Option<OffsetDateTime>,
+
+    pub enclosure: Option<Enclosure>,
+
+    /// A preview image.
+    pub image: Option<Url>,
+
+    /// The URL of the entry's comments page.
+    pub comments: Option<Url>,
+
+    /// The names of the categories (tags) the entry belongs to.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub categories: Vec<String>,
+
+    /// A custom GUID. If unset, the GUID is generated from the feed name and the entry id (or
+    /// is the entry URL if `guid_is_permalink` is set).
+    pub guid: Option<String>,
+
+    /// Whether the GUID is the URL of the entry.
+    pub guid_is_permalink: bool,
+
+    /// Additional elements rendered in the feed, keyed by their qualified names (e.g.,
+    /// `dc:creator`).
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub extra: BTreeMap<String, String>,
+}
+
+/// A media file attached to an entry (e.g., a podcast episode).
+#[derive(Serialize, Debug, Clone)]
+pub struct Enclosure {
+    pub url: Url,
+    pub mime_type: String,
+
+    /// The size of the file in bytes.
+    pub length: Option<u64>,
+}
+
+/// Metadata of the HTTP response the source page was retrieved from.
+#[derive(Debug, Clone)]
+pub struct Response {
+    pub status: u16,
+
+    /// The URL of the page after following redirects.
+    pub url: Url,
+
+    /// Header values keyed by lowercase header names. Repeated headers are joined with `, `.
+    pub headers: BTreeMap<String, String>,
+}
+
+impl Response {
+    pub fn new(response: &reqwest::Response) -> Self {
+        Self {
+            status: response.status().as_u16(),
+            url: response.url().clone(),
+            headers: collect_headers(response.headers()),
+        }
+    }
+
+    /// Fails if the `Content-Type` of the response is not of the `expected` kind (e.g., if an
+    /// HTML page turns out to be a PDF). Responses without a `Content-Type` are accepted.
+    pub fn check_content_type(&self, expected: ContentKind) -> Result<()> {
+        let Some(content_type) = self.headers.get("content-type") else {
+            return Ok(());
+        };
+        let mime_type = content_type
+            .split(';')
+            .next()
+            .unwrap_or_default()
+            .trim()
+            .to_ascii_lowercase();
+
+        if mime_type.is_empty() || expected.matches(&mime_type) {
+            return Ok(());
+        }
+
+        let expected = match expected {
+            ContentKind::Html => "an HTML page",
+            ContentKind::Xml => "an XML document",
+            ContentKind::Json => "a JSON document",
+            ContentKind::Any => unreachable!(),
+        };
+
+        bail!("expected {expected}, but the server returned `{mime_type}`")
+    }
+}
+
+pub fn collect_headers(header_map: &HeaderMap) -> BTreeMap<String, String> {
+    let mut headers = BTreeMap::<String, String>::new();
+
+    for (name, value) in header_map {
+        let value = String::from_utf8_lossy(value.as_bytes());
+
+        headers
+            .entry(name.as_str().into())
+            .and_modify(|existing| {
+                existing.push_str(", ");
+                existing.push_str(&value);
+            })
+            .or_insert_with(|| value.into_owned());
+    }
+
+    headers
+}
+
+/// The result of extracting a page.
+pub struct Extraction {
+    pub entries: Vec<Entry>,
+
+    /// The next page of the listing, which is fetched and extracted in turn (up to the feed's
+    /// `max_pages`).
+    pub next_page: Option<Url>,
+
+    /// Feed metadata found on the page, which takes precedence over the feed name when serving the
+    /// feed.
+    pub channel: Option<ChannelInfo>,
+
+    /// The number of entries found on the page that could not be extracted (e.g., for lacking a
+    /// required field).
+    pub skipped: usize,
+}
+
+impl Extraction {
+    pub fn with_skipped(mut self, skipped: usize) -> Self {
+        self.skipped = skipped;
+
+        self
+    }
+}
+
+impl From<Vec<Entry>> for Extraction {
+    fn from(entries: Vec<Entry>) -> Self {
+        Self {
+            entries,
+            next_page: None,
+            channel: None,
+            skipped: 0,
+        }
+    }
+}
+
+/// Feed-level metadata provided by an extractor.
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct ChannelInfo {
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub link: Option<Url>,
 }
 
 pub struct Context<'c> {
+    feed_name: Option<&'c str>,
     fetch_url: &'c Url,
+    response: Option<&'c Response>,
+    fetch_time: OffsetDateTime,
+    timezone: Option<Timezone>,
+    http_client: Option<&'c ClientWithMiddleware>,
+    cancel: Option<&'c CancellationToken>,
+    storage: Option<&'c Arc<Storage>>,
 }
 
 impl<'c> Context<'c> {
     pub fn new(fetch_url: &'c Url) -> Self {
-        Self { fetch_url }
+        Self {
+            feed_name: None,
+            fetch_url,
+            response: None,
+            fetch_time: OffsetDateTime::now_utc(),
+            timezone: None,
+            http_client: None,
+            cancel: None,
+            storage: None,
+        }
+    }
+
+    pub fn with_feed_name(mut self, feed_name: &'c str) -> Self {
+        self.feed_name = Some(feed_name);
+
+        self
+    }
+
+    /// Attaches the metadata of the response the page was retrieved from (unset when the page
+    /// was provided directly).
+    pub fn with_response(mut self, response: Option<&'c Response>) -> Self {
+        self.response = response;
+
+        self
+    }
+
+    /// Overrides the time the page was retrieved (the current time by default).
+    pub fn with_fetch_time(mut self, fetch_time: OffsetDateTime) -> Self {
+        self.fetch_time = fetch_time;
+
+        self
+    }
+
+    /// Sets the timezone of datetimes that have no UTC offset.
+    pub fn with_timezone(mut self, timezone: Option<Timezone>) -> Self {
+        self.timezone = timezone;
+
+        self
+    }
+
+    /// Lets the extractor make HTTP requests of its own (aborted when `cancel` is triggered).
+    pub fn with_http_client(
+        mut self,
+        http_client: &'c ClientWithMiddleware,
+        cancel: Option<&'c CancellationToken>,
+    ) -> Self {
+        self.http_client = Some(http_client);
+        self.cancel = cancel;
+
+        self
+    }
+
+    /// Lets the extractor persist state across updates of the feed.
+    pub fn with_storage(mut self, storage: &'c Arc<Storage>) -> Self {
+        self.storage = Some(storage);
+
+        self
+    }
+
+    /// The name of the feed being updated (unset when trying out an extractor).
+    pub fn feed_name(&self) -> Option<&'c str> {
+        self.feed_name
     }
 
     pub fn fetch_url(&self) -> &'c Url {
         self.fetch_url
     }
+
+    pub fn response(&self) -> Option<&'c Response> {
+        self.response
+    }
+
+    /// The URL relative links are resolved against: the final URL after redirects if known,
+    /// the request URL otherwise.
+    pub fn base_url(&self) -> &'c Url {
+        self.response
+            .map(|response| &response.url)
+            .unwrap_or(self.fetch_url)
+    }
+
+    /// The time the source page was retrieved (relative dates are resolved against it).
+    pub fn fetch_time(&self) -> OffsetDateTime {
+        self.fetch_time
+    }
+
+    /// The timezone of datetimes that have no UTC offset (the feed's `timezone`).
+    pub fn timezone(&self) -> Option<Timezone> {
+        self.timezone
+    }
+
+    pub fn http_client(&self) -> Option<&'c ClientWithMiddleware> {
+        self.http_client
+    }
+
+    pub fn cancel(&self) -> Option<&'c CancellationToken> {
+        self.cancel
+    }
+
+    pub fn storage(&self) -> Option<&'c Arc<Storage>> {
+        self.storage
+    }
 }
 
+/// The error an extractor fails with if the source page turns out to be a login page.
+///
+/// The fetcher then runs [`Extractor::prepare`] again and retries.
+#[derive(Debug)]
+pub struct LoginRequired;
+
+impl fmt::Display for LoginRequired {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "the source page asks to log in")
+    }
+}
+
+impl std::error::Error for LoginRequired {}
+
 pub trait Extractor {
-    fn extract(&mut self, ctx: Context<'_>, html: &str) -> Result<Vec<Entry>>;
+    /// Runs before the source page is first fetched (e.g., to log in). The context has no
+    /// response.
+    fn prepare(&mut self, _ctx: Context<'_>) -> Result<()> {
+        Ok(())
+    }
+
+    fn extract(&mut self, ctx: Context<'_>, html: &str) -> Result<Extraction>;
 }
@@ -0,0 +1,219 @@
+use std::collections::BTreeMap;
+
+use anyhow::Result;
+use scraper::{ElementRef, Html};
+use tracing::warn;
+
+use crate::config::{self, DateTimeFormats};
+use crate::css::{CssQuery, CssSelector};
+use crate::reldate::{self, Locale};
+
+use super::{Context as ExtractorContext, Enclosure, Entry, Extraction, Extractor};
+
+const DEFAULT_ENCLOSURE_TYPE: &str = "application/octet-stream";
+
+pub struct CssExtractor {
+    entry: CssSelector,
+    id: CssQuery,
+    title: CssQuery,
+    description: CssQuery,
+    content: Option<CssQuery>,
+    url: CssQuery,
+    author: Option<CssQuery>,
+    pub_date: Option<(CssQuery, DateTimeFormats)>,
+    relative_date_locales: Vec<Locale>,
+    enclosure: Option<EnclosureQueries>,
+    image: Option<CssQuery>,
+    comments: Option<CssQuery>,
+    categories: Option<CssQuery>,
+    guid: Option<CssQuery>,
+    guid_is_permalink: bool,
+    extra: BTreeMap<String, CssQuery>,
+}
+
+struct EnclosureQueries {
+    url: CssQuery,
+    mime_type: Option<CssQuery>,
+    length: Option<CssQuery>,
+}
+
+impl CssExtractor {
+    pub fn from_cfg(cfg: &config::CssExtractorConfig) -> Self {
+        Self {
+            entry: cfg.entry.clone(),
+            id: cfg.id.clone(),
+            title: cfg.title.clone(),
+            description: cfg.description.clone(),
+            content: cfg.content.clone(),
+            url: cfg.url.clone(),
+            author: cfg.author.clone(),
+            pub_date: cfg
+                .pub_date
+                .clone()
+                .map(|query| (query, cfg.pub_date_format.clone().unwrap_or_default())),
+            relative_date_locales: cfg.relative_date_locales.clone(),
+            enclosure: cfg.enclosure_url.clone().map(|url| EnclosureQueries {
+                url,
+                mime_type: cfg.enclosure_type.clone(),
+                length: cfg.enclosure_length.clone(),
+            }),
+            image: cfg.image.clone(),
+            comments: cfg.comments.clone(),
+            categories: cfg.categories.clone(),
+            guid: cfg.guid.clone(),
+            guid_is_permalink: cfg.guid_is_permalink,
+            extra: cfg.extra.clone(),
+        }
+    }
+
+    fn extract_entry(
+        &self,
+        ctx: &ExtractorContext<'_>,
+        idx: usize,
+        entry: ElementRef<'_>,
+    ) -> Option<Entry> {
+        let find_one = |query: &CssQuery, what: &str, allow_empty: bool| {
+            let Some(s) = query.evaluate(entry) else {
+                warn!(
+                    "The {what} query `{}` matched nothing in entry #{idx}",
+                    query.as_str()
+                );
+
+                return None;
+            };
+            let s = s.trim();
+
+            if s.is_empty() && !allow_empty {
+                warn!("The {what} query returned an empty string for entry #{idx}");
+
+                None
+            } else {
+                Some(s.to_owned())
+            }
+        };
+
+        let find_optional = |query: Option<&CssQuery>| {
+            query
+                .and_then(|query| query.evaluate(entry))
+                .map(|s| s.trim().to_owned())
+                .filter(|s| !s.is_empty())
+        };
+
+        let find_url = |query: Option<&CssQuery>, what: &str| {
+            let url = find_optional(query)?;
+
+            ctx.base_url()
+                .join(&url)
+                .inspect_err(|e| {
+                    warn!("The {what} URL for entry #{idx} could not be parsed: {e:#}")
+                })
+                .ok()
+        };
+
+        let id = find_one(&self.id, "id", false)?;
+        let title = find_one(&self.title, "title", false)?;
+        let description = find_one(&self.description, "description", true)?;
+        let content = self
+            .content
+            .as_ref()
+            .and_then(|query| query.evaluate_html(entry))
+            .filter(|content| !content.trim().is_empty());
+        let url = find_one(&self.url, "url", false)?;
+        let url = ctx
+            .base_url()
+            .join(&url)
+            .inspect_err(|e| {
+                warn!(
+                    "The result of evaluating the url query for entry #{idx} \
+                        could not be parsed as an URL: {e:#}",
+                )
+            })
+            .ok()?;
+        let author = find_optional(self.author.as_ref());
+
+        let pub_date = self.pub_date.as_ref().and_then(|(query, fmt)| {
+            let s = find_one(query, "pub_date", false)?;
+
+            fmt.parse(&s, ctx.timezone())
+                .or_else(|e| {
+                    reldate::parse(&s, ctx.fetch_time(), &self.relative_date_locales).ok_or(e)
+                })
+                .inspect_err(|e| warn!("The date `{s}` could not be parsed: {e:#}"))
+                .ok()
+        });
+
+        let enclosure = self.enclosure.as_ref().and_then(|queries| {
+            let url = find_url(Some(&queries.url), "enclosure")?;
+            let mime_type = match &queries.mime_type {
+                Some(query) => find_one(query, "enclosure_type", false)?,
+                None => DEFAULT_ENCLOSURE_TYPE.into(),
+            };
+            let length = find_optional(queries.length.as_ref()).and_then(|s| {
+                s.parse()
+                    .inspect_err(|e| warn!("The enclosure length `{s}` could not be parsed: {e:#}"))
+                    .ok()
+            });
+
+            Some(Enclosure {
+                url,
+                mime_type,
+                length,
+            })
+        });
+
+        let categories = self
+            .categories
+            .as_ref()
+            .map(|query| {
+                query
+                    .evaluate_all(entry)
+                    .into_iter()
+                    .map(|s| s.trim().to_owned())
+                    .filter(|s| !s.is_empty())
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let extra = self
+            .extra
+            .iter()
+            .filter_map(|(name, query)| {
+                find_one(query, name, false).map(|value| (name.clone(), value))
+            })
+            .collect();
+
+        Some(Entry {
+            id,
+            title,
+            description,
+            content,
+            url,
+            author,
+            pub_date,
+            enclosure,
+            image: find_url(self.image.as_ref(), "image"),
+            comments: find_url(self.comments.as_ref(), "comments"),
+            categories,
+            guid: find_optional(self.guid.as_ref()),
+            guid_is_permalink: self.guid_is_permalink,
+            extra,
+        })
+    }
+}
+
+impl Extractor for CssExtractor {
+    fn extract(&mut self, ctx: ExtractorContext<'_>, html: &str) -> Result<Extraction> {
+        let html = Html::parse_document(html);
+
+        let found = html.select(&self.entry).collect::<Vec<_>>();
+        let found_count = found.len();
+        let entries = found
+            .into_iter()
+            .enumerate()
+            .filter_map(|(idx, entry)| self.extract_entry(&ctx, idx + 1, entry))
+            .collect::<Vec<_>>();
+        let skipped = found_count - entries.len();
+
+        Ok(Extraction::from(entries).with_skipped(skipped))
+    }
+}
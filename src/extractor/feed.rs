@@ -0,0 +1,253 @@
+use anyhow::{anyhow, Result};
+use atom_syndication::FixedDateTime;
+use reqwest::Url;
+use time::format_description::well_known::{Rfc2822, Rfc3339};
+use time::OffsetDateTime;
+use tracing::warn;
+
+use crate::config;
+
+use super::{ChannelInfo, Context as ExtractorContext, Enclosure, Entry, Extraction, Extractor};
+
+const DEFAULT_ENCLOSURE_TYPE: &str = "application/octet-stream";
+
+fn non_empty(s: &str) -> Option<&str> {
+    Some(s.trim()).filter(|s| !s.is_empty())
+}
+
+fn join_url(ctx: &ExtractorContext<'_>, url: &str, what: &str, idx: usize) -> Option<Url> {
+    ctx.base_url()
+        .join(url)
+        .inspect_err(|e| warn!("The {what} URL for entry #{idx} could not be parsed: {e:#}"))
+        .ok()
+}
+
+fn parse_length(length: &str) -> Option<u64> {
+    length
+        .trim()
+        .parse()
+        .inspect_err(|e| warn!("The enclosure length `{length}` could not be parsed: {e:#}"))
+        .ok()
+}
+
+fn convert_rss_item(ctx: &ExtractorContext<'_>, idx: usize, item: &rss::Item) -> Option<Entry> {
+    let Some(url) = item.link().and_then(non_empty) else {
+        warn!("Entry #{idx} has no link");
+
+        return None;
+    };
+    let url = join_url(ctx, url, "entry", idx)?;
+
+    let Some(title) = item.title().and_then(non_empty) else {
+        warn!("Entry #{idx} has no title");
+
+        return None;
+    };
+
+    let guid = item.guid().filter(|guid| !guid.value().trim().is_empty());
+
+    let pub_date = item.pub_date().and_then(|s| {
+        OffsetDateTime::parse(s.trim(), &Rfc2822)
+            .inspect_err(|e| warn!("The date `{s}` could not be parsed: {e:#}"))
+            .ok()
+    });
+
+    let author = item.author().and_then(non_empty).or_else(|| {
+        item.dublin_core_ext()
+            .and_then(|dc| dc.creators().first())
+            .and_then(|creator| non_empty(creator))
+    });
+
+    let enclosure = item.enclosure().and_then(|enclosure| {
+        Some(Enclosure {
+            url: join_url(ctx, enclosure.url(), "enclosure", idx)?,
+            mime_type: non_empty(enclosure.mime_type())
+                .unwrap_or(DEFAULT_ENCLOSURE_TYPE)
+                .into(),
+            length: parse_length(enclosure.length()).filter(|&length| length > 0),
+        })
+    });
+
+    Some(Entry {
+        id: guid.map_or(url.as_str(), |guid| guid.value()).into(),
+        title: title.into(),
+        description: item.description().unwrap_or_default().into(),
+        content: item.content().and_then(non_empty).map(Into::into),
+        url,
+        author: author.map(Into::into),
+        pub_date,
+        enclosure,
+        image: None,
+        comments: item
+            .comments()
+            .and_then(non_empty)
+            .and_then(|comments| join_url(ctx, comments, "comments", idx)),
+        categories: item
+            .categories()
+            .iter()
+            .filter_map(|category| non_empty(category.name()))
+            .map(Into::into)
+            .collect(),
+        guid: guid.map(|guid| guid.value().into()),
+        guid_is_permalink: guid.is_some_and(|guid| guid.is_permalink()),
+        extra: Default::default(),
+    })
+}
+
+fn extract_rss(ctx: &ExtractorContext<'_>, channel: rss::Channel) -> Extraction {
+    let entries = channel
+        .items()
+        .iter()
+        .enumerate()
+        .filter_map(|(idx, item)| convert_rss_item(ctx, idx + 1, item))
+        .collect::<Vec<_>>();
+    let skipped = channel.items().len() - entries.len();
+
+    Extraction {
+        entries,
+        next_page: None,
+        channel: Some(ChannelInfo {
+            title: non_empty(channel.title()).map(Into::into),
+            description: non_empty(channel.description()).map(Into::into),
+            link: non_empty(channel.link()).and_then(|link| ctx.base_url().join(link).ok()),
+        }),
+        skipped,
+    }
+}
+
+fn to_offset_date_time(datetime: &FixedDateTime) -> Option<OffsetDateTime> {
+    OffsetDateTime::parse(&datetime.to_rfc3339(), &Rfc3339)
+        .inspect_err(|e| warn!("The date `{datetime}` could not be converted: {e:#}"))
+        .ok()
+}
+
+fn find_atom_link<'a>(links: &'a [atom_syndication::Link], rel: &str) -> Option<&'a str> {
+    links
+        .iter()
+        .find(|link| link.rel() == rel)
+        .map(|link| link.href())
+}
+
+fn convert_atom_entry(
+    ctx: &ExtractorContext<'_>,
+    idx: usize,
+    entry: &atom_syndication::Entry,
+) -> Option<Entry> {
+    let Some(url) = find_atom_link(entry.links(), "alternate")
+        .or_else(|| entry.links().first().map(|link| link.href()))
+        .and_then(non_empty)
+    else {
+        warn!("Entry #{idx} has no link");
+
+        return None;
+    };
+    let url = join_url(ctx, url, "entry", idx)?;
+
+    let Some(title) = non_empty(&entry.title().value) else {
+        warn!("Entry #{idx} has no title");
+
+        return None;
+    };
+
+    let enclosure = entry
+        .links()
+        .iter()
+        .find(|link| link.rel() == "enclosure")
+        .and_then(|link| {
+            Some(Enclosure {
+                url: join_url(ctx, link.href(), "enclosure", idx)?,
+                mime_type: link
+                    .mime_type()
+                    .and_then(non_empty)
+                    .unwrap_or(DEFAULT_ENCLOSURE_TYPE)
+                    .into(),
+                length: link.length().and_then(parse_length),
+            })
+        });
+
+    Some(Entry {
+        id: non_empty(entry.id()).unwrap_or(url.as_str()).into(),
+        title: title.into(),
+        description: entry
+            .summary()
+            .map(|summary| summary.value.clone())
+            .unwrap_or_default(),
+        content: entry
+            .content()
+            .and_then(|content| content.value())
+            .and_then(non_empty)
+            .map(Into::into),
+        url,
+        author: entry
+            .authors()
+            .first()
+            .and_then(|author| non_empty(author.name()))
+            .map(Into::into),
+        pub_date: to_offset_date_time(entry.published().unwrap_or(entry.updated())),
+        enclosure,
+        image: None,
+        comments: find_atom_link(entry.links(), "replies")
+            .and_then(|comments| join_url(ctx, comments, "comments", idx)),
+        categories: entry
+            .categories()
+            .iter()
+            .filter_map(|category| non_empty(category.label().unwrap_or(category.term())))
+            .map(Into::into)
+            .collect(),
+        guid: non_empty(entry.id()).map(Into::into),
+        guid_is_permalink: false,
+        extra: Default::default(),
+    })
+}
+
+fn extract_atom(ctx: &ExtractorContext<'_>, feed: atom_syndication::Feed) -> Extraction {
+    let entries = feed
+        .entries()
+        .iter()
+        .enumerate()
+        .filter_map(|(idx, entry)| convert_atom_entry(ctx, idx + 1, entry))
+        .collect::<Vec<_>>();
+    let skipped = feed.entries().len() - entries.len();
+
+    Extraction {
+        entries,
+        next_page: None,
+        channel: Some(ChannelInfo {
+            title: non_empty(&feed.title().value).map(Into::into),
+            description: feed
+                .subtitle()
+                .and_then(|subtitle| non_empty(&subtitle.value))
+                .map(Into::into),
+            link: find_atom_link(feed.links(), "alternate")
+                .and_then(|link| ctx.base_url().join(link).ok()),
+        }),
+        skipped,
+    }
+}
+
+/// Re-emits entries of an existing RSS or Atom feed.
+pub struct FeedExtractor;
+
+impl FeedExtractor {
+    pub fn from_cfg(_cfg: &config::FeedExtractorConfig) -> Self {
+        Self
+    }
+}
+
+impl Extractor for FeedExtractor {
+    fn extract(&mut self, ctx: ExtractorContext<'_>, body: &str) -> Result<Extraction> {
+        let rss_err = match body.parse::<rss::Channel>() {
+            Ok(channel) => return Ok(extract_rss(&ctx, channel)),
+            Err(e) => e,
+        };
+
+        let atom_err = match body.parse::<atom_syndication::Feed>() {
+            Ok(feed) => return Ok(extract_atom(&ctx, feed)),
+            Err(e) => e,
+        };
+
+        Err(anyhow!(
+            "could not parse the page as a feed (as RSS: {rss_err}; as Atom: {atom_err})"
+        ))
+    }
+}
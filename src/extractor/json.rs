@@ -0,0 +1,256 @@
+use std::collections::BTreeMap;
+
+use anyhow::{Context as _, Result};
+use serde_json::Value;
+use time::OffsetDateTime;
+use tracing::warn;
+
+use crate::config::{self, DateTimeFormats};
+use crate::jsonpath::JsonPath;
+use crate::reldate::{self, Locale};
+
+use super::{Context as ExtractorContext, Enclosure, Entry, Extraction, Extractor};
+
+const DEFAULT_ENCLOSURE_TYPE: &str = "application/octet-stream";
+
+/// Converts a scalar JSON value to a string. Returns `None` for nulls, arrays, and objects.
+fn value_to_string(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        Value::Bool(b) => Some(b.to_string()),
+        Value::Null | Value::Array(_) | Value::Object(_) => None,
+    }
+}
+
+pub struct JsonExtractor {
+    entry: JsonPath,
+    id: JsonPath,
+    title: JsonPath,
+    description: JsonPath,
+    content: Option<JsonPath>,
+    url: JsonPath,
+    author: Option<JsonPath>,
+    pub_date: Option<(JsonPath, DateTimeFormats)>,
+    relative_date_locales: Vec<Locale>,
+    enclosure: Option<EnclosurePaths>,
+    image: Option<JsonPath>,
+    comments: Option<JsonPath>,
+    categories: Option<JsonPath>,
+    guid: Option<JsonPath>,
+    guid_is_permalink: bool,
+    extra: BTreeMap<String, JsonPath>,
+}
+
+struct EnclosurePaths {
+    url: JsonPath,
+    mime_type: Option<JsonPath>,
+    length: Option<JsonPath>,
+}
+
+impl JsonExtractor {
+    pub fn from_cfg(cfg: &config::JsonExtractorConfig) -> Self {
+        Self {
+            entry: cfg.entry.clone(),
+            id: cfg.id.clone(),
+            title: cfg.title.clone(),
+            description: cfg.description.clone(),
+            content: cfg.content.clone(),
+            url: cfg.url.clone(),
+            author: cfg.author.clone(),
+            pub_date: cfg
+                .pub_date
+                .clone()
+                .map(|path| (path, cfg.pub_date_format.clone().unwrap_or_default())),
+            relative_date_locales: cfg.relative_date_locales.clone(),
+            enclosure: cfg.enclosure_url.clone().map(|url| EnclosurePaths {
+                url,
+                mime_type: cfg.enclosure_type.clone(),
+                length: cfg.enclosure_length.clone(),
+            }),
+            image: cfg.image.clone(),
+            comments: cfg.comments.clone(),
+            categories: cfg.categories.clone(),
+            guid: cfg.guid.clone(),
+            guid_is_permalink: cfg.guid_is_permalink,
+            extra: cfg.extra.clone(),
+        }
+    }
+
+    fn extract_entry(
+        &self,
+        ctx: &ExtractorContext<'_>,
+        idx: usize,
+        entry: &Value,
+    ) -> Option<Entry> {
+        let find_one = |path: &JsonPath, what: &str, allow_empty: bool| {
+            let Some(value) = path.query(entry).into_iter().next() else {
+                warn!(
+                    "The {what} JSONPath expression `{}` matched nothing in entry #{idx}",
+                    path.as_str(),
+                );
+
+                return None;
+            };
+
+            let Some(s) = value_to_string(value) else {
+                warn!(
+                    "The {what} JSONPath expression returned a non-scalar value for entry #{idx}"
+                );
+
+                return None;
+            };
+
+            if s.is_empty() && !allow_empty {
+                warn!("The {what} JSONPath expression returned an empty string for entry #{idx}");
+
+                None
+            } else {
+                Some(s)
+            }
+        };
+
+        let find_optional = |path: Option<&JsonPath>| {
+            path.and_then(|path| path.query(entry).into_iter().next())
+                .and_then(value_to_string)
+                .filter(|s| !s.is_empty())
+        };
+
+        let find_url = |path: Option<&JsonPath>, what: &str| {
+            let url = find_optional(path)?;
+
+            ctx.base_url()
+                .join(&url)
+                .inspect_err(|e| {
+                    warn!("The {what} URL for entry #{idx} could not be parsed: {e:#}")
+                })
+                .ok()
+        };
+
+        let id = find_one(&self.id, "id", false)?;
+        let title = find_one(&self.title, "title", false)?;
+        let description = find_one(&self.description, "description", true)?;
+        let content = find_optional(self.content.as_ref());
+        let url = find_one(&self.url, "url", false)?;
+        let url = ctx
+            .base_url()
+            .join(&url)
+            .inspect_err(|e| {
+                warn!(
+                    "The result of evaluating the url JSONPath expression for entry #{idx} \
+                        could not be parsed as an URL: {e:#}",
+                )
+            })
+            .ok()?;
+        let author = find_optional(self.author.as_ref());
+
+        // numbers are Unix timestamps.
+        let pub_date = self.pub_date.as_ref().and_then(|(path, fmt)| {
+            if let Some(Value::Number(n)) = path.query(entry).into_iter().next() {
+                return n
+                    .as_i64()
+                    .or_else(|| n.as_f64().map(|n| n as i64))
+                    .and_then(|n| OffsetDateTime::from_unix_timestamp(n).ok())
+                    .or_else(|| {
+                        warn!("The timestamp `{n}` is out of range");
+
+                        None
+                    });
+            }
+
+            let s = find_one(path, "pub_date", false)?;
+
+            fmt.parse(&s, ctx.timezone())
+                .or_else(|e| {
+                    reldate::parse(&s, ctx.fetch_time(), &self.relative_date_locales).ok_or(e)
+                })
+                .inspect_err(|e| warn!("The date `{s}` could not be parsed: {e:#}"))
+                .ok()
+        });
+
+        let enclosure = self.enclosure.as_ref().and_then(|paths| {
+            let url = find_url(Some(&paths.url), "enclosure")?;
+            let mime_type = match &paths.mime_type {
+                Some(path) => find_one(path, "enclosure_type", false)?,
+                None => DEFAULT_ENCLOSURE_TYPE.into(),
+            };
+            let length = find_optional(paths.length.as_ref()).and_then(|s| {
+                s.trim()
+                    .parse()
+                    .inspect_err(|e| warn!("The enclosure length `{s}` could not be parsed: {e:#}"))
+                    .ok()
+            });
+
+            Some(Enclosure {
+                url,
+                mime_type,
+                length,
+            })
+        });
+
+        // arrays of categories are flattened.
+        let categories = self
+            .categories
+            .as_ref()
+            .map(|path| {
+                path.query(entry)
+                    .into_iter()
+                    .flat_map(|value| match value {
+                        Value::Array(values) => values.iter().collect::<Vec<_>>(),
+                        value => vec![value],
+                    })
+                    .filter_map(value_to_string)
+                    .map(|s| s.trim().to_owned())
+                    .filter(|s| !s.is_empty())
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let extra = self
+            .extra
+            .iter()
+            .filter_map(|(name, path)| {
+                find_one(path, name, false).map(|value| (name.clone(), value))
+            })
+            .collect();
+
+        Some(Entry {
+            id,
+            title,
+            description,
+            content,
+            url,
+            author,
+            pub_date,
+            enclosure,
+            image: find_url(self.image.as_ref(), "image"),
+            comments: find_url(self.comments.as_ref(), "comments"),
+            categories,
+            guid: find_optional(self.guid.as_ref()),
+            guid_is_permalink: self.guid_is_permalink,
+            extra,
+        })
+    }
+}
+
+impl Extractor for JsonExtractor {
+    fn extract(&mut self, ctx: ExtractorContext<'_>, body: &str) -> Result<Extraction> {
+        let body: Value = serde_json::from_str(body).context("could not parse the page as JSON")?;
+
+        // an array of entries may be returned as a single node.
+        let entries: Vec<_> = match &*self.entry.query(&body) {
+            [Value::Array(entries)] => entries.iter().collect(),
+            entries => entries.to_vec(),
+        };
+
+        let found_count = entries.len();
+        let entries = entries
+            .into_iter()
+            .enumerate()
+            .filter_map(|(idx, entry)| self.extract_entry(&ctx, idx + 1, entry))
+            .collect::<Vec<_>>();
+        let skipped = found_count - entries.len();
+
+        Ok(Extraction::from(entries).with_skipped(skipped))
+    }
+}
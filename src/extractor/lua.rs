@@ -1,84 +1,523 @@
 mod api;
+mod date;
+mod digest;
+mod encoding;
+mod escape;
+mod http;
+mod kv;
+mod limits;
+mod repl;
+mod require;
+mod text;
+mod traceback;
 mod types;
+mod url;
+mod util;
+mod xml;
 
-use anyhow::{anyhow, Context, Result};
-use mlua::{ChunkMode, Function, Lua, LuaOptions, RegistryKey, StdLib};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, bail, Context, Result};
+use mlua::{
+    ChunkMode, Function, IntoLua, Lua, LuaOptions, RegistryKey, Result as LuaResult, StdLib,
+    Table as LuaTable, Value as LuaValue,
+};
 use tracing::{debug, warn};
 
 use crate::config;
 
-use self::api::add_feedgen_api;
-use self::types::{Buffer, LuaEntries};
+pub use self::repl::run_repl;
+
+use self::api::{add_feedgen_api, LoginRequested};
+use self::date::{make_offset_table, DefaultTimezone, FetchTime};
+use self::http::HttpState;
+use self::kv::KvState;
+use self::limits::Limits;
+use self::require::make_require;
+use self::traceback::Traceback;
+use self::types::{Buffer, LuaChannelInfo, LuaEntries, SelectorCache, Stringified};
+use self::url::LuaUrl;
+
+use super::{ChannelInfo, Enclosure, Entry, Extraction, Extractor, LoginRequired};
+
+#[cfg(not(any(feature = "lua54", feature = "luajit", feature = "luau")))]
+compile_error!("one of the features `lua54`, `luajit`, or `luau` must be enabled");
+
+#[cfg(all(feature = "lua54", feature = "luajit"))]
+compile_error!(
+    "the features `lua54` and `luajit` are mutually exclusive \
+        (use `--no-default-features` to disable `lua54`)"
+);
+#[cfg(all(feature = "lua54", feature = "luau"))]
+compile_error!(
+    "the features `lua54` and `luau` are mutually exclusive \
+        (use `--no-default-features` to disable `lua54`)"
+);
+#[cfg(all(feature = "luajit", feature = "luau"))]
+compile_error!("the features `luajit` and `luau` are mutually exclusive");
 
-use super::{Entry, Extractor};
+/// The Lua runtime Feedgen was built with.
+#[cfg(feature = "lua54")]
+const RUNTIME: config::LuaRuntime = config::LuaRuntime::Lua54;
+#[cfg(feature = "luajit")]
+const RUNTIME: config::LuaRuntime = config::LuaRuntime::LuaJit;
+#[cfg(feature = "luau")]
+const RUNTIME: config::LuaRuntime = config::LuaRuntime::Luau;
 
-fn make_vm() -> Result<Lua> {
+fn make_vm(lua_cfg: &config::LuaConfig) -> Result<Lua> {
+    if let Some(runtime) = lua_cfg.runtime.filter(|&runtime| runtime != RUNTIME) {
+        bail!(
+            "the scripts are written for `{}`, but Feedgen was built with `{}` \
+                (see the cargo features `lua54`, `luajit`, and `luau`)",
+            runtime.name(),
+            RUNTIME.name(),
+        );
+    }
+
+    // LuaJIT provides coroutines as part of the base library and has no `utf8`.
+    #[cfg(feature = "luajit")]
+    let lua_libs = StdLib::TABLE | StdLib::STRING | StdLib::MATH;
+    #[cfg(not(feature = "luajit"))]
     let lua_libs = StdLib::COROUTINE | StdLib::TABLE | StdLib::STRING | StdLib::UTF8 | StdLib::MATH;
     let lua = Lua::new_with(lua_libs, LuaOptions::new().catch_rust_panics(false))?;
     add_feedgen_api(&lua).context("could not register the Feedgen API")?;
+    lua.set_app_data(SelectorCache::default());
+    traceback::install(&lua).context("could not set up Lua traceback capturing")?;
+
+    let require = lua
+        .create_function(make_require(lua_cfg.path.clone()))
+        .context("could not create a function `require`")?;
+    lua.globals()
+        .set("require", require)
+        .context("could not register `require`")?;
+
+    // makes the libraries and the builtin metatables read-only, and gives scripts their own
+    // globals, so that they can't tamper with what the other scripts on the VM see.
+    #[cfg(feature = "luau")]
+    lua.sandbox(true)
+        .context("could not enable the Luau sandbox")?;
 
     Ok(lua)
 }
 
+fn toml_to_lua<'lua>(lua: &'lua Lua, value: &toml::Value) -> LuaResult<LuaValue<'lua>> {
+    Ok(match value {
+        toml::Value::String(s) => s.as_str().into_lua(lua)?,
+        toml::Value::Integer(i) => LuaValue::Integer(*i),
+        toml::Value::Float(f) => LuaValue::Number(*f),
+        toml::Value::Boolean(b) => LuaValue::Boolean(*b),
+        toml::Value::Datetime(dt) => dt.to_string().into_lua(lua)?,
+
+        toml::Value::Array(values) => {
+            let tbl = lua.create_table()?;
+
+            for value in values {
+                tbl.push(toml_to_lua(lua, value)?)?;
+            }
+
+            LuaValue::Table(tbl)
+        }
+
+        toml::Value::Table(values) => {
+            let tbl = lua.create_table()?;
+
+            for (key, value) in values {
+                tbl.set(key.as_str(), toml_to_lua(lua, value)?)?;
+            }
+
+            LuaValue::Table(tbl)
+        }
+    })
+}
+
+/// Runs a shared library script, storing its return value in a global named after the file.
+fn preload(lua: &Lua, path: &Path) -> Result<()> {
+    let name = path
+        .file_stem()
+        .and_then(|name| name.to_str())
+        .ok_or_else(|| anyhow!("cannot derive a global name from `{}`", path.display()))?;
+
+    debug!("Preloading a Lua script `{}` as `{name}`", path.display());
+    let value: LuaValue<'_> = lua
+        .load(path)
+        .set_mode(ChunkMode::Text)
+        .call(())
+        .with_context(|| {
+            anyhow!(
+                "could not run the preloaded Lua script at `{}`",
+                path.display()
+            )
+        })?;
+
+    if !value.is_nil() {
+        lua.globals()
+            .set(name, value)
+            .with_context(|| anyhow!("could not set the global `{name}`"))?;
+    }
+
+    Ok(())
+}
+
+/// Converts the `options` of the extractor config to a Lua table.
+fn make_options<'lua>(
+    lua: &'lua Lua,
+    options: &BTreeMap<String, toml::Value>,
+) -> LuaResult<LuaTable<'lua>> {
+    let tbl = lua.create_table()?;
+
+    for (key, value) in options {
+        tbl.set(key.as_str(), toml_to_lua(lua, value)?)?;
+    }
+
+    Ok(tbl)
+}
+
+/// Finds the `extract` and `prepare` functions of a script given the value the script returned.
+///
+/// A script may either define global `extract` and (optionally) `prepare` functions or return a
+/// module table with the fields `extract` and, optionally, `prepare` and `init`, which is called
+/// with the extractor options.
+fn find_functions<'lua>(
+    lua: &'lua Lua,
+    module: LuaValue<'lua>,
+    options: &BTreeMap<String, toml::Value>,
+) -> Result<(Function<'lua>, Option<Function<'lua>>)> {
+    let LuaValue::Table(module) = module else {
+        let globals = lua.globals();
+        let extract = globals
+            .get("extract")
+            .context("found no suitable `extract` function")?;
+        let prepare = globals
+            .get("prepare")
+            .context("the global `prepare` is not a function")?;
+
+        return Ok((extract, prepare));
+    };
+
+    let extract: Function<'_> = module
+        .get("extract")
+        .context("the module returned by the script has no suitable `extract` function")?;
+    let prepare: Option<Function<'_>> = module
+        .get("prepare")
+        .context("the `prepare` field of the module returned by the script is not a function")?;
+    let init: Option<Function<'_>> = module
+        .get("init")
+        .context("the `init` field of the module returned by the script is not a function")?;
+
+    if let Some(init) = init {
+        let options = make_options(lua, options).context("could not create the options table")?;
+        init.call::<_, ()>(options)
+            .context("running the `init` function failed")?;
+    }
+
+    Ok((extract, prepare))
+}
+
+/// Creates the context table passed to the `extract` and `prepare` functions.
+fn make_context<'lua>(
+    lua: &'lua Lua,
+    options: &BTreeMap<String, toml::Value>,
+    ctx: &super::Context<'_>,
+) -> LuaResult<LuaTable<'lua>> {
+    let tbl = lua.create_table()?;
+    tbl.set("feedName", ctx.feed_name())?;
+    tbl.set("url", LuaUrl::from(ctx.fetch_url().clone()))?;
+    tbl.set("fetchTime", make_offset_table(lua, ctx.fetch_time())?)?;
+
+    if let Some(response) = ctx.response() {
+        let response_tbl = lua.create_table()?;
+        response_tbl.set("status", response.status)?;
+        response_tbl.set("url", LuaUrl::from(response.url.clone()))?;
+        response_tbl.set("headers", response.headers.clone())?;
+        tbl.set("response", response_tbl)?;
+    }
+
+    tbl.set("options", make_options(lua, options)?)?;
+    tbl.set("channel", lua.create_table()?)?;
+
+    Ok(tbl)
+}
+
 pub struct LuaExtractor {
     lua: Lua,
+    path: PathBuf,
     extract_key: RegistryKey,
+    prepare_key: Option<RegistryKey>,
+    limits: Limits,
+    options: BTreeMap<String, toml::Value>,
 }
 
 impl LuaExtractor {
-    pub fn from_cfg(cfg: &config::LuaExtractorConfig) -> Result<Self> {
+    pub fn from_cfg(cfg: &config::LuaExtractorConfig, lua_cfg: &config::LuaConfig) -> Result<Self> {
         debug!("Loading a Lua extractor script: {}", cfg.path.display());
 
-        let lua = make_vm().context("could not set up a Lua VM")?;
-        lua.load(cfg.path.as_path())
-            .set_mode(ChunkMode::Text)
-            .exec()
-            .with_context(|| anyhow!("could not run the Lua script at `{}`", cfg.path.display()))?;
-        let extract: Function<'_> = lua
-            .globals()
-            .get("extract")
-            .context("found no suitable `extract` function")?;
+        let lua = make_vm(lua_cfg).context("could not set up a Lua VM")?;
+
+        if let Some(max_memory) = cfg.max_memory.or(lua_cfg.max_memory) {
+            lua.set_memory_limit(max_memory.bytes().try_into().unwrap_or(usize::MAX))
+                .context("could not set the memory limit of the Lua VM")?;
+        }
+        lua.set_app_data(HttpState::new(
+            cfg.request_delay.into(),
+            cfg.request_timeout.into(),
+        ));
+        lua.set_app_data(KvState::default());
+        let limits = Limits::install(
+            &lua,
+            &cfg.path,
+            cfg.time_limit.into(),
+            cfg.instruction_limit,
+        );
+
+        limits.begin();
+        let result = lua_cfg
+            .preload
+            .iter()
+            .try_for_each(|path| preload(&lua, path))
+            .and_then(|_| {
+                lua.load(cfg.path.as_path())
+                    .set_mode(ChunkMode::Text)
+                    .call::<_, LuaValue<'_>>(())
+                    .with_context(|| {
+                        anyhow!("could not run the Lua script at `{}`", cfg.path.display())
+                    })
+            })
+            .and_then(|module| find_functions(&lua, module, &cfg.options));
+        limits.end();
+        let (extract, prepare) = result?;
+
         let extract_key = lua
             .create_registry_value(extract)
             .context("could not save the `extract` function in the Lua registry")?;
+        let prepare_key = prepare
+            .map(|prepare| lua.create_registry_value(prepare))
+            .transpose()
+            .context("could not save the `prepare` function in the Lua registry")?;
+
+        Ok(Self {
+            lua,
+            path: cfg.path.clone(),
+            extract_key,
+            prepare_key,
+            limits,
+            options: cfg.options.clone(),
+        })
+    }
+
+    /// Makes the context's HTTP client and storage available to the script.
+    fn begin_session(&self, ctx: &super::Context<'_>) {
+        if let Some(http_client) = ctx.http_client() {
+            if let Some(mut http) = self.lua.app_data_mut::<HttpState>() {
+                http.begin(
+                    http_client.clone(),
+                    ctx.cancel().cloned(),
+                    ctx.base_url().clone(),
+                );
+            }
+        }
+
+        if let Some(mut kv) = self.lua.app_data_mut::<KvState>() {
+            kv.begin(ctx.storage().cloned(), ctx.feed_name());
+        }
+
+        self.lua.set_app_data(FetchTime(ctx.fetch_time()));
+        self.lua.set_app_data(DefaultTimezone(ctx.timezone()));
+    }
+
+    fn end_session(&self) {
+        if let Some(mut http) = self.lua.app_data_mut::<HttpState>() {
+            http.end();
+        }
+
+        if let Some(mut kv) = self.lua.app_data_mut::<KvState>() {
+            kv.end();
+        }
+    }
+
+    /// Adds the location of the error to the result of calling the script function `name`.
+    fn check_result<T>(
+        &self,
+        name: &str,
+        result: LuaResult<T>,
+        traceback: Option<Traceback>,
+    ) -> Result<T> {
+        if let (Err(_), Some(traceback)) = (&result, &traceback) {
+            warn!(
+                "The `{name}` function of `{}` raised an error; Lua stack traceback:{traceback}",
+                self.path.display(),
+            );
+        }
+
+        result.with_context(|| match traceback.as_ref().and_then(Traceback::location) {
+            Some(location) => anyhow!(
+                "running the `{name}` function of `{}` failed at {location}",
+                self.path.display(),
+            ),
 
-        Ok(Self { lua, extract_key })
+            None => anyhow!(
+                "running the `{name}` function of `{}` failed",
+                self.path.display(),
+            ),
+        })
     }
 }
 
 impl Extractor for LuaExtractor {
-    fn extract(&mut self, ctx: super::Context<'_>, html: &str) -> Result<Vec<Entry>> {
+    fn prepare(&mut self, ctx: super::Context<'_>) -> Result<()> {
+        let Some(prepare_key) = &self.prepare_key else {
+            return Ok(());
+        };
+
+        let prepare: Function<'_> = self
+            .lua
+            .registry_value(prepare_key)
+            .context("could not retrieve the `prepare` function")?;
+
+        self.begin_session(&ctx);
+        let lua_ctx = make_context(&self.lua, &self.options, &ctx)
+            .context("could not create the context table")?;
+        self.limits.begin();
+        let (result, traceback) = traceback::call::<()>(&self.lua, prepare, lua_ctx);
+        self.limits.end();
+        self.end_session();
+
+        self.check_result("prepare", result, traceback)
+    }
+
+    fn extract(&mut self, ctx: super::Context<'_>, html: &str) -> Result<Extraction> {
         let buf = Buffer::from(html);
         let extract: Function<'_> = self
             .lua
             .registry_value(&self.extract_key)
             .context("could not retrieve the `extract` function")?;
-        let entries: LuaEntries = extract
-            .call(buf)
-            .context("running the `extract` function failed")?;
+
+        self.begin_session(&ctx);
+        let lua_ctx = make_context(&self.lua, &self.options, &ctx)
+            .context("could not create the context table")?;
+        self.lua.remove_app_data::<LoginRequested>();
+        self.limits.begin();
+        let (result, traceback) = traceback::call::<(LuaEntries, Option<Stringified>)>(
+            &self.lua,
+            extract,
+            (buf, lua_ctx.clone()),
+        );
+        self.limits.end();
+        self.end_session();
+
+        if let Some(cache) = self.lua.app_data_ref::<SelectorCache>() {
+            cache.log_stats();
+        }
+
+        if result.is_err() && self.lua.remove_app_data::<LoginRequested>().is_some() {
+            return Err(LoginRequired.into());
+        }
+
+        let (entries, next_page) = self.check_result("extract", result, traceback)?;
         let entries = Vec::from(entries);
+        let channel = lua_ctx
+            .get::<_, Option<LuaChannelInfo>>("channel")
+            .context("`ctx.channel` is invalid")?
+            .map(|channel| ChannelInfo {
+                title: channel.title,
+                description: channel.description,
+                link: channel.link.and_then(|link| {
+                    ctx.base_url()
+                        .join(&link)
+                        .inspect_err(|e| warn!("The channel link could not be parsed: {e:#}"))
+                        .ok()
+                }),
+            });
+        let next_page = next_page
+            .filter(|next_page| !next_page.is_empty())
+            .and_then(|next_page| {
+                ctx.base_url()
+                    .join(&next_page)
+                    .inspect_err(|e| warn!("The next page URL could not be parsed: {e:#}"))
+                    .ok()
+            });
 
-        Ok(entries
+        let found_count = entries.len();
+        let entries = entries
             .into_iter()
             .enumerate()
             .filter_map(|(idx, entry)| {
                 let url = ctx
-                    .fetch_url()
+                    .base_url()
                     .join(&entry.url)
                     .inspect_err(|e| {
                         warn!("The URL for entry #{} could not be parsed: {e:#}", idx + 1);
                     })
                     .ok()?;
 
+                let enclosure = entry.enclosure.and_then(|enclosure| {
+                    let url = ctx
+                        .base_url()
+                        .join(&enclosure.url)
+                        .inspect_err(|e| {
+                            warn!(
+                                "The enclosure URL for entry #{} could not be parsed: {e:#}",
+                                idx + 1,
+                            );
+                        })
+                        .ok()?;
+
+                    Some(Enclosure {
+                        url,
+                        mime_type: enclosure.mime_type,
+                        length: enclosure.length,
+                    })
+                });
+
+                let image = entry.image.and_then(|image| {
+                    ctx.base_url()
+                        .join(&image)
+                        .inspect_err(|e| {
+                            warn!(
+                                "The image URL for entry #{} could not be parsed: {e:#}",
+                                idx + 1,
+                            );
+                        })
+                        .ok()
+                });
+
+                let comments = entry.comments.and_then(|comments| {
+                    ctx.base_url()
+                        .join(&comments)
+                        .inspect_err(|e| {
+                            warn!(
+                                "The comments URL for entry #{} could not be parsed: {e:#}",
+                                idx + 1,
+                            );
+                        })
+                        .ok()
+                });
+
                 Some(Entry {
                     id: entry.id,
                     title: entry.title,
                     description: entry.description,
+                    content: entry.content,
                     url,
                     author: entry.author,
                     pub_date: entry.pub_date,
+                    enclosure,
+                    image,
+                    comments,
+                    categories: entry.categories,
+                    guid: entry.guid,
+                    guid_is_permalink: entry.guid_is_permalink,
+                    extra: entry.extra,
                 })
             })
-            .collect())
+            .collect::<Vec<_>>();
+        let skipped = found_count - entries.len();
+
+        Ok(Extraction {
+            entries,
+            next_page,
+            channel,
+            skipped,
+        })
     }
 }
@@ -1,12 +1,22 @@
+#[cfg(feature = "lua54")]
 use std::cell::Cell;
 
 use anyhow::{anyhow, Context, Result};
 use mlua::Result as LuaResult;
-use mlua::{FromLuaMulti, IntoLuaMulti, Lua, MultiValue, Table as LuaTable};
+use mlua::{Error as LuaError, FromLuaMulti, IntoLuaMulti, Lua, MultiValue, Table as LuaTable};
 use scraper::Html;
 use tracing::{debug, error, info, trace, warn};
 
+use super::date::{self, make_offset_table, DefaultTimezone};
+use super::digest;
+use super::encoding;
+use super::escape;
+use super::http::fetch;
+use super::kv;
 use super::types::{Buffer, LuaHtml, SelectorWrapper};
+use super::url;
+use super::util;
+use super::xml::{parse_xml, register_node_metatable};
 
 fn parse_selector(_lua: &Lua, selector: SelectorWrapper) -> LuaResult<SelectorWrapper> {
     Ok(selector)
@@ -19,6 +29,55 @@ fn parse_html(_lua: &Lua, buf: Buffer) -> LuaResult<LuaHtml> {
     Ok(html)
 }
 
+fn parse_html_fragment(_lua: &Lua, buf: Buffer) -> LuaResult<LuaHtml> {
+    let html = Html::parse_fragment(&buf);
+    let html = LuaHtml::from(html);
+
+    Ok(html)
+}
+
+fn readability(_lua: &Lua, buf: Buffer) -> LuaResult<Option<String>> {
+    Ok(crate::readability::extract(&buf))
+}
+
+fn metadata(lua: &Lua, buf: Buffer) -> LuaResult<LuaTable<'_>> {
+    let html = Html::parse_document(&buf);
+    let tz = lua.app_data_ref::<DefaultTimezone>().and_then(|tz| tz.0);
+    let articles = lua.create_table()?;
+
+    for (idx, article) in crate::metadata::extract(&html, tz).into_iter().enumerate() {
+        let tbl = lua.create_table()?;
+        tbl.set("id", article.url.clone())?;
+        tbl.set("title", article.title)?;
+        tbl.set("description", article.description)?;
+        tbl.set("url", article.url)?;
+        tbl.set("author", article.author)?;
+        tbl.set(
+            "pubDate",
+            article
+                .published
+                .map(|published| make_offset_table(lua, published))
+                .transpose()?,
+        )?;
+        tbl.set("image", article.image)?;
+        tbl.set("categories", article.keywords)?;
+        articles.set(idx + 1, tbl)?;
+    }
+
+    Ok(articles)
+}
+
+/// Set by `feedgen.loginRequired` for the extractor to report [`LoginRequired`].
+///
+/// [`LoginRequired`]: crate::extractor::LoginRequired
+pub struct LoginRequested;
+
+fn login_required(lua: &Lua, _: ()) -> LuaResult<()> {
+    lua.set_app_data(LoginRequested);
+
+    Err(LuaError::runtime("the page asks to log in"))
+}
+
 fn get_caller_info(lua: &Lua) -> String {
     let Some(debug) = lua.inspect_stack(1) else {
         return "<unknown>".into();
@@ -33,7 +92,7 @@ fn get_caller_info(lua: &Lua) -> String {
     }
 }
 
-fn args_to_string(values: MultiValue<'_>, sep: &str) -> String {
+pub fn args_to_string(values: MultiValue<'_>, sep: &str) -> String {
     use std::fmt::Write;
 
     let mut result = String::new();
@@ -105,6 +164,7 @@ fn log_error(lua: &Lua, args: MultiValue<'_>) -> LuaResult<()> {
     Ok(())
 }
 
+#[cfg(feature = "lua54")]
 fn make_warning_emitter() -> impl Fn(&Lua, &str, bool) -> LuaResult<()> + Send + 'static {
     let last_continued = Cell::new(false);
 
@@ -155,6 +215,17 @@ pub fn add_feedgen_api(lua: &Lua) -> Result<()> {
 
     register!("feedgen.parseSelector", "parseSelector", parse_selector)?;
     register!("feedgen.parseHtml", "parseHtml", parse_html)?;
+    register!(
+        "feedgen.parseHtmlFragment",
+        "parseHtmlFragment",
+        parse_html_fragment
+    )?;
+    register!("feedgen.parseXml", "parseXml", parse_xml)?;
+    register!("feedgen.fetch", "fetch", fetch)?;
+    register!("feedgen.readability", "readability", readability)?;
+    register!("feedgen.metadata", "metadata", metadata)?;
+    register!("feedgen.loginRequired", "loginRequired", login_required)?;
+    register_node_metatable(lua).context("could not register the XML node metatable")?;
 
     let log = lua
         .create_table()
@@ -168,12 +239,175 @@ pub fn add_feedgen_api(lua: &Lua) -> Result<()> {
     feedgen
         .set("log", log)
         .context("could not register `feedgen.log`")?;
+
+    let date = lua
+        .create_table()
+        .context("could not create a table `feedgen.date`")?;
+    register(lua, &date, "feedgen.date.parse", "parse", date::parse)?;
+    register(
+        lua,
+        &date,
+        "feedgen.date.parseRelative",
+        "parseRelative",
+        date::parse_relative,
+    )?;
+
+    feedgen
+        .set("date", date)
+        .context("could not register `feedgen.date`")?;
+
+    let html = lua
+        .create_table()
+        .context("could not create a table `feedgen.html`")?;
+    register(lua, &html, "feedgen.html.escape", "escape", escape::escape)?;
+    register(
+        lua,
+        &html,
+        "feedgen.html.unescape",
+        "unescape",
+        escape::unescape,
+    )?;
+
+    feedgen
+        .set("html", html)
+        .context("could not register `feedgen.html`")?;
+
+    let base64 = lua
+        .create_table()
+        .context("could not create a table `feedgen.base64`")?;
+    register(
+        lua,
+        &base64,
+        "feedgen.base64.encode",
+        "encode",
+        encoding::base64_encode,
+    )?;
+    register(
+        lua,
+        &base64,
+        "feedgen.base64.decode",
+        "decode",
+        encoding::base64_decode,
+    )?;
+
+    feedgen
+        .set("base64", base64)
+        .context("could not register `feedgen.base64`")?;
+
+    let hex = lua
+        .create_table()
+        .context("could not create a table `feedgen.hex`")?;
+    register(
+        lua,
+        &hex,
+        "feedgen.hex.encode",
+        "encode",
+        encoding::hex_encode,
+    )?;
+    register(
+        lua,
+        &hex,
+        "feedgen.hex.decode",
+        "decode",
+        encoding::hex_decode,
+    )?;
+
+    feedgen
+        .set("hex", hex)
+        .context("could not register `feedgen.hex`")?;
+
+    let digest = lua
+        .create_table()
+        .context("could not create a table `feedgen.digest`")?;
+    register(
+        lua,
+        &digest,
+        "feedgen.digest.sha256",
+        "sha256",
+        digest::sha256,
+    )?;
+    register(lua, &digest, "feedgen.digest.sha1", "sha1", digest::sha1)?;
+    register(lua, &digest, "feedgen.digest.md5", "md5", digest::md5)?;
+
+    feedgen
+        .set("digest", digest)
+        .context("could not register `feedgen.digest`")?;
+
+    let kv = lua
+        .create_table()
+        .context("could not create a table `feedgen.kv`")?;
+    register(lua, &kv, "feedgen.kv.get", "get", kv::get)?;
+    register(lua, &kv, "feedgen.kv.set", "set", kv::set)?;
+
+    feedgen
+        .set("kv", kv)
+        .context("could not register `feedgen.kv`")?;
+
+    let url = lua
+        .create_table()
+        .context("could not create a table `feedgen.url`")?;
+    register(lua, &url, "feedgen.url.parse", "parse", url::parse)?;
+    register(lua, &url, "feedgen.url.join", "join", url::join)?;
+    register(
+        lua,
+        &url,
+        "feedgen.url.encodeQuery",
+        "encodeQuery",
+        url::encode_query,
+    )?;
+    register(
+        lua,
+        &url,
+        "feedgen.url.decodeQuery",
+        "decodeQuery",
+        url::decode_query,
+    )?;
+
+    feedgen
+        .set("url", url)
+        .context("could not register `feedgen.url`")?;
+
+    let util = lua
+        .create_table()
+        .context("could not create a table `feedgen.util`")?;
+    register(lua, &util, "feedgen.util.trim", "trim", util::trim)?;
+    register(
+        lua,
+        &util,
+        "feedgen.util.collapseWhitespace",
+        "collapseWhitespace",
+        util::collapse_whitespace,
+    )?;
+    register(
+        lua,
+        &util,
+        "feedgen.util.truncate",
+        "truncate",
+        util::truncate,
+    )?;
+    register(
+        lua,
+        &util,
+        "feedgen.util.stripTags",
+        "stripTags",
+        util::strip_tags,
+    )?;
+
+    feedgen
+        .set("util", util)
+        .context("could not register `feedgen.util`")?;
+
     lua.globals()
         .set("feedgen", feedgen)
         .context("could not register `feedgen`")?;
 
     register(lua, &lua.globals(), "print", "print", log_info)?;
+
+    // `warn` is only built into Lua 5.4; elsewhere, emulate it for scripts that use it.
+    #[cfg(feature = "lua54")]
     lua.set_warning_function(make_warning_emitter());
+    #[cfg(not(feature = "lua54"))]
+    register(lua, &lua.globals(), "warn", "warn", log_warn)?;
 
     Ok(())
 }
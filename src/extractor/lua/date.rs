@@ -0,0 +1,145 @@
+use mlua::prelude::*;
+use time::format_description::well_known::{Rfc2822, Rfc3339};
+use time::format_description::{self, OwnedFormatItem};
+use time::{Date, OffsetDateTime, PrimitiveDateTime, Time};
+
+use crate::config::Timezone;
+use crate::reldate::{self, Locale};
+
+/// The time the source page was retrieved, stored in the Lua VM's app data during extraction.
+pub struct FetchTime(pub OffsetDateTime);
+
+/// The feed's timezone, stored in the Lua VM's app data during extraction. Datetimes without a
+/// UTC offset or a timezone are assumed to be in it.
+pub struct DefaultTimezone(pub Option<Timezone>);
+
+enum Format {
+    Rfc2822,
+    Rfc3339,
+    Custom(OwnedFormatItem),
+}
+
+impl Format {
+    fn parse(format: &str) -> LuaResult<Self> {
+        Ok(match format {
+            "rfc2822" => Self::Rfc2822,
+            "rfc3339" => Self::Rfc3339,
+
+            _ => Self::Custom(format_description::parse_owned::<2>(format).map_err(|e| {
+                LuaError::runtime(format!("could not parse the format `{format}`: {e}"))
+            })?),
+        })
+    }
+
+    fn parse_offset(&self, s: &str) -> Result<OffsetDateTime, time::error::Parse> {
+        match self {
+            Self::Rfc2822 => OffsetDateTime::parse(s, &Rfc2822),
+            Self::Rfc3339 => OffsetDateTime::parse(s, &Rfc3339),
+            Self::Custom(format) => OffsetDateTime::parse(s, format),
+        }
+    }
+
+    /// Parses a datetime without a UTC offset. If the format has no time components, the time is
+    /// set to midnight.
+    fn parse_primitive(&self, s: &str) -> Option<PrimitiveDateTime> {
+        let Self::Custom(format) = self else {
+            return None;
+        };
+
+        PrimitiveDateTime::parse(s, format).ok().or_else(|| {
+            Date::parse(s, format)
+                .ok()
+                .map(|date| date.with_time(Time::MIDNIGHT))
+        })
+    }
+}
+
+fn make_table<'lua>(lua: &'lua Lua, datetime: PrimitiveDateTime) -> LuaResult<LuaTable<'lua>> {
+    let tbl = lua.create_table()?;
+    tbl.set("year", datetime.year())?;
+    tbl.set("month", datetime.month() as u8)?;
+    tbl.set("day", datetime.day())?;
+    tbl.set("hour", datetime.hour())?;
+    tbl.set("minute", datetime.minute())?;
+    tbl.set("second", datetime.second())?;
+
+    Ok(tbl)
+}
+
+pub fn make_offset_table(lua: &Lua, datetime: OffsetDateTime) -> LuaResult<LuaTable<'_>> {
+    let tbl = make_table(
+        lua,
+        PrimitiveDateTime::new(datetime.date(), datetime.time()),
+    )?;
+    tbl.set("utcOffset", datetime.offset().whole_minutes())?;
+
+    Ok(tbl)
+}
+
+/// `feedgen.date.parse(s, format, tz)`: parses a datetime into a table suitable for `pubDate`.
+///
+/// `format` is either a `time` format description or one of `rfc2822` and `rfc3339`. If the
+/// parsed string has no UTC offset, the datetime is assumed to be in the timezone `tz`, which
+/// defaults to the feed's timezone.
+pub fn parse<'lua>(
+    lua: &'lua Lua,
+    (s, format, tz): (String, String, Option<String>),
+) -> LuaResult<LuaTable<'lua>> {
+    let format = Format::parse(&format)?;
+
+    let err = match format.parse_offset(&s) {
+        Ok(datetime) => return make_offset_table(lua, datetime),
+
+        Err(e) => e,
+    };
+
+    let tz = tz.or_else(|| {
+        lua.app_data_ref::<DefaultTimezone>()
+            .and_then(|tz| tz.0)
+            .map(|tz| tz.name().into())
+    });
+
+    match (tz, format.parse_primitive(&s)) {
+        (Some(tz), Some(datetime)) => {
+            let tbl = make_table(lua, datetime)?;
+            tbl.set("tz", tz)?;
+
+            Ok(tbl)
+        }
+
+        _ => Err(LuaError::runtime(format!(
+            "could not parse the date `{s}`: {err}"
+        ))),
+    }
+}
+
+/// `feedgen.date.parseRelative(s, locales)`: converts a relative timestamp ("5 min ago") to a
+/// table suitable for `pubDate`, or returns `nil` if it's not recognized.
+///
+/// `locales` is a list of language codes and defaults to `{"en"}`.
+pub fn parse_relative<'lua>(
+    lua: &'lua Lua,
+    (s, locales): (String, Option<Vec<String>>),
+) -> LuaResult<Option<LuaTable<'lua>>> {
+    let locales = match locales {
+        Some(locales) => locales
+            .iter()
+            .map(|name| {
+                Locale::from_name(name)
+                    .ok_or_else(|| LuaError::runtime(format!("unknown locale '{name}'")))
+            })
+            .collect::<LuaResult<Vec<_>>>()?,
+
+        None => vec![Locale::En],
+    };
+
+    let now = lua
+        .app_data_ref::<FetchTime>()
+        .map_or_else(OffsetDateTime::now_utc, |fetch_time| fetch_time.0);
+
+    let Some(datetime) = reldate::parse(&s, now, &locales) else {
+        return Ok(None);
+    };
+
+    make_offset_table(lua, datetime).map(Some)
+}
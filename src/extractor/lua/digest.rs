@@ -0,0 +1,26 @@
+use md5::Md5;
+use mlua::prelude::*;
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+
+fn hex_digest<D: Digest>(s: &LuaString<'_>) -> String {
+    D::digest(s.as_bytes())
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+/// `feedgen.digest.sha256(s)`: returns the SHA-256 digest of a string as lowercase hex.
+pub fn sha256(_lua: &Lua, s: LuaString<'_>) -> LuaResult<String> {
+    Ok(hex_digest::<Sha256>(&s))
+}
+
+/// `feedgen.digest.sha1(s)`: returns the SHA-1 digest of a string as lowercase hex.
+pub fn sha1(_lua: &Lua, s: LuaString<'_>) -> LuaResult<String> {
+    Ok(hex_digest::<Sha1>(&s))
+}
+
+/// `feedgen.digest.md5(s)`: returns the MD5 digest of a string as lowercase hex.
+pub fn md5(_lua: &Lua, s: LuaString<'_>) -> LuaResult<String> {
+    Ok(hex_digest::<Md5>(&s))
+}
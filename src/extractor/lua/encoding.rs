@@ -0,0 +1,92 @@
+use base64::alphabet;
+use base64::engine::general_purpose::{GeneralPurpose, GeneralPurposeConfig};
+use base64::engine::DecodePaddingMode;
+use base64::prelude::*;
+use mlua::prelude::*;
+
+/// Decoding engines that accept input with or without padding.
+const BASE64_LENIENT: GeneralPurpose = GeneralPurpose::new(
+    &alphabet::STANDARD,
+    GeneralPurposeConfig::new().with_decode_padding_mode(DecodePaddingMode::Indifferent),
+);
+const BASE64_URL_SAFE_LENIENT: GeneralPurpose = GeneralPurpose::new(
+    &alphabet::URL_SAFE,
+    GeneralPurposeConfig::new().with_decode_padding_mode(DecodePaddingMode::Indifferent),
+);
+
+/// `feedgen.base64.encode(s, urlSafe)`: encodes a (possibly binary) string as base64.
+pub fn base64_encode(
+    _lua: &Lua,
+    (s, url_safe): (LuaString<'_>, Option<bool>),
+) -> LuaResult<String> {
+    Ok(if url_safe.unwrap_or(false) {
+        BASE64_URL_SAFE.encode(s.as_bytes())
+    } else {
+        BASE64_STANDARD.encode(s.as_bytes())
+    })
+}
+
+/// `feedgen.base64.decode(s, urlSafe)`: decodes base64, returning a (possibly binary) string.
+/// Whitespace is ignored, and padding is optional.
+pub fn base64_decode<'lua>(
+    lua: &'lua Lua,
+    (s, url_safe): (LuaString<'lua>, Option<bool>),
+) -> LuaResult<LuaString<'lua>> {
+    let input = s
+        .as_bytes()
+        .iter()
+        .copied()
+        .filter(|c| !c.is_ascii_whitespace())
+        .collect::<Vec<_>>();
+    let engine = if url_safe.unwrap_or(false) {
+        &BASE64_URL_SAFE_LENIENT
+    } else {
+        &BASE64_LENIENT
+    };
+    let bytes = engine
+        .decode(input)
+        .map_err(|e| LuaError::runtime(format!("invalid base64: {e}")))?;
+
+    lua.create_string(bytes)
+}
+
+/// `feedgen.hex.encode(s)`: encodes a (possibly binary) string as lowercase hexadecimal digits.
+pub fn hex_encode(_lua: &Lua, s: LuaString<'_>) -> LuaResult<String> {
+    const DIGITS: &[u8; 16] = b"0123456789abcdef";
+
+    Ok(s.as_bytes()
+        .iter()
+        .flat_map(|&b| [DIGITS[(b >> 4) as usize], DIGITS[(b & 0xf) as usize]])
+        .map(char::from)
+        .collect())
+}
+
+/// `feedgen.hex.decode(s)`: decodes hexadecimal digits (in either case), returning a (possibly
+/// binary) string.
+pub fn hex_decode<'lua>(lua: &'lua Lua, s: LuaString<'lua>) -> LuaResult<LuaString<'lua>> {
+    fn digit(c: u8) -> LuaResult<u8> {
+        match c {
+            b'0'..=b'9' => Ok(c - b'0'),
+            b'a'..=b'f' => Ok(c - b'a' + 10),
+            b'A'..=b'F' => Ok(c - b'A' + 10),
+
+            _ => Err(LuaError::runtime(format!(
+                "invalid hex: unexpected character {:?}",
+                char::from(c),
+            ))),
+        }
+    }
+
+    let input = s.as_bytes();
+
+    if !input.len().is_multiple_of(2) {
+        return Err(LuaError::runtime("invalid hex: odd number of digits"));
+    }
+
+    let bytes = input
+        .chunks_exact(2)
+        .map(|pair| Ok((digit(pair[0])? << 4) | digit(pair[1])?))
+        .collect::<LuaResult<Vec<u8>>>()?;
+
+    lua.create_string(bytes)
+}
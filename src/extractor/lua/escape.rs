@@ -0,0 +1,15 @@
+use mlua::prelude::*;
+
+use super::types::Stringified;
+
+/// `feedgen.html.escape(s)`: escapes text for embedding into HTML (both as element contents and as
+/// a quoted attribute value).
+pub fn escape(_lua: &Lua, s: Stringified) -> LuaResult<String> {
+    Ok(html_escape::encode_safe(&*s).into_owned())
+}
+
+/// `feedgen.html.unescape(s)`: replaces HTML character references (`&amp;`, `&#39;`, etc.) with
+/// the characters they represent.
+pub fn unescape(_lua: &Lua, s: Stringified) -> LuaResult<String> {
+    Ok(html_escape::decode_html_entities(&*s).into_owned())
+}
@@ -0,0 +1,218 @@
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use anyhow::anyhow;
+use mlua::prelude::*;
+use reqwest::{Method, Url};
+use reqwest_middleware::ClientWithMiddleware;
+use tokio::runtime::Handle;
+use tokio::time::{self, Instant};
+use tokio_util::sync::CancellationToken;
+use tracing::debug;
+
+use crate::extractor;
+
+use super::types::{Buffer, Stringified};
+
+/// The state of `feedgen.fetch`, stored in the Lua VM's app data.
+pub struct HttpState {
+    /// The minimum time between two requests.
+    delay: Duration,
+
+    timeout: Duration,
+    next_request: Option<Instant>,
+
+    /// Set only while the `extract` or `prepare` function is running.
+    session: Option<Session>,
+}
+
+struct Session {
+    http_client: ClientWithMiddleware,
+    cancel: Option<CancellationToken>,
+    base_url: Url,
+}
+
+impl HttpState {
+    pub fn new(delay: Duration, timeout: Duration) -> Self {
+        Self {
+            delay,
+            timeout,
+            next_request: None,
+            session: None,
+        }
+    }
+
+    pub fn begin(
+        &mut self,
+        http_client: ClientWithMiddleware,
+        cancel: Option<CancellationToken>,
+        base_url: Url,
+    ) {
+        self.session = Some(Session {
+            http_client,
+            cancel,
+            base_url,
+        });
+    }
+
+    pub fn end(&mut self) {
+        self.session = None;
+    }
+}
+
+struct Request {
+    method: Method,
+    url: Url,
+    headers: BTreeMap<String, String>,
+    body: Option<String>,
+}
+
+impl Request {
+    fn from_lua(url: &str, options: Option<LuaTable<'_>>, base_url: &Url) -> LuaResult<Self> {
+        let url = base_url
+            .join(url)
+            .map_err(|e| LuaError::runtime(format!("the URL `{url}` is invalid: {e}")))?;
+
+        let Some(options) = options else {
+            return Ok(Self {
+                method: Method::GET,
+                url,
+                headers: Default::default(),
+                body: None,
+            });
+        };
+
+        let method: Option<String> = options.get("method").context("'method' is invalid")?;
+        let method = match method.as_deref().map(str::to_ascii_uppercase).as_deref() {
+            None | Some("GET") => Method::GET,
+            Some("POST") => Method::POST,
+
+            Some(method) => {
+                return Err(LuaError::runtime(format!(
+                    "unsupported method '{method}' (expected 'GET' or 'POST')"
+                )))
+            }
+        };
+
+        let headers: Option<BTreeMap<String, String>> =
+            options.get("headers").context("'headers' is invalid")?;
+        let body: Option<Buffer> = options.get("body").context("'body' is invalid")?;
+
+        Ok(Self {
+            method,
+            url,
+            headers: headers.unwrap_or_default(),
+            body: body.map(|body| String::from(&*body)),
+        })
+    }
+}
+
+struct Response {
+    status: u16,
+    url: Url,
+    headers: BTreeMap<String, String>,
+    body: String,
+}
+
+async fn send(
+    http_client: &ClientWithMiddleware,
+    request: Request,
+    timeout: Duration,
+) -> anyhow::Result<Response> {
+    let url = request.url.clone();
+    let mut builder = http_client.request(request.method, request.url);
+
+    for (name, value) in &request.headers {
+        builder = builder.header(name, value);
+    }
+
+    if let Some(body) = request.body {
+        builder = builder.body(body);
+    }
+
+    let fut = async {
+        let response = builder.send().await?;
+        let status = response.status().as_u16();
+        let url = response.url().clone();
+        let headers = extractor::collect_headers(response.headers());
+
+        let body = response.text().await?;
+
+        anyhow::Ok(Response {
+            status,
+            url,
+            headers,
+            body,
+        })
+    };
+
+    time::timeout(timeout, fut)
+        .await
+        .map_err(|_| anyhow!("the request to `{url}` timed out"))?
+        .map_err(|e| anyhow!("the request to `{url}` failed: {e:#}"))
+}
+
+/// `feedgen.fetch(url, options)`: performs an HTTP request and returns the response.
+pub fn fetch<'lua>(
+    lua: &'lua Lua,
+    (url, options): (Stringified, Option<LuaTable<'lua>>),
+) -> LuaResult<LuaTable<'lua>> {
+    let (http_client, cancel, request, timeout, start) = {
+        let mut state = lua
+            .app_data_mut::<HttpState>()
+            .ok_or_else(|| LuaError::runtime("feedgen.fetch is not available"))?;
+        let HttpState {
+            delay,
+            timeout,
+            next_request,
+            session,
+        } = &mut *state;
+        let session = session.as_ref().ok_or_else(|| {
+            LuaError::runtime("feedgen.fetch can only be called inside `extract` or `prepare`")
+        })?;
+
+        let request = Request::from_lua(&url, options, &session.base_url)?;
+        let now = Instant::now();
+        let start = next_request.map_or(now, |next_request| next_request.max(now));
+        *next_request = Some(start + *delay);
+
+        (
+            session.http_client.clone(),
+            session.cancel.clone(),
+            request,
+            *timeout,
+            start,
+        )
+    };
+
+    let handle = Handle::try_current().map_err(LuaError::external)?;
+    debug!(method = %request.method, url = %request.url, "Sending a request from Lua");
+
+    let response = handle.block_on(async {
+        let cancelled = async {
+            match &cancel {
+                Some(cancel) => cancel.cancelled().await,
+                None => std::future::pending().await,
+            }
+        };
+
+        tokio::select! {
+            _ = cancelled => Err(anyhow!("the request was cancelled")),
+
+            response = async {
+                time::sleep_until(start).await;
+                send(&http_client, request, timeout).await
+            } => response,
+        }
+    });
+    let response = response.map_err(LuaError::external)?;
+
+    let result = lua.create_table()?;
+    result.set("status", response.status)?;
+    result.set("ok", (200..300).contains(&response.status))?;
+    result.set("url", response.url.as_str())?;
+    result.set("headers", response.headers)?;
+    result.set("body", Buffer::from(response.body))?;
+
+    Ok(result)
+}
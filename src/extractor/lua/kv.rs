@@ -0,0 +1,148 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use mlua::prelude::*;
+use tokio::runtime::Handle;
+
+use crate::storage::Storage;
+
+/// The state of `feedgen.kv`, stored in the Lua VM's app data.
+#[derive(Default)]
+pub struct KvState {
+    /// Set only while the `extract` function is running.
+    session: Option<Session>,
+}
+
+#[derive(Clone)]
+enum Session {
+    /// Values are stored in the database.
+    Persistent {
+        storage: Arc<Storage>,
+        feed_name: String,
+    },
+
+    /// Values are discarded after the extraction (used when previewing an extractor, so that it
+    /// doesn't affect the state of a real feed).
+    Ephemeral(Arc<Mutex<HashMap<String, String>>>),
+}
+
+impl KvState {
+    pub fn begin(&mut self, storage: Option<Arc<Storage>>, feed_name: Option<&str>) {
+        self.session = Some(match (storage, feed_name) {
+            (Some(storage), Some(feed_name)) => Session::Persistent {
+                storage,
+                feed_name: feed_name.into(),
+            },
+
+            _ => Session::Ephemeral(Default::default()),
+        });
+    }
+
+    pub fn end(&mut self) {
+        self.session = None;
+    }
+}
+
+fn to_json(value: LuaValue<'_>) -> LuaResult<Option<serde_json::Value>> {
+    Ok(Some(match value {
+        LuaValue::Nil => return Ok(None),
+        LuaValue::Boolean(b) => b.into(),
+        LuaValue::Integer(i) => i.into(),
+
+        LuaValue::Number(n) => serde_json::Number::from_f64(n)
+            .ok_or_else(|| LuaError::runtime(format!("the number {n} cannot be stored")))?
+            .into(),
+
+        LuaValue::String(s) => s.to_str()?.into(),
+
+        _ => {
+            return Err(LuaError::runtime(format!(
+                "cannot store a value of type {} (expected string, number, boolean, or nil)",
+                value.type_name(),
+            )))
+        }
+    }))
+}
+
+fn from_json(lua: &Lua, value: serde_json::Value) -> LuaResult<LuaValue<'_>> {
+    Ok(match value {
+        serde_json::Value::Bool(b) => LuaValue::Boolean(b),
+        serde_json::Value::String(s) => s.into_lua(lua)?,
+
+        serde_json::Value::Number(n) => match n.as_i64() {
+            Some(i) => LuaValue::Integer(i),
+            None => LuaValue::Number(n.as_f64().unwrap_or(f64::NAN)),
+        },
+
+        _ => LuaValue::Nil,
+    })
+}
+
+fn block_on<F: std::future::Future>(f: F) -> LuaResult<F::Output> {
+    let handle = Handle::try_current().map_err(LuaError::external)?;
+
+    Ok(handle.block_on(f))
+}
+
+fn session(lua: &Lua) -> LuaResult<Session> {
+    let state = lua
+        .app_data_ref::<KvState>()
+        .ok_or_else(|| LuaError::runtime("feedgen.kv is not available"))?;
+
+    state
+        .session
+        .clone()
+        .ok_or_else(|| LuaError::runtime("feedgen.kv can only be used while extracting entries"))
+}
+
+/// `feedgen.kv.get(key)`: returns the value stored under `key`, or `nil`.
+pub fn get(lua: &Lua, key: String) -> LuaResult<LuaValue<'_>> {
+    let value = match session(lua)? {
+        Session::Ephemeral(values) => values.lock().unwrap().get(&key).cloned(),
+
+        Session::Persistent { storage, feed_name } => block_on(async {
+            let mut tx = storage.begin().await?;
+            let value = tx.get_kv(&feed_name, &key).await?;
+            tx.commit().await?;
+
+            anyhow::Ok(value)
+        })?
+        .map_err(LuaError::external)?,
+    };
+
+    let Some(value) = value else {
+        return Ok(LuaValue::Nil);
+    };
+
+    let value = serde_json::from_str(&value).map_err(|e| {
+        LuaError::runtime(format!("the value stored under '{key}' is malformed: {e}"))
+    })?;
+
+    from_json(lua, value)
+}
+
+/// `feedgen.kv.set(key, value)`: stores `value` under `key` (or removes the key if `value` is
+/// `nil`).
+pub fn set<'lua>(lua: &'lua Lua, (key, value): (String, LuaValue<'lua>)) -> LuaResult<()> {
+    let value = to_json(value)?.map(|value| value.to_string());
+
+    match session(lua)? {
+        Session::Ephemeral(values) => {
+            let mut values = values.lock().unwrap();
+
+            match value {
+                Some(value) => values.insert(key, value),
+                None => values.remove(&key),
+            };
+
+            Ok(())
+        }
+
+        Session::Persistent { storage, feed_name } => block_on(async {
+            let mut tx = storage.begin().await?;
+            tx.set_kv(&feed_name, &key, value.as_deref()).await?;
+            tx.commit().await
+        })?
+        .map_err(LuaError::external),
+    }
+}
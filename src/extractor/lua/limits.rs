@@ -0,0 +1,106 @@
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use mlua::prelude::*;
+#[cfg(not(feature = "luau"))]
+use mlua::HookTriggers;
+#[cfg(feature = "luau")]
+use tracing::warn;
+
+/// The number of VM instructions between two checks of the budget.
+///
+/// Luau has no instruction hooks: it checks the budget at function calls and loop iterations
+/// instead, so the instruction limit isn't supported there.
+const CHECK_PERIOD: u32 = 1000;
+
+/// Execution limits of a script, enforced with a VM hook.
+pub struct Limits {
+    time_limit: Duration,
+    instruction_limit: Option<u64>,
+    budget: Arc<Mutex<Option<Budget>>>,
+}
+
+/// What's left of the limits while the script is running.
+struct Budget {
+    deadline: Instant,
+    remaining_instructions: Option<u64>,
+}
+
+impl Limits {
+    pub fn install(
+        lua: &Lua,
+        script_path: &Path,
+        time_limit: Duration,
+        instruction_limit: Option<u64>,
+    ) -> Self {
+        let budget = Arc::new(Mutex::new(None::<Budget>));
+        let script = script_path.display().to_string();
+
+        #[cfg(feature = "luau")]
+        let instruction_limit = instruction_limit.and_then(|_| {
+            warn!("Instruction limits are not supported by Luau; only the time limit is enforced");
+
+            None
+        });
+
+        let check = {
+            let budget = budget.clone();
+
+            move || {
+                let mut budget = budget.lock().unwrap();
+                let Some(budget) = budget.as_mut() else {
+                    return Ok(());
+                };
+
+                if Instant::now() > budget.deadline {
+                    return Err(LuaError::runtime(format!(
+                        "the script `{script}` exceeded its time limit of {}s",
+                        time_limit.as_secs_f64(),
+                    )));
+                }
+
+                if let Some(remaining) = &mut budget.remaining_instructions {
+                    match remaining.checked_sub(CHECK_PERIOD.into()) {
+                        Some(left) => *remaining = left,
+
+                        None => {
+                            return Err(LuaError::runtime(format!(
+                                "the script `{script}` exceeded its limit of {} instructions",
+                                instruction_limit.unwrap_or_default(),
+                            )))
+                        }
+                    }
+                }
+
+                Ok(())
+            }
+        };
+
+        #[cfg(not(feature = "luau"))]
+        lua.set_hook(
+            HookTriggers::new().every_nth_instruction(CHECK_PERIOD),
+            move |_lua, _debug| check(),
+        );
+        #[cfg(feature = "luau")]
+        lua.set_interrupt(move |_lua| check().map(|()| mlua::VmState::Continue));
+
+        Self {
+            time_limit,
+            instruction_limit,
+            budget,
+        }
+    }
+
+    /// Starts enforcing the limits (the budget is reset on each call).
+    pub fn begin(&self) {
+        *self.budget.lock().unwrap() = Some(Budget {
+            deadline: Instant::now() + self.time_limit,
+            remaining_instructions: self.instruction_limit,
+        });
+    }
+
+    pub fn end(&self) {
+        *self.budget.lock().unwrap() = None;
+    }
+}
@@ -0,0 +1,148 @@
+use std::collections::BTreeMap;
+use std::io::{self, BufRead, Write};
+use std::time::Duration;
+
+use anyhow::{Context as _, Result};
+use mlua::{Error as LuaError, Lua, MultiValue as LuaMultiValue, Result as LuaResult};
+use scraper::Html;
+
+use crate::config;
+use crate::extractor::Context;
+
+use super::api::args_to_string;
+use super::date::{DefaultTimezone, FetchTime};
+use super::http::HttpState;
+use super::kv::KvState;
+use super::types::{Buffer, LuaHtml};
+use super::{make_context, make_vm, preload, LuaExtractor};
+
+/// The time limit for requests made with `feedgen.fetch` when no feed is given.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Evaluates a chunk typed in by the user.
+fn eval<'lua>(lua: &'lua Lua, chunk: &str) -> LuaResult<LuaMultiValue<'lua>> {
+    // try the input as an expression first so that its value gets printed.
+    if let Ok(f) = lua
+        .load(format!("return {chunk}"))
+        .set_name("=stdin")
+        .into_function()
+    {
+        return f.call(());
+    }
+
+    lua.load(chunk).set_name("=stdin").eval()
+}
+
+/// Runs an interactive Lua session (see `feedgen lua`).
+///
+/// If `extractor_cfg` is given, the extractor script is loaded first, so its `extract` function
+/// can be called directly.
+pub fn run_repl(
+    lua_cfg: &config::LuaConfig,
+    extractor_cfg: Option<&config::LuaExtractorConfig>,
+    ctx: Context<'_>,
+    page: Option<&str>,
+) -> Result<()> {
+    let (lua, options) = match extractor_cfg {
+        Some(cfg) => {
+            let extractor = LuaExtractor::from_cfg(cfg, lua_cfg)?;
+
+            (extractor.lua, extractor.options)
+        }
+
+        None => {
+            let lua = make_vm(lua_cfg).context("could not set up a Lua VM")?;
+            lua.set_app_data(HttpState::new(Duration::ZERO, REQUEST_TIMEOUT));
+            lua.set_app_data(KvState::default());
+
+            for path in &lua_cfg.preload {
+                preload(&lua, path)?;
+            }
+
+            (lua, BTreeMap::new())
+        }
+    };
+
+    if let Some(http_client) = ctx.http_client() {
+        if let Some(mut http) = lua.app_data_mut::<HttpState>() {
+            http.begin(
+                http_client.clone(),
+                ctx.cancel().cloned(),
+                ctx.base_url().clone(),
+            );
+        }
+    }
+
+    // values stored with `feedgen.kv` are discarded when the session ends.
+    if let Some(mut kv) = lua.app_data_mut::<KvState>() {
+        kv.begin(None, None);
+    }
+
+    lua.set_app_data(FetchTime(ctx.fetch_time()));
+    lua.set_app_data(DefaultTimezone(ctx.timezone()));
+
+    let globals = lua.globals();
+    globals
+        .set(
+            "ctx",
+            make_context(&lua, &options, &ctx).context("could not create the context table")?,
+        )
+        .context("could not set the global `ctx`")?;
+
+    if let Some(page) = page {
+        let source = Buffer::from(page);
+        let html = LuaHtml::from(Html::parse_document(&source));
+        globals
+            .set("source", source)
+            .context("could not set the global `source`")?;
+        globals
+            .set("page", html)
+            .context("could not set the global `page`")?;
+    }
+
+    eprintln!(
+        "Feedgen Lua session. Globals: `ctx`{}. Press Ctrl-D to exit.",
+        if page.is_some() {
+            ", `source`, `page`"
+        } else {
+            ""
+        },
+    );
+
+    let mut stdin = io::stdin().lock();
+    let mut chunk = String::new();
+
+    loop {
+        print!("{}", if chunk.is_empty() { "> " } else { ">> " });
+        io::stdout().flush().context("could not write to stdout")?;
+
+        let mut line = String::new();
+
+        if stdin
+            .read_line(&mut line)
+            .context("could not read from stdin")?
+            == 0
+        {
+            println!();
+            break;
+        }
+
+        chunk.push_str(&line);
+
+        match eval(&lua, &chunk) {
+            // wait for the rest of the statement.
+            Err(LuaError::SyntaxError {
+                incomplete_input: true,
+                ..
+            }) => continue,
+
+            Ok(values) if values.is_empty() => {}
+            Ok(values) => println!("{}", args_to_string(values, "\t")),
+            Err(e) => eprintln!("{e}"),
+        }
+
+        chunk.clear();
+    }
+
+    Ok(())
+}
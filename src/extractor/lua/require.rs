@@ -0,0 +1,120 @@
+use std::path::{Path, PathBuf};
+
+use mlua::prelude::*;
+use tracing::debug;
+
+/// The registry table of modules already loaded by `require`, keyed by module names.
+const LOADED_KEY: &str = "feedgen.require.loaded";
+
+/// The registry table of modules currently being loaded (used to detect cycles).
+const LOADING_KEY: &str = "feedgen.require.loading";
+
+fn registry_table<'lua>(lua: &'lua Lua, key: &str) -> LuaResult<LuaTable<'lua>> {
+    if let Some(tbl) = lua.named_registry_value::<Option<LuaTable<'_>>>(key)? {
+        return Ok(tbl);
+    }
+
+    let tbl = lua.create_table()?;
+    lua.set_named_registry_value(key, tbl.clone())?;
+
+    Ok(tbl)
+}
+
+/// Converts a module name (`foo.bar`) to a relative path (`foo/bar`).
+///
+/// Only letters, digits, `_`, and `-` are allowed in name components, so that modules can't be
+/// loaded from outside the search path.
+fn module_path(name: &str) -> Option<PathBuf> {
+    name.split('.')
+        .map(|component| {
+            (!component.is_empty()
+                && component
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-'))
+            .then_some(component)
+        })
+        .collect()
+}
+
+fn find_module(search_path: &[PathBuf], name: &str) -> LuaResult<PathBuf> {
+    let relative_path = module_path(name)
+        .ok_or_else(|| LuaError::runtime(format!("invalid module name '{name}'")))?;
+    let mut tried = vec![];
+
+    for dir in search_path {
+        let candidates = [
+            dir.join(&relative_path).with_extension("lua"),
+            dir.join(&relative_path).join("init.lua"),
+        ];
+
+        for candidate in candidates {
+            if candidate.is_file() {
+                return Ok(candidate);
+            }
+
+            tried.push(candidate);
+        }
+    }
+
+    if search_path.is_empty() {
+        return Err(LuaError::runtime(format!(
+            "module '{name}' not found: no module directories are configured (see `lua.path`)"
+        )));
+    }
+
+    Err(LuaError::runtime(format!(
+        "module '{name}' not found; tried:{}",
+        tried
+            .iter()
+            .map(|path| format!("\n\t{}", path.display()))
+            .collect::<String>(),
+    )))
+}
+
+fn load_module<'lua>(lua: &'lua Lua, name: &str, path: &Path) -> LuaResult<LuaValue<'lua>> {
+    debug!("Loading the Lua module `{name}` from `{}`", path.display());
+
+    let value: LuaValue<'_> = lua
+        .load(path)
+        .set_mode(mlua::ChunkMode::Text)
+        .call((name, path.to_string_lossy().into_owned()))?;
+
+    // like the standard `require`, store `true` if the module returns nothing.
+    Ok(if value.is_nil() {
+        LuaValue::Boolean(true)
+    } else {
+        value
+    })
+}
+
+/// Creates the `require` function that loads modules only from `search_path`.
+pub fn make_require(
+    search_path: Vec<PathBuf>,
+) -> impl Fn(&Lua, String) -> LuaResult<LuaValue<'_>> + Send + 'static {
+    move |lua, name| {
+        let loaded = registry_table(lua, LOADED_KEY)?;
+        let value: LuaValue<'_> = loaded.raw_get(name.as_str())?;
+
+        if !value.is_nil() {
+            return Ok(value);
+        }
+
+        let loading = registry_table(lua, LOADING_KEY)?;
+
+        if loading.raw_get::<_, bool>(name.as_str())? {
+            return Err(LuaError::runtime(format!(
+                "module '{name}' is required while it's being loaded (circular dependency)"
+            )));
+        }
+
+        let path = find_module(&search_path, &name)?;
+        loading.raw_set(name.as_str(), true)?;
+        let value = load_module(lua, &name, &path);
+        loading.raw_set(name.as_str(), LuaValue::Nil)?;
+
+        let value = value.with_context(|_| format!("could not load the module '{name}'"))?;
+        loaded.raw_set(name.as_str(), value.clone())?;
+
+        Ok(value)
+    }
+}
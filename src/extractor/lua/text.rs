@@ -0,0 +1,167 @@
+use ego_tree::iter::Edge;
+use scraper::{ElementRef, Node};
+
+/// Elements rendered on their own lines.
+const BLOCK_ELEMENTS: &[&str] = &[
+    "address",
+    "article",
+    "aside",
+    "blockquote",
+    "caption",
+    "dd",
+    "details",
+    "dialog",
+    "div",
+    "dl",
+    "dt",
+    "fieldset",
+    "figcaption",
+    "figure",
+    "footer",
+    "form",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "header",
+    "hgroup",
+    "hr",
+    "legend",
+    "li",
+    "main",
+    "nav",
+    "ol",
+    "pre",
+    "section",
+    "summary",
+    "table",
+    "tbody",
+    "tfoot",
+    "thead",
+    "tr",
+    "ul",
+];
+
+/// Elements separated from surrounding text by a blank line.
+const PARAGRAPH_ELEMENTS: &[&str] = &["p"];
+
+/// Table cells, separated from each other by a space.
+const CELL_ELEMENTS: &[&str] = &["td", "th"];
+
+/// Elements whose contents are never rendered.
+const HIDDEN_ELEMENTS: &[&str] = &["head", "noscript", "script", "style", "template"];
+
+/// Accumulates words, collapsing whitespace between them.
+///
+/// Separators are only emitted between words, so leading and trailing whitespace is dropped.
+#[derive(Default)]
+pub struct TextWriter {
+    text: String,
+    pending_space: bool,
+    pending_newlines: usize,
+}
+
+impl TextWriter {
+    pub fn push_text(&mut self, s: &str) {
+        if s.starts_with(char::is_whitespace) {
+            self.pending_space = true;
+        }
+
+        for word in s.split_whitespace() {
+            if !self.text.is_empty() {
+                if self.pending_newlines > 0 {
+                    self.text.extend((0..self.pending_newlines).map(|_| '\n'));
+                } else if self.pending_space {
+                    self.text.push(' ');
+                }
+            }
+
+            self.text.push_str(word);
+            self.pending_space = false;
+            self.pending_newlines = 0;
+        }
+
+        if s.ends_with(char::is_whitespace) {
+            self.pending_space = true;
+        }
+    }
+
+    pub fn push_space(&mut self) {
+        self.pending_space = true;
+    }
+
+    /// Makes sure the next word is separated from the previous one by at least `count` newlines.
+    pub fn break_lines(&mut self, count: usize) {
+        self.pending_newlines = self.pending_newlines.max(count);
+    }
+
+    /// Adds a forced line break (unlike [`break_lines`](Self::break_lines), these accumulate).
+    pub fn push_newline(&mut self) {
+        self.pending_newlines += 1;
+    }
+
+    pub fn finish(self) -> String {
+        self.text
+    }
+}
+
+/// Renders the contents of an element as readable text, approximating the DOM's `innerText`.
+///
+/// Whitespace is collapsed, block elements are put on separate lines, and paragraphs are separated
+/// by blank lines. `<br>` produces a line break. The contents of scripts, styles, and similar
+/// elements are skipped.
+pub fn inner_text(element: ElementRef<'_>) -> String {
+    let mut writer = TextWriter::default();
+    let mut hidden_depth = 0usize;
+
+    for edge in element.traverse() {
+        match edge {
+            Edge::Open(node) => match node.value() {
+                _ if hidden_depth > 0 && node.value().is_element() => hidden_depth += 1,
+                _ if hidden_depth > 0 => {}
+
+                Node::Text(text) => writer.push_text(text),
+
+                Node::Element(el) => {
+                    let name = el.name();
+
+                    if HIDDEN_ELEMENTS.contains(&name) {
+                        hidden_depth += 1;
+                    } else if name == "br" {
+                        writer.push_newline();
+                    } else {
+                        separate(&mut writer, name);
+                    }
+                }
+
+                _ => {}
+            },
+
+            Edge::Close(node) => {
+                let Node::Element(el) = node.value() else {
+                    continue;
+                };
+
+                if hidden_depth > 0 {
+                    hidden_depth -= 1;
+                } else {
+                    separate(&mut writer, el.name());
+                }
+            }
+        }
+    }
+
+    writer.finish()
+}
+
+fn separate(writer: &mut TextWriter, name: &str) {
+    if PARAGRAPH_ELEMENTS.contains(&name) {
+        writer.break_lines(2);
+    } else if BLOCK_ELEMENTS.contains(&name) {
+        writer.break_lines(1);
+    } else if CELL_ELEMENTS.contains(&name) {
+        writer.push_space();
+    }
+}
@@ -0,0 +1,128 @@
+use std::fmt::{self, Display};
+
+use mlua::prelude::*;
+
+/// The registry key of the original `xpcall` (scripts may overwrite the global one).
+const XPCALL_KEY: &str = "feedgen.traceback.xpcall";
+
+/// The registry key of the message handler that captures tracebacks.
+const HANDLER_KEY: &str = "feedgen.traceback.handler";
+
+/// A stack frame active when an error was raised.
+struct Frame {
+    /// `None` for native functions.
+    location: Option<String>,
+    function: String,
+}
+
+impl Frame {
+    fn new(debug: &mlua::Debug<'_>) -> Self {
+        let source = debug.source();
+        let names = debug.names();
+        let line = debug.curr_line();
+
+        let location = source
+            .short_src
+            .as_deref()
+            .filter(|_| line > 0)
+            .map(|src| format!("{src}:{line}"));
+
+        let function = match (source.what, names.name) {
+            ("main", _) => "main chunk".into(),
+            (_, Some(name)) => format!("function '{name}'"),
+            ("C", None) => "native function".into(),
+
+            (_, None) => format!(
+                "function <{}:{}>",
+                source.short_src.as_deref().unwrap_or("?"),
+                source.line_defined.unwrap_or_default(),
+            ),
+        };
+
+        Self { location, function }
+    }
+}
+
+/// The Lua stack at the point an error was raised, innermost frame first.
+pub struct Traceback {
+    frames: Vec<Frame>,
+}
+
+impl Traceback {
+    /// Returns the location (`file:line`) of the innermost frame running Lua code.
+    pub fn location(&self) -> Option<&str> {
+        self.frames
+            .iter()
+            .find_map(|frame| frame.location.as_deref())
+    }
+}
+
+impl Display for Traceback {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for frame in &self.frames {
+            write!(
+                f,
+                "\n\t{}: in {}",
+                frame.location.as_deref().unwrap_or("[C]"),
+                frame.function,
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Records the stack in the app data and passes the error through unchanged.
+fn capture<'lua>(lua: &'lua Lua, e: LuaValue<'lua>) -> LuaResult<LuaValue<'lua>> {
+    // level 0 is the handler itself.
+    let mut frames = (1..)
+        .map_while(|level| lua.inspect_stack(level))
+        .map(|debug| Frame::new(&debug))
+        .collect::<Vec<_>>();
+
+    // the outermost frame is `xpcall`.
+    frames.pop();
+    lua.set_app_data(Traceback { frames });
+
+    Ok(e)
+}
+
+/// Sets up the VM for [`call`]. Must run before any scripts are loaded.
+pub fn install(lua: &Lua) -> LuaResult<()> {
+    let xpcall: LuaFunction<'_> = lua.globals().get("xpcall")?;
+    lua.set_named_registry_value(XPCALL_KEY, xpcall)?;
+    lua.set_named_registry_value(HANDLER_KEY, lua.create_function(capture)?)?;
+
+    Ok(())
+}
+
+/// Calls `f`, capturing the traceback if it raises an error.
+pub fn call<'lua, R: FromLuaMulti<'lua>>(
+    lua: &'lua Lua,
+    f: LuaFunction<'lua>,
+    args: impl IntoLuaMulti<'lua>,
+) -> (LuaResult<R>, Option<Traceback>) {
+    lua.remove_app_data::<Traceback>();
+
+    let result = (|| {
+        let xpcall: LuaFunction<'_> = lua.named_registry_value(XPCALL_KEY)?;
+        let handler: LuaFunction<'_> = lua.named_registry_value(HANDLER_KEY)?;
+        let mut args = args.into_lua_multi(lua)?;
+        args.push_front(LuaValue::Function(handler));
+        args.push_front(LuaValue::Function(f));
+
+        let mut results: LuaMultiValue<'_> = xpcall.call(args)?;
+        let ok = results.pop_front().and_then(|ok| ok.as_boolean()) == Some(true);
+
+        if ok {
+            return R::from_lua_multi(results, lua);
+        }
+
+        Err(match results.pop_front().unwrap_or(LuaValue::Nil) {
+            LuaValue::Error(e) => e,
+            value => LuaError::runtime(value.to_string()?),
+        })
+    })();
+
+    (result, lua.remove_app_data::<Traceback>())
+}
@@ -1,3 +1,4 @@
+use std::collections::{BTreeMap, HashMap};
 use std::ops::Deref;
 use std::sync::Arc;
 
@@ -11,8 +12,12 @@ use scraper::selector::ToCss;
 use scraper::{element_ref, Node};
 use scraper::{CaseSensitivity, ElementRef, Html, Selector};
 use time::{Date, Month, OffsetDateTime, Time, UtcOffset};
-use time_tz::{timezones, OffsetResult, PrimitiveDateTimeExt};
-use tracing::warn;
+use tracing::debug;
+
+use crate::config::Timezone;
+
+use super::date::DefaultTimezone;
+use super::text;
 
 #[derive(From, Clone)]
 #[from(forward)]
@@ -58,7 +63,7 @@ impl Deref for Buffer {
     }
 }
 
-struct Stringified(String);
+pub struct Stringified(String);
 
 impl Deref for Stringified {
     type Target = str;
@@ -142,27 +147,25 @@ impl<'lua> FromLua<'lua> for PubDate {
         })?;
         let datetime = date.with_time(time);
 
-        if let Some(name) = tz {
-            let name = name.0;
-            let tz = timezones::get_by_name(&name)
+        let tz = if let Some(NonEmptyString(name)) = tz {
+            let tz = Timezone::from_name(&name)
                 .ok_or_else(|| LuaError::runtime(format_args!("unknown timezone '{name}'")))?;
 
-            match datetime.assume_timezone(tz) {
-                OffsetResult::Some(dt) => Ok(Self(dt)),
-
-                OffsetResult::Ambiguous(lhs, rhs) => {
-                    warn!(
-                        "Datetime {datetime} is ambiguous in the timezone `{name}`: \
-                            could be {lhs} or {rhs}; picking the former"
-                    );
-
-                    Ok(Self(lhs))
-                }
-
-                OffsetResult::None => Err(LuaError::runtime(format!(
-                    "datetime {datetime} is invalid in timezone '{name}'"
-                ))),
-            }
+            Some(tz)
+        } else if utc_offset.is_none() {
+            // the feed's timezone only applies if neither is specified.
+            lua.app_data_ref::<DefaultTimezone>().and_then(|tz| tz.0)
+        } else {
+            None
+        };
+
+        if let Some(tz) = tz {
+            tz.assume(datetime).map(Self).ok_or_else(|| {
+                LuaError::runtime(format!(
+                    "datetime {datetime} is invalid in timezone '{}'",
+                    tz.name(),
+                ))
+            })
         } else if let Some(whole_minutes) = utc_offset {
             let hours: i8 = whole_minutes.div_euclid(60).try_into().map_err(|_| {
                 LuaError::runtime(format!("UTC offset {whole_minutes} is too large"))
@@ -175,20 +178,69 @@ impl<'lua> FromLua<'lua> for PubDate {
             Ok(Self(datetime.assume_offset(utc_offset)))
         } else {
             Err(LuaError::runtime(
-                "neither 'tz' nor 'utcOffset' was specified",
+                "neither 'tz' nor 'utcOffset' was specified, and the feed has no timezone",
             ))
         }
     }
 }
 
+#[derive(Clone)]
+pub struct LuaEnclosure {
+    pub url: String,
+    pub mime_type: String,
+    pub length: Option<u64>,
+}
+
+impl<'lua> FromLua<'lua> for LuaEnclosure {
+    fn from_lua(value: LuaValue<'lua>, lua: &'lua Lua) -> LuaResult<Self> {
+        let tbl = LuaTable::from_lua(value, lua)?;
+        let url: NonEmptyString = tbl.get("url").context("'url' is invalid")?;
+        // `type` is accepted for compatibility with older scripts.
+        let mime_type: Option<NonEmptyString> =
+            tbl.get("mimeType").context("'mimeType' is invalid")?;
+        let mime_type = match mime_type {
+            Some(mime_type) => Some(mime_type),
+            None => tbl.get("type").context("'type' is invalid")?,
+        };
+        let Some(NonEmptyString(mime_type)) = mime_type else {
+            return Err(LuaError::runtime("'mimeType' is missing"));
+        };
+
+        match mime_type.split_once('/') {
+            Some((kind, subtype)) if !kind.is_empty() && !subtype.is_empty() => {}
+            _ => {
+                return Err(LuaError::runtime(format!(
+                    "'mimeType' is not a MIME type: `{mime_type}`"
+                )))
+            }
+        }
+
+        let length: Option<u64> = tbl.get("length").context("'length' is invalid")?;
+
+        Ok(Self {
+            url: url.0,
+            mime_type,
+            length,
+        })
+    }
+}
+
 #[derive(Clone)]
 pub struct LuaEntry {
     pub id: String,
     pub title: String,
     pub description: String,
+    pub content: Option<String>,
     pub url: String,
     pub author: Option<String>,
     pub pub_date: Option<OffsetDateTime>,
+    pub enclosure: Option<LuaEnclosure>,
+    pub image: Option<String>,
+    pub comments: Option<String>,
+    pub categories: Vec<String>,
+    pub guid: Option<String>,
+    pub guid_is_permalink: bool,
+    pub extra: BTreeMap<String, String>,
 }
 
 impl<'lua> FromLua<'lua> for LuaEntry {
@@ -196,20 +248,57 @@ impl<'lua> FromLua<'lua> for LuaEntry {
         let entry = LuaTable::from_lua(value, lua)?;
         let id: NonEmptyString = entry.get("id").context("'id' is invalid")?;
         let title: NonEmptyString = entry.get("title").context("'title' is invalid")?;
-        let description: Stringified = entry.get("description").context("'description' is invalid")?;
+        let description: Stringified = entry
+            .get("description")
+            .context("'description' is invalid")?;
+        let content: Option<Stringified> = entry.get("content").context("'content' is invalid")?;
         let url: Stringified = entry.get("url").context("'url' is invalid")?;
         let author: Option<Stringified> = entry.get("author").context("'author' is invalid")?;
         let pub_date: Option<PubDate> = entry.get("pubDate").context("'pubDate' is invalid")?;
+        let enclosure: Option<LuaEnclosure> =
+            entry.get("enclosure").context("'enclosure' is invalid")?;
+        let image: Option<Stringified> = entry.get("image").context("'image' is invalid")?;
+        let comments: Option<Stringified> =
+            entry.get("comments").context("'comments' is invalid")?;
+        let categories: Option<Vec<Stringified>> =
+            entry.get("categories").context("'categories' is invalid")?;
+        let guid: Option<NonEmptyString> = entry.get("guid").context("'guid' is invalid")?;
+        let guid_is_permalink: Option<bool> = entry
+            .get("guidIsPermalink")
+            .context("'guidIsPermalink' is invalid")?;
+        let extra: Option<BTreeMap<String, Stringified>> =
+            entry.get("extra").context("'extra' is invalid")?;
 
         Ok(LuaEntry {
             id: id.0,
             title: title.0,
             description: description.0,
+            content: content
+                .map(|content| content.0)
+                .filter(|content| !content.is_empty()),
             url: url.0,
             author: author
                 .map(|author| author.0)
                 .filter(|author| !author.is_empty()),
             pub_date: pub_date.map(|pub_date| pub_date.0),
+            enclosure,
+            image: image.map(|image| image.0).filter(|image| !image.is_empty()),
+            comments: comments
+                .map(|comments| comments.0)
+                .filter(|comments| !comments.is_empty()),
+            categories: categories
+                .unwrap_or_default()
+                .into_iter()
+                .map(|category| category.0)
+                .filter(|category| !category.is_empty())
+                .collect(),
+            guid: guid.map(|guid| guid.0),
+            guid_is_permalink: guid_is_permalink.unwrap_or(false),
+            extra: extra
+                .unwrap_or_default()
+                .into_iter()
+                .map(|(name, value)| (name, value.0))
+                .collect(),
         })
     }
 }
@@ -234,6 +323,79 @@ impl<'lua> FromLua<'lua> for LuaEntries {
     }
 }
 
+/// Feed metadata set by the script in `ctx.channel`.
+#[derive(Clone, Default)]
+pub struct LuaChannelInfo {
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub link: Option<String>,
+}
+
+impl<'lua> FromLua<'lua> for LuaChannelInfo {
+    fn from_lua(value: LuaValue<'lua>, lua: &'lua Lua) -> LuaResult<Self> {
+        let tbl = LuaTable::from_lua(value, lua)?;
+        let title: Option<NonEmptyString> = tbl.get("title").context("'title' is invalid")?;
+        let description: Option<Stringified> =
+            tbl.get("description").context("'description' is invalid")?;
+        let link: Option<NonEmptyString> = tbl.get("link").context("'link' is invalid")?;
+
+        Ok(Self {
+            title: title.map(|s| s.0),
+            description: description.map(|s| s.0),
+            link: link.map(|s| s.0),
+        })
+    }
+}
+
+fn parse_selector(s: &str) -> LuaResult<Arc<Selector>> {
+    Selector::parse(s)
+        .map(Arc::new)
+        .map_err(|e| LuaError::runtime(format_args!("could not parse the CSS selector: {e}")))
+}
+
+/// Selectors parsed from strings passed where a `Selector` is expected, keyed by their source, so
+/// that scripts don't re-parse the same selector for every entry. Stored in the Lua VM's app data.
+#[derive(Default)]
+pub struct SelectorCache {
+    selectors: HashMap<String, Arc<Selector>>,
+    hits: u64,
+    misses: u64,
+}
+
+impl SelectorCache {
+    /// The number of cached selectors after which the cache is cleared (in case a script builds
+    /// selectors dynamically).
+    const MAX_SIZE: usize = 1024;
+
+    fn get_or_parse(&mut self, s: &str) -> LuaResult<Arc<Selector>> {
+        if let Some(selector) = self.selectors.get(s) {
+            self.hits += 1;
+
+            return Ok(selector.clone());
+        }
+
+        self.misses += 1;
+        let selector = parse_selector(s)?;
+
+        if self.selectors.len() >= Self::MAX_SIZE {
+            self.selectors.clear();
+        }
+
+        self.selectors.insert(s.into(), selector.clone());
+
+        Ok(selector)
+    }
+
+    pub fn log_stats(&self) {
+        debug!(
+            hits = self.hits,
+            misses = self.misses,
+            size = self.selectors.len(),
+            "Selector cache stats",
+        );
+    }
+}
+
 #[derive(From, Clone)]
 pub struct SelectorWrapper(Arc<Selector>);
 
@@ -244,13 +406,18 @@ impl SelectorWrapper {
 }
 
 impl FromLua<'_> for SelectorWrapper {
-    fn from_lua(value: LuaValue<'_>, _lua: &Lua) -> LuaResult<Self> {
+    fn from_lua(value: LuaValue<'_>, lua: &Lua) -> LuaResult<Self> {
         match value {
             LuaValue::UserData(ud) => ud.borrow::<Self>().map(|this| this.clone()),
 
-            LuaValue::String(s) => Ok(Self(Arc::new(Selector::parse(s.to_str()?).map_err(
-                |e| LuaError::runtime(format_args!("could not parse the CSS selector: {e}")),
-            )?))),
+            LuaValue::String(s) => {
+                let s = s.to_str()?;
+
+                match lua.app_data_mut::<SelectorCache>() {
+                    Some(mut cache) => cache.get_or_parse(s).map(Self),
+                    None => parse_selector(s).map(Self),
+                }
+            }
 
             _ => Err(LuaError::FromLuaConversionError {
                 from: value.type_name(),
@@ -280,6 +447,18 @@ impl LuaHtml {
         ))
     }
 
+    fn select_first(
+        _lua: &Lua,
+        this: &Self,
+        selector: SelectorWrapper,
+    ) -> LuaResult<Option<LuaElementRef>> {
+        Ok(this
+            .0
+            .select(&selector.0)
+            .next()
+            .map(|element| LuaElementRef::from_node_id(this.0.clone(), element.id()).unwrap()))
+    }
+
     fn root(_lua: &Lua, this: &Self, _: ()) -> LuaResult<LuaElementRef> {
         Ok(LuaElementRef::new(this.0.clone(), |html| {
             html.root_element()
@@ -290,6 +469,7 @@ impl LuaHtml {
 impl LuaUserData for LuaHtml {
     fn add_methods<'lua, M: LuaUserDataMethods<'lua, Self>>(methods: &mut M) {
         methods.add_method("select", Self::select);
+        methods.add_method("selectFirst", Self::select_first);
         methods.add_method("root", Self::root);
     }
 }
@@ -312,11 +492,27 @@ impl LuaHtmlSelect {
             })
         }))
     }
+
+    fn collect<'lua>(lua: &'lua Lua, this: &mut Self, _: ()) -> LuaResult<LuaTable<'lua>> {
+        let mut items = vec![];
+
+        while let Some(item) = Self::call(lua, this, ())? {
+            items.push(item);
+        }
+
+        lua.create_sequence_from(items)
+    }
+
+    fn len(_lua: &Lua, this: &Self, _: ()) -> LuaResult<usize> {
+        Ok(this.borrow_select().clone().count())
+    }
 }
 
 impl LuaUserData for LuaHtmlSelect {
     fn add_methods<'lua, M: LuaUserDataMethods<'lua, Self>>(methods: &mut M) {
         methods.add_meta_method_mut("__call", Self::call);
+        methods.add_method_mut("collect", Self::collect);
+        methods.add_meta_method("__len", Self::len);
     }
 }
 
@@ -519,11 +715,33 @@ impl LuaChildren {
             None
         }))
     }
+
+    fn collect<'lua>(lua: &'lua Lua, this: &mut Self, _: ()) -> LuaResult<LuaTable<'lua>> {
+        let mut items = vec![];
+
+        while let Some(item) = Self::call(lua, this, ())? {
+            items.push(item);
+        }
+
+        lua.create_sequence_from(items)
+    }
+
+    fn len(_lua: &Lua, this: &Self, _: ()) -> LuaResult<usize> {
+        Ok(this.with(|fields| {
+            fields
+                .iter
+                .clone()
+                .filter(|node_ref| !*fields.elements_only || node_ref.value().is_element())
+                .count()
+        }))
+    }
 }
 
 impl LuaUserData for LuaChildren {
     fn add_methods<'lua, M: LuaUserDataMethods<'lua, Self>>(methods: &mut M) {
         methods.add_meta_method_mut("__call", Self::call);
+        methods.add_method_mut("collect", Self::collect);
+        methods.add_meta_method("__len", Self::len);
     }
 }
 
@@ -551,11 +769,33 @@ impl LuaDescendants {
             None
         }))
     }
+
+    fn collect<'lua>(lua: &'lua Lua, this: &mut Self, _: ()) -> LuaResult<LuaTable<'lua>> {
+        let mut items = vec![];
+
+        while let Some(item) = Self::call(lua, this, ())? {
+            items.push(item);
+        }
+
+        lua.create_sequence_from(items)
+    }
+
+    fn len(_lua: &Lua, this: &Self, _: ()) -> LuaResult<usize> {
+        Ok(this.with(|fields| {
+            fields
+                .iter
+                .clone()
+                .filter(|node_ref| !*fields.elements_only || node_ref.value().is_element())
+                .count()
+        }))
+    }
 }
 
 impl LuaUserData for LuaDescendants {
     fn add_methods<'lua, M: LuaUserDataMethods<'lua, Self>>(methods: &mut M) {
         methods.add_meta_method_mut("__call", Self::call);
+        methods.add_method_mut("collect", Self::collect);
+        methods.add_meta_method("__len", Self::len);
     }
 }
 
@@ -834,6 +1074,10 @@ impl LuaElementRef {
         }))
     }
 
+    fn inner_text(_lua: &Lua, this: &Self, _: ()) -> LuaResult<String> {
+        Ok(text::inner_text(*this.borrow_element_ref()))
+    }
+
     fn child_elements(_lua: &Lua, this: &Self, _: ()) -> LuaResult<LuaChildren> {
         let node_id = this.borrow_element_ref().id();
 
@@ -878,6 +1122,59 @@ impl LuaElementRef {
         ))
     }
 
+    fn select_first(
+        _lua: &Lua,
+        this: &Self,
+        selector: SelectorWrapper,
+    ) -> LuaResult<Option<LuaElementRef>> {
+        Ok(this
+            .borrow_element_ref()
+            .select(&selector.0)
+            .next()
+            .map(|element| {
+                LuaElementRef::from_node_id(this.borrow_html().clone(), element.id()).unwrap()
+            }))
+    }
+
+    fn matches(_lua: &Lua, this: &Self, selector: SelectorWrapper) -> LuaResult<bool> {
+        Ok(selector.0.matches(this.borrow_element_ref()))
+    }
+
+    fn closest(
+        _lua: &Lua,
+        this: &Self,
+        selector: SelectorWrapper,
+    ) -> LuaResult<Option<LuaElementRef>> {
+        let element_ref = *this.borrow_element_ref();
+
+        Ok(std::iter::once(element_ref)
+            .chain(element_ref.ancestors().filter_map(ElementRef::wrap))
+            .find(|element| selector.0.matches(element))
+            .map(|element| {
+                LuaElementRef::from_node_id(this.borrow_html().clone(), element.id()).unwrap()
+            }))
+    }
+
+    fn prev_element_sibling(_lua: &Lua, this: &Self, _: ()) -> LuaResult<Option<LuaElementRef>> {
+        Ok(this
+            .borrow_element_ref()
+            .prev_siblings()
+            .find_map(ElementRef::wrap)
+            .map(|element| {
+                LuaElementRef::from_node_id(this.borrow_html().clone(), element.id()).unwrap()
+            }))
+    }
+
+    fn next_element_sibling(_lua: &Lua, this: &Self, _: ()) -> LuaResult<Option<LuaElementRef>> {
+        Ok(this
+            .borrow_element_ref()
+            .next_siblings()
+            .find_map(ElementRef::wrap)
+            .map(|element| {
+                LuaElementRef::from_node_id(this.borrow_html().clone(), element.id()).unwrap()
+            }))
+    }
+
     fn to_string(_lua: &Lua, this: &Self, _: ()) -> LuaResult<String> {
         let mut text = String::new();
 
@@ -909,9 +1206,15 @@ impl LuaUserData for LuaElementRef {
         methods.add_method("hasClass", Self::has_class);
         methods.add_method("classes", Self::classes);
         methods.add_method("text", Self::text);
+        methods.add_method("innerText", Self::inner_text);
+        methods.add_method("prevElementSibling", Self::prev_element_sibling);
+        methods.add_method("nextElementSibling", Self::next_element_sibling);
         methods.add_method("childElements", Self::child_elements);
         methods.add_method("descendantElements", Self::descendant_elements);
         methods.add_method("select", Self::select);
+        methods.add_method("selectFirst", Self::select_first);
+        methods.add_method("matches", Self::matches);
+        methods.add_method("closest", Self::closest);
         methods.add_meta_method("__tostring", Self::to_string);
 
         BaseNodeRef::add_methods(methods);
@@ -934,11 +1237,27 @@ impl LuaAttrs {
             .map(|(k, v)| (k.into(), v.into()))
             .unzip())
     }
+
+    fn collect<'lua>(lua: &'lua Lua, this: &mut Self, _: ()) -> LuaResult<LuaTable<'lua>> {
+        let mut items = vec![];
+
+        while let (Some(name), Some(value)) = Self::call(lua, this, ())? {
+            items.push((name, value));
+        }
+
+        lua.create_table_from(items)
+    }
+
+    fn len(_lua: &Lua, this: &Self, _: ()) -> LuaResult<usize> {
+        Ok(this.borrow_attrs().clone().count())
+    }
 }
 
 impl LuaUserData for LuaAttrs {
     fn add_methods<'lua, M: LuaUserDataMethods<'lua, Self>>(methods: &mut M) {
         methods.add_meta_method_mut("__call", Self::call);
+        methods.add_method_mut("collect", Self::collect);
+        methods.add_meta_method("__len", Self::len);
     }
 }
 
@@ -957,11 +1276,27 @@ impl LuaClasses {
             .with_classes_mut(|classes| classes.next())
             .map(Into::into))
     }
+
+    fn collect<'lua>(lua: &'lua Lua, this: &mut Self, _: ()) -> LuaResult<LuaTable<'lua>> {
+        let mut items = vec![];
+
+        while let Some(item) = Self::call(lua, this, ())? {
+            items.push(item);
+        }
+
+        lua.create_sequence_from(items)
+    }
+
+    fn len(_lua: &Lua, this: &Self, _: ()) -> LuaResult<usize> {
+        Ok(this.borrow_classes().clone().count())
+    }
 }
 
 impl LuaUserData for LuaClasses {
     fn add_methods<'lua, M: LuaUserDataMethods<'lua, Self>>(methods: &mut M) {
         methods.add_meta_method_mut("__call", Self::call);
+        methods.add_method_mut("collect", Self::collect);
+        methods.add_meta_method("__len", Self::len);
     }
 }
 
@@ -978,11 +1313,27 @@ impl LuaElementText {
     fn call(_lua: &Lua, this: &mut Self, _: ()) -> LuaResult<Option<String>> {
         Ok(this.with_text_mut(|text| text.next()).map(Into::into))
     }
+
+    fn collect<'lua>(lua: &'lua Lua, this: &mut Self, _: ()) -> LuaResult<LuaTable<'lua>> {
+        let mut items = vec![];
+
+        while let Some(item) = Self::call(lua, this, ())? {
+            items.push(item);
+        }
+
+        lua.create_sequence_from(items)
+    }
+
+    fn len(_lua: &Lua, this: &Self, _: ()) -> LuaResult<usize> {
+        Ok(this.borrow_text().clone().count())
+    }
 }
 
 impl LuaUserData for LuaElementText {
     fn add_methods<'lua, M: LuaUserDataMethods<'lua, Self>>(methods: &mut M) {
         methods.add_meta_method_mut("__call", Self::call);
+        methods.add_method_mut("collect", Self::collect);
+        methods.add_meta_method("__len", Self::len);
     }
 }
 
@@ -1004,11 +1355,27 @@ impl LuaSelect {
             })
         }))
     }
+
+    fn collect<'lua>(lua: &'lua Lua, this: &mut Self, _: ()) -> LuaResult<LuaTable<'lua>> {
+        let mut items = vec![];
+
+        while let Some(item) = Self::call(lua, this, ())? {
+            items.push(item);
+        }
+
+        lua.create_sequence_from(items)
+    }
+
+    fn len(_lua: &Lua, this: &Self, _: ()) -> LuaResult<usize> {
+        Ok(this.borrow_select().clone().count())
+    }
 }
 
 impl LuaUserData for LuaSelect {
     fn add_methods<'lua, M: LuaUserDataMethods<'lua, Self>>(methods: &mut M) {
         methods.add_meta_method_mut("__call", Self::call);
+        methods.add_method_mut("collect", Self::collect);
+        methods.add_meta_method("__len", Self::len);
     }
 }
 
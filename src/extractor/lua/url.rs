@@ -0,0 +1,154 @@
+use derive_more::From;
+use mlua::prelude::*;
+use reqwest::Url;
+
+use super::types::Stringified;
+
+#[derive(From, Clone)]
+pub struct LuaUrl(Url);
+
+impl LuaUrl {
+    fn to_string(_lua: &Lua, this: &Self, _: ()) -> LuaResult<String> {
+        Ok(this.0.to_string())
+    }
+
+    fn eq(_lua: &Lua, this: &Self, other: LuaUrl) -> LuaResult<bool> {
+        Ok(this.0 == other.0)
+    }
+
+    fn join(_lua: &Lua, this: &Self, url: Stringified) -> LuaResult<LuaUrl> {
+        join_url(&this.0, &url)
+    }
+
+    fn query_param(_lua: &Lua, this: &Self, name: String) -> LuaResult<Option<String>> {
+        Ok(this
+            .0
+            .query_pairs()
+            .find(|(key, _)| *key == name)
+            .map(|(_, value)| value.into_owned()))
+    }
+}
+
+impl FromLua<'_> for LuaUrl {
+    fn from_lua(value: LuaValue<'_>, _lua: &Lua) -> LuaResult<Self> {
+        match value {
+            LuaValue::UserData(ud) => ud.borrow::<Self>().map(|this| this.clone()),
+            LuaValue::String(s) => parse_url(s.to_str()?),
+
+            _ => Err(LuaError::FromLuaConversionError {
+                from: value.type_name(),
+                to: "Url",
+                message: Some("expected string or Url".into()),
+            }),
+        }
+    }
+}
+
+impl LuaUserData for LuaUrl {
+    fn add_fields<'lua, F: LuaUserDataFields<'lua, Self>>(fields: &mut F) {
+        fields.add_field_method_get("scheme", |_, this| Ok(this.0.scheme().to_owned()));
+        fields.add_field_method_get("username", |_, this| {
+            Ok(Some(this.0.username().to_owned()).filter(|username| !username.is_empty()))
+        });
+        fields.add_field_method_get("password", |_, this| {
+            Ok(this.0.password().map(ToOwned::to_owned))
+        });
+        fields.add_field_method_get("host", |_, this| {
+            Ok(this.0.host_str().map(ToOwned::to_owned))
+        });
+        fields.add_field_method_get("port", |_, this| Ok(this.0.port_or_known_default()));
+        fields.add_field_method_get("path", |_, this| Ok(this.0.path().to_owned()));
+        fields.add_field_method_get("query", |_, this| Ok(this.0.query().map(ToOwned::to_owned)));
+        fields.add_field_method_get("fragment", |_, this| {
+            Ok(this.0.fragment().map(ToOwned::to_owned))
+        });
+        fields.add_field_method_get("pathSegments", |_, this| {
+            Ok(this
+                .0
+                .path_segments()
+                .map(|segments| segments.map(ToOwned::to_owned).collect::<Vec<_>>()))
+        });
+    }
+
+    fn add_methods<'lua, M: LuaUserDataMethods<'lua, Self>>(methods: &mut M) {
+        methods.add_meta_method("__tostring", Self::to_string);
+        methods.add_meta_method("__eq", Self::eq);
+        methods.add_method("join", Self::join);
+        methods.add_method("queryParam", Self::query_param);
+    }
+}
+
+fn parse_url(s: &str) -> LuaResult<LuaUrl> {
+    Url::parse(s)
+        .map(LuaUrl)
+        .map_err(|e| LuaError::runtime(format!("could not parse the URL `{s}`: {e}")))
+}
+
+fn join_url(base: &Url, url: &str) -> LuaResult<LuaUrl> {
+    base.join(url)
+        .map(LuaUrl)
+        .map_err(|e| LuaError::runtime(format!("could not join the URL `{url}` to `{base}`: {e}")))
+}
+
+/// `feedgen.url.parse(s)`: parses an absolute URL.
+pub fn parse(_lua: &Lua, s: String) -> LuaResult<LuaUrl> {
+    parse_url(&s)
+}
+
+/// `feedgen.url.join(base, url)`: resolves a (possibly relative) URL against a base URL.
+pub fn join(_lua: &Lua, (base, url): (LuaUrl, Stringified)) -> LuaResult<LuaUrl> {
+    join_url(&base.0, &url)
+}
+
+/// `feedgen.url.encodeQuery(params)`: encodes a table as a query string.
+///
+/// Accepts either a map of names to values (encoded in the order of the names) or a list of
+/// `{name, value}` pairs (which allows repeating names).
+pub fn encode_query(_lua: &Lua, params: LuaTable<'_>) -> LuaResult<String> {
+    let mut pairs = vec![];
+
+    if params.raw_len() > 0 {
+        for pair in params.sequence_values::<LuaTable<'_>>() {
+            let pair = pair.context("expected a list of {name, value} pairs")?;
+            pairs.push((pair.get::<_, String>(1)?, pair.get::<_, String>(2)?));
+        }
+    } else {
+        for pair in params.pairs::<String, String>() {
+            pairs.push(pair.context("expected a table of names to values")?);
+        }
+
+        pairs.sort();
+    }
+
+    Ok(pairs
+        .iter()
+        .map(|(name, value)| {
+            format!(
+                "{}={}",
+                urlencoding::encode(name),
+                urlencoding::encode(value)
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("&"))
+}
+
+/// `feedgen.url.decodeQuery(query)`: decodes a query string (with or without the leading `?`) as a
+/// table mapping names to values. If a name is repeated, the last value is used.
+pub fn decode_query<'lua>(lua: &'lua Lua, query: String) -> LuaResult<LuaTable<'lua>> {
+    let query = query.strip_prefix('?').unwrap_or(&query);
+    let result = lua.create_table()?;
+
+    let decode = |s: &str| {
+        urlencoding::decode(&s.replace('+', " "))
+            .map(|s| s.into_owned())
+            .map_err(|e| LuaError::runtime(format!("could not decode `{s}`: {e}")))
+    };
+
+    for pair in query.split('&').filter(|pair| !pair.is_empty()) {
+        let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+        result.set(decode(name)?, decode(value)?)?;
+    }
+
+    Ok(result)
+}
@@ -0,0 +1,42 @@
+use mlua::prelude::*;
+use scraper::Html;
+
+use super::types::Stringified;
+
+/// `feedgen.util.trim(s)`: removes leading and trailing whitespace.
+pub fn trim(_lua: &Lua, s: Stringified) -> LuaResult<String> {
+    Ok(s.trim().into())
+}
+
+/// `feedgen.util.collapseWhitespace(s)`: replaces each run of whitespace with a single space and
+/// trims the result.
+pub fn collapse_whitespace(_lua: &Lua, s: Stringified) -> LuaResult<String> {
+    Ok(s.split_whitespace().collect::<Vec<_>>().join(" "))
+}
+
+/// `feedgen.util.truncate(s, maxLength, ellipsis)`: shortens `s` to at most `maxLength` characters
+/// (not bytes), ending it with `ellipsis` (`"…"` by default) if anything was cut off.
+pub fn truncate(
+    _lua: &Lua,
+    (s, max_len, ellipsis): (Stringified, usize, Option<String>),
+) -> LuaResult<String> {
+    if s.chars().count() <= max_len {
+        return Ok(s.to_string());
+    }
+
+    let ellipsis = ellipsis.unwrap_or_else(|| "…".into());
+    let kept = max_len.saturating_sub(ellipsis.chars().count());
+    let end = s.char_indices().nth(kept).map_or(s.len(), |(idx, _)| idx);
+    let mut result = s[..end].trim_end().to_owned();
+    result.push_str(&ellipsis);
+
+    Ok(result)
+}
+
+/// `feedgen.util.stripTags(s)`: parses `s` as an HTML fragment and returns its text content, with
+/// character references decoded.
+pub fn strip_tags(_lua: &Lua, s: Stringified) -> LuaResult<String> {
+    let html = Html::parse_fragment(&s);
+
+    Ok(html.root_element().text().collect())
+}
@@ -0,0 +1,244 @@
+use mlua::prelude::*;
+use sxd_document::dom::{ChildOfElement, ChildOfRoot, Element};
+use sxd_document::QName;
+
+use super::types::Buffer;
+
+/// The registry key of the metatable shared by XML nodes.
+const NODE_METATABLE: &str = "feedgen.xml.node";
+
+fn qualified_name(prefix: Option<&str>, name: QName<'_>) -> String {
+    match prefix {
+        Some(prefix) => format!("{prefix}:{}", name.local_part()),
+        None => name.local_part().into(),
+    }
+}
+
+/// Formats a name in the Clark notation (`{namespace}local`).
+fn clark_name(namespace: &str, local_name: &str) -> String {
+    format!("{{{namespace}}}{local_name}")
+}
+
+fn make_node<'lua>(lua: &'lua Lua, ty: &str) -> LuaResult<LuaTable<'lua>> {
+    let node = lua.create_table()?;
+    node.set("type", ty)?;
+    node.set_metatable(Some(lua.named_registry_value(NODE_METATABLE)?));
+
+    Ok(node)
+}
+
+fn convert_element<'lua>(lua: &'lua Lua, element: Element<'_>) -> LuaResult<LuaTable<'lua>> {
+    let node = make_node(lua, "element")?;
+    let name = element.name();
+    node.set("name", qualified_name(element.preferred_prefix(), name))?;
+    node.set("localName", name.local_part())?;
+    node.set("namespace", name.namespace_uri())?;
+    node.set("prefix", element.preferred_prefix())?;
+
+    let attrs = lua.create_table()?;
+
+    for attr in element.attributes() {
+        let name = attr.name();
+        attrs.set(qualified_name(attr.preferred_prefix(), name), attr.value())?;
+
+        // namespaced attributes can also be looked up regardless of the prefix.
+        if let Some(namespace) = name.namespace_uri() {
+            attrs.set(clark_name(namespace, name.local_part()), attr.value())?;
+        }
+    }
+
+    node.set("attrs", attrs)?;
+
+    let children = lua.create_table()?;
+
+    for child in element.children() {
+        let child = match child {
+            ChildOfElement::Element(element) => convert_element(lua, element)?,
+
+            ChildOfElement::Text(text) => {
+                let node = make_node(lua, "text")?;
+                node.set("text", text.text())?;
+
+                node
+            }
+
+            ChildOfElement::Comment(comment) => {
+                let node = make_node(lua, "comment")?;
+                node.set("text", comment.text())?;
+
+                node
+            }
+
+            ChildOfElement::ProcessingInstruction(pi) => {
+                let node = make_node(lua, "processingInstruction")?;
+                node.set("target", pi.target())?;
+                node.set("value", pi.value())?;
+
+                node
+            }
+        };
+
+        children.push(child)?;
+    }
+
+    node.set("children", children)?;
+
+    Ok(node)
+}
+
+/// `feedgen.parseXml(source)`: parses a buffer as an XML document.
+pub fn parse_xml(lua: &Lua, buf: Buffer) -> LuaResult<LuaTable<'_>> {
+    let package = sxd_document::parser::parse(&buf)
+        .map_err(|e| LuaError::runtime(format!("could not parse the XML document: {e:?}")))?;
+    let document = package.as_document();
+
+    let node = make_node(lua, "document")?;
+    let children = lua.create_table()?;
+
+    for child in document.root().children() {
+        let child = match child {
+            ChildOfRoot::Element(element) => {
+                let element = convert_element(lua, element)?;
+                node.set("root", element.clone())?;
+
+                element
+            }
+
+            ChildOfRoot::Comment(comment) => {
+                let node = make_node(lua, "comment")?;
+                node.set("text", comment.text())?;
+
+                node
+            }
+
+            ChildOfRoot::ProcessingInstruction(pi) => {
+                let node = make_node(lua, "processingInstruction")?;
+                node.set("target", pi.target())?;
+                node.set("value", pi.value())?;
+
+                node
+            }
+        };
+
+        children.push(child)?;
+    }
+
+    node.set("children", children)?;
+
+    Ok(node)
+}
+
+fn is_element_named(
+    node: &LuaTable<'_>,
+    local_name: &str,
+    namespace: Option<&str>,
+) -> LuaResult<bool> {
+    if node.get::<_, String>("type")? != "element" {
+        return Ok(false);
+    }
+
+    if node.get::<_, String>("localName")? != local_name {
+        return Ok(false);
+    }
+
+    Ok(match namespace {
+        Some(namespace) => {
+            node.get::<_, Option<String>>("namespace")?.as_deref() == Some(namespace)
+        }
+        None => true,
+    })
+}
+
+/// `node:child(localName, namespace)`: returns the first child element with the given name.
+fn child<'lua>(
+    _lua: &'lua Lua,
+    (this, local_name, namespace): (LuaTable<'lua>, String, Option<String>),
+) -> LuaResult<Option<LuaTable<'lua>>> {
+    let children: LuaTable<'_> = this.get("children")?;
+
+    for child in children.sequence_values::<LuaTable<'_>>() {
+        let child = child?;
+
+        if is_element_named(&child, &local_name, namespace.as_deref())? {
+            return Ok(Some(child));
+        }
+    }
+
+    Ok(None)
+}
+
+/// `node:childrenNamed(localName, namespace)`: returns a list of the child elements with the
+/// given name.
+fn children_named<'lua>(
+    lua: &'lua Lua,
+    (this, local_name, namespace): (LuaTable<'lua>, String, Option<String>),
+) -> LuaResult<LuaTable<'lua>> {
+    let children: LuaTable<'_> = this.get("children")?;
+    let result = lua.create_table()?;
+
+    for child in children.sequence_values::<LuaTable<'_>>() {
+        let child = child?;
+
+        if is_element_named(&child, &local_name, namespace.as_deref())? {
+            result.push(child)?;
+        }
+    }
+
+    Ok(result)
+}
+
+fn collect_text(node: &LuaTable<'_>, result: &mut String) -> LuaResult<()> {
+    match &*node.get::<_, String>("type")? {
+        "text" => result.push_str(&node.get::<_, String>("text")?),
+
+        "element" | "document" => {
+            let children: LuaTable<'_> = node.get("children")?;
+
+            for child in children.sequence_values::<LuaTable<'_>>() {
+                collect_text(&child?, result)?;
+            }
+        }
+
+        _ => {}
+    }
+
+    Ok(())
+}
+
+/// `node:text()`: returns the concatenated text of the node's descendants.
+fn text(_lua: &Lua, this: LuaTable<'_>) -> LuaResult<String> {
+    let mut result = String::new();
+    collect_text(&this, &mut result)?;
+
+    Ok(result)
+}
+
+/// `node:attr(localName, namespace)`: returns the value of an attribute.
+///
+/// Without a namespace, the attribute is looked up by its name as written in the document.
+fn attr(
+    _lua: &Lua,
+    (this, name, namespace): (LuaTable<'_>, String, Option<String>),
+) -> LuaResult<Option<String>> {
+    let Some(attrs) = this.get::<_, Option<LuaTable<'_>>>("attrs")? else {
+        return Ok(None);
+    };
+
+    match namespace {
+        Some(namespace) => attrs.get(clark_name(&namespace, &name)),
+        None => attrs.get(name),
+    }
+}
+
+/// Registers the metatable shared by the nodes returned by `feedgen.parseXml`.
+pub fn register_node_metatable(lua: &Lua) -> LuaResult<()> {
+    let methods = lua.create_table()?;
+    methods.set("child", lua.create_function(child)?)?;
+    methods.set("childrenNamed", lua.create_function(children_named)?)?;
+    methods.set("text", lua.create_function(text)?)?;
+    methods.set("attr", lua.create_function(attr)?)?;
+
+    let metatable = lua.create_table()?;
+    metatable.set("__index", methods)?;
+    lua.set_named_registry_value(NODE_METATABLE, metatable)
+}
@@ -0,0 +1,80 @@
+use anyhow::Result;
+use scraper::Html;
+use tracing::warn;
+
+use crate::config;
+use crate::metadata::{self, Article};
+
+use super::{Context as ExtractorContext, Entry, Extraction, Extractor};
+
+/// Emits the articles described by the page's JSON-LD, microdata, or OpenGraph metadata.
+pub struct MetadataExtractor;
+
+impl MetadataExtractor {
+    pub fn from_cfg(_cfg: &config::MetadataExtractorConfig) -> Self {
+        Self
+    }
+}
+
+fn convert_article(ctx: &ExtractorContext<'_>, idx: usize, article: Article) -> Option<Entry> {
+    let Some(title) = article.title else {
+        warn!("Article #{idx} has no title");
+
+        return None;
+    };
+
+    // a page describing itself may omit its URL.
+    let url = match article.url {
+        Some(url) => ctx
+            .base_url()
+            .join(&url)
+            .inspect_err(|e| warn!("The URL for article #{idx} could not be parsed: {e:#}"))
+            .ok()?,
+
+        None => ctx.base_url().clone(),
+    };
+
+    Some(Entry {
+        id: url.to_string(),
+        title,
+        description: article
+            .description
+            .map(|description| html_escape::encode_text(&description).into_owned())
+            .unwrap_or_default(),
+        content: None,
+        url,
+        author: article.author,
+        pub_date: article.published,
+        enclosure: None,
+        image: article.image.and_then(|image| {
+            ctx.base_url()
+                .join(&image)
+                .inspect_err(|e| {
+                    warn!("The image URL for article #{idx} could not be parsed: {e:#}")
+                })
+                .ok()
+        }),
+        comments: None,
+        categories: article.keywords,
+        guid: None,
+        guid_is_permalink: false,
+        extra: Default::default(),
+    })
+}
+
+impl Extractor for MetadataExtractor {
+    fn extract(&mut self, ctx: ExtractorContext<'_>, html: &str) -> Result<Extraction> {
+        let html = Html::parse_document(html);
+
+        let articles = metadata::extract(&html, ctx.timezone());
+        let article_count = articles.len();
+        let entries = articles
+            .into_iter()
+            .enumerate()
+            .filter_map(|(idx, article)| convert_article(&ctx, idx + 1, article))
+            .collect::<Vec<_>>();
+        let skipped = article_count - entries.len();
+
+        Ok(Extraction::from(entries).with_skipped(skipped))
+    }
+}
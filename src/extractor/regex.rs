@@ -0,0 +1,164 @@
+use anyhow::{bail, Result};
+use regex_lite::{Captures, Regex};
+use tracing::warn;
+
+use crate::config::{self, DateTimeFormats};
+use crate::reldate::{self, Locale};
+
+use super::{Context as ExtractorContext, Enclosure, Entry, Extraction, Extractor};
+
+const DEFAULT_ENCLOSURE_TYPE: &str = "application/octet-stream";
+
+/// The capture group names recognized in the entry pattern.
+const GROUP_NAMES: &[&str] = &[
+    "id",
+    "title",
+    "description",
+    "content",
+    "url",
+    "author",
+    "pub_date",
+    "enclosure_url",
+    "enclosure_type",
+    "enclosure_length",
+    "image",
+    "comments",
+    "categories",
+    "guid",
+];
+
+pub struct RegexExtractor {
+    entry: Regex,
+    pub_date_format: DateTimeFormats,
+    relative_date_locales: Vec<Locale>,
+    guid_is_permalink: bool,
+}
+
+impl RegexExtractor {
+    pub fn from_cfg(cfg: &config::RegexExtractorConfig) -> Result<Self> {
+        let entry = cfg.entry.as_regex().clone();
+
+        for name in entry.capture_names().flatten() {
+            if !GROUP_NAMES.contains(&name) {
+                bail!("the entry pattern has an unknown capture group `{name}`");
+            }
+        }
+
+        for name in ["title", "url"] {
+            if !entry.capture_names().flatten().any(|group| group == name) {
+                bail!("the entry pattern must have a capture group `{name}`");
+            }
+        }
+
+        Ok(Self {
+            entry,
+            pub_date_format: cfg.pub_date_format.clone().unwrap_or_default(),
+            relative_date_locales: cfg.relative_date_locales.clone(),
+            guid_is_permalink: cfg.guid_is_permalink,
+        })
+    }
+
+    fn extract_entry(
+        &self,
+        ctx: &ExtractorContext<'_>,
+        idx: usize,
+        captures: Captures<'_>,
+    ) -> Option<Entry> {
+        let group = |name: &str| {
+            captures
+                .name(name)
+                .map(|m| m.as_str().trim())
+                .filter(|s| !s.is_empty())
+        };
+
+        let join_url = |name: &str| {
+            let url = group(name)?;
+
+            ctx.base_url()
+                .join(url)
+                .inspect_err(|e| {
+                    warn!("The {name} URL for entry #{idx} could not be parsed: {e:#}")
+                })
+                .ok()
+        };
+
+        if group("url").is_none() {
+            warn!("The url group of entry #{idx} is empty");
+
+            return None;
+        }
+
+        let Some(title) = group("title") else {
+            warn!("The title group of entry #{idx} is empty");
+
+            return None;
+        };
+        let url = join_url("url")?;
+
+        let pub_date = group("pub_date").and_then(|s| {
+            self.pub_date_format
+                .parse(s, ctx.timezone())
+                .or_else(|e| {
+                    reldate::parse(s, ctx.fetch_time(), &self.relative_date_locales).ok_or(e)
+                })
+                .inspect_err(|e| warn!("The date `{s}` could not be parsed: {e:#}"))
+                .ok()
+        });
+
+        let enclosure = join_url("enclosure_url").map(|url| Enclosure {
+            url,
+            mime_type: group("enclosure_type")
+                .unwrap_or(DEFAULT_ENCLOSURE_TYPE)
+                .into(),
+            length: group("enclosure_length").and_then(|s| {
+                s.parse()
+                    .inspect_err(|e| warn!("The enclosure length `{s}` could not be parsed: {e:#}"))
+                    .ok()
+            }),
+        });
+
+        // the categories are separated by commas.
+        let categories = group("categories")
+            .map(|s| {
+                s.split(',')
+                    .map(str::trim)
+                    .filter(|s| !s.is_empty())
+                    .map(str::to_owned)
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Some(Entry {
+            // the URL is used as the id if there's no group for it.
+            id: group("id").unwrap_or(url.as_str()).into(),
+            title: title.into(),
+            description: group("description").unwrap_or_default().into(),
+            content: group("content").map(Into::into),
+            url,
+            author: group("author").map(Into::into),
+            pub_date,
+            enclosure,
+            image: join_url("image"),
+            comments: join_url("comments"),
+            categories,
+            guid: group("guid").map(Into::into),
+            guid_is_permalink: self.guid_is_permalink,
+            extra: Default::default(),
+        })
+    }
+}
+
+impl Extractor for RegexExtractor {
+    fn extract(&mut self, ctx: ExtractorContext<'_>, text: &str) -> Result<Extraction> {
+        let found = self.entry.captures_iter(text).collect::<Vec<_>>();
+        let found_count = found.len();
+        let entries = found
+            .into_iter()
+            .enumerate()
+            .filter_map(|(idx, captures)| self.extract_entry(&ctx, idx + 1, captures))
+            .collect::<Vec<_>>();
+        let skipped = found_count - entries.len();
+
+        Ok(Extraction::from(entries).with_skipped(skipped))
+    }
+}
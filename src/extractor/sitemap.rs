@@ -0,0 +1,266 @@
+use std::collections::{HashSet, VecDeque};
+
+use anyhow::{anyhow, bail, Context as _, Result};
+use reqwest::Url;
+use sxd_document::dom::{ChildOfElement, ChildOfRoot, Element};
+use time::format_description::well_known::Rfc3339;
+use time::macros::format_description;
+use time::{Date, OffsetDateTime};
+use tokio::runtime::Handle;
+use tracing::{debug, warn};
+
+use crate::config::{self, Timezone};
+use crate::fetch::fetch_page;
+
+use super::{Context as ExtractorContext, Entry, Extraction, Extractor};
+
+fn child_elements(element: Element<'_>) -> impl Iterator<Item = Element<'_>> {
+    element
+        .children()
+        .into_iter()
+        .filter_map(|child| match child {
+            ChildOfElement::Element(element) => Some(element),
+            _ => None,
+        })
+}
+
+fn child<'d>(element: Element<'d>, name: &str) -> Option<Element<'d>> {
+    child_elements(element).find(|child| child.name().local_part() == name)
+}
+
+fn child_text(element: Element<'_>, name: &str) -> Option<String> {
+    let text = child(element, name)?
+        .children()
+        .into_iter()
+        .filter_map(|child| match child {
+            ChildOfElement::Text(text) => Some(text.text()),
+            _ => None,
+        })
+        .collect::<String>();
+
+    Some(text.trim().to_owned()).filter(|s| !s.is_empty())
+}
+
+/// Parses a `lastmod` value (a W3C datetime, possibly without the time). A date is midnight in
+/// `tz` (UTC if unset).
+fn parse_lastmod(s: &str, tz: Option<Timezone>) -> Option<OffsetDateTime> {
+    OffsetDateTime::parse(s, &Rfc3339)
+        .ok()
+        .or_else(|| {
+            let datetime = Date::parse(s, format_description!("[year]-[month]-[day]"))
+                .ok()?
+                .midnight();
+
+            match tz {
+                Some(tz) => tz.assume(datetime),
+                None => Some(datetime.assume_utc()),
+            }
+        })
+        .or_else(|| {
+            warn!("The lastmod date `{s}` could not be parsed");
+
+            None
+        })
+}
+
+/// Emits the URLs of a sitemap modified since the previous update.
+pub struct SitemapExtractor {
+    max_sitemaps: usize,
+    max_age: Option<time::Duration>,
+}
+
+impl SitemapExtractor {
+    pub fn from_cfg(cfg: &config::SitemapExtractorConfig) -> Self {
+        Self {
+            max_sitemaps: cfg.max_sitemaps.max(1),
+            max_age: cfg
+                .max_age
+                .and_then(|max_age| std::time::Duration::from(max_age).try_into().ok()),
+        }
+    }
+
+    /// Retrieves the time of the previous update of the feed.
+    fn last_update(ctx: &ExtractorContext<'_>) -> Result<Option<OffsetDateTime>> {
+        let (Some(storage), Some(feed_name)) = (ctx.storage(), ctx.feed_name()) else {
+            return Ok(None);
+        };
+
+        Handle::try_current()?.block_on(async {
+            let mut tx = storage.begin().await?;
+            let last_update = tx.get_feed_last_updated(feed_name).await?;
+            tx.commit().await?;
+
+            anyhow::Ok(last_update)
+        })
+    }
+
+    /// Retrieves a sitemap referenced by a sitemap index.
+    fn fetch(ctx: &ExtractorContext<'_>, url: &Url) -> Result<String> {
+        let http_client = ctx
+            .http_client()
+            .ok_or_else(|| anyhow!("cannot retrieve `{url}`: HTTP requests are unavailable"))?;
+        let cancel = ctx.cancel();
+
+        Handle::try_current()?.block_on(async {
+            let cancelled = async {
+                match cancel {
+                    Some(cancel) => cancel.cancelled().await,
+                    None => std::future::pending().await,
+                }
+            };
+
+            tokio::select! {
+                _ = cancelled => bail!("the request was cancelled"),
+                result = fetch_page(http_client, url) => result.map(|(_, body)| body),
+            }
+        })
+    }
+}
+
+impl Extractor for SitemapExtractor {
+    fn extract(&mut self, ctx: ExtractorContext<'_>, body: &str) -> Result<Extraction> {
+        let last_update = Self::last_update(&ctx).context("could not retrieve the last update")?;
+        let oldest = self.max_age.map(|max_age| ctx.fetch_time() - max_age);
+
+        // a URL is emitted if it was modified after both of these.
+        let since = last_update.max(oldest);
+        let is_recent = |lastmod: Option<OffsetDateTime>| match (lastmod, since) {
+            (_, None) => true,
+            (Some(lastmod), Some(since)) => lastmod > since,
+            (None, Some(_)) => false,
+        };
+
+        let mut queue = VecDeque::from([(ctx.base_url().clone(), Some(body.to_owned()))]);
+        let mut visited = HashSet::new();
+        let mut entries = vec![];
+
+        while let Some((url, body)) = queue.pop_front() {
+            if !visited.insert(url.clone()) {
+                continue;
+            }
+
+            let is_root = body.is_some();
+            let body = match body {
+                Some(body) => body,
+
+                None if visited.len() > self.max_sitemaps => {
+                    warn!(
+                        "Not retrieving the sitemap `{url}`: reached the limit of {} sitemaps",
+                        self.max_sitemaps,
+                    );
+
+                    continue;
+                }
+
+                None => match Self::fetch(&ctx, &url) {
+                    Ok(body) => body,
+
+                    Err(e) => {
+                        warn!("Could not retrieve the sitemap `{url}`: {e:#}");
+
+                        continue;
+                    }
+                },
+            };
+
+            let package = match sxd_document::parser::parse(&body) {
+                Ok(package) => package,
+                Err(e) if is_root => bail!("could not parse the sitemap: {e:?}"),
+
+                Err(e) => {
+                    warn!("Could not parse the sitemap `{url}`: {e:?}");
+
+                    continue;
+                }
+            };
+            let document = package.as_document();
+            let Some(root) = document
+                .root()
+                .children()
+                .into_iter()
+                .find_map(|child| match child {
+                    ChildOfRoot::Element(element) => Some(element),
+                    _ => None,
+                })
+            else {
+                continue;
+            };
+
+            match root.name().local_part() {
+                "sitemapindex" => {
+                    for sitemap in child_elements(root) {
+                        let Some(loc) = child_text(sitemap, "loc") else {
+                            continue;
+                        };
+                        let lastmod = child_text(sitemap, "lastmod")
+                            .and_then(|s| parse_lastmod(&s, ctx.timezone()));
+
+                        // sitemaps without a date may still list modified URLs.
+                        if lastmod.is_some() && !is_recent(lastmod) {
+                            debug!("Skipping the unmodified sitemap `{loc}`");
+
+                            continue;
+                        }
+
+                        match url.join(&loc) {
+                            Ok(loc) => queue.push_back((loc, None)),
+                            Err(e) => warn!("The sitemap URL `{loc}` could not be parsed: {e:#}"),
+                        }
+                    }
+                }
+
+                "urlset" => {
+                    for entry in child_elements(root) {
+                        let Some(loc) = child_text(entry, "loc") else {
+                            continue;
+                        };
+                        let lastmod = child_text(entry, "lastmod")
+                            .and_then(|s| parse_lastmod(&s, ctx.timezone()));
+
+                        if !is_recent(lastmod) {
+                            continue;
+                        }
+
+                        let entry_url = match url.join(&loc) {
+                            Ok(entry_url) => entry_url,
+
+                            Err(e) => {
+                                warn!("The URL `{loc}` could not be parsed: {e:#}");
+
+                                continue;
+                            }
+                        };
+
+                        // the news and image extensions provide a title and a preview image.
+                        let title = child(entry, "news").and_then(|news| child_text(news, "title"));
+                        let image = child(entry, "image")
+                            .and_then(|image| child_text(image, "loc"))
+                            .and_then(|image| url.join(&image).ok());
+
+                        entries.push(Entry {
+                            id: entry_url.to_string(),
+                            title: title.unwrap_or_else(|| entry_url.to_string()),
+                            description: String::new(),
+                            content: None,
+                            url: entry_url,
+                            author: None,
+                            pub_date: lastmod,
+                            enclosure: None,
+                            image,
+                            comments: None,
+                            categories: vec![],
+                            guid: None,
+                            guid_is_permalink: false,
+                            extra: Default::default(),
+                        });
+                    }
+                }
+
+                name if is_root => bail!("expected a sitemap, found a `{name}` element"),
+                name => warn!("The sitemap `{url}` has an unexpected `{name}` element"),
+            }
+        }
+
+        Ok(entries.into())
+    }
+}
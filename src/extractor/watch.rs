@@ -0,0 +1,295 @@
+use anyhow::{Context as _, Result};
+use ego_tree::NodeRef;
+use scraper::{ElementRef, Html, Node};
+use sha2::{Digest, Sha256};
+use tokio::runtime::Handle;
+use tracing::debug;
+
+use crate::config::{self, WatchMode};
+use crate::css::CssSelector;
+
+use super::{Context as ExtractorContext, Entry, Extraction, Extractor};
+
+/// The key the text of the watched region is stored under.
+const TEXT_KEY: &str = "watch.text";
+
+/// The maximum product of the line counts of two versions for which a diff is computed.
+const MAX_DIFF_SIZE: usize = 1 << 20;
+
+/// Elements whose contents start on a new line.
+const BLOCK_ELEMENTS: &[&str] = &[
+    "address",
+    "article",
+    "aside",
+    "blockquote",
+    "br",
+    "dd",
+    "div",
+    "dl",
+    "dt",
+    "figcaption",
+    "figure",
+    "footer",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "header",
+    "hr",
+    "li",
+    "main",
+    "nav",
+    "ol",
+    "p",
+    "pre",
+    "section",
+    "table",
+    "td",
+    "th",
+    "tr",
+    "ul",
+];
+
+/// Collects the text of the node, putting the contents of block elements on separate lines.
+fn collect_text(node: NodeRef<'_, Node>, text: &mut String) {
+    for child in node.children() {
+        match child.value() {
+            Node::Text(s) => text.push_str(s),
+
+            Node::Element(element)
+                if matches!(element.name(), "script" | "style" | "noscript" | "template") => {}
+
+            Node::Element(element) => {
+                let is_block = BLOCK_ELEMENTS.contains(&element.name());
+
+                if is_block {
+                    text.push('\n');
+                }
+
+                collect_text(child, text);
+
+                if is_block {
+                    text.push('\n');
+                }
+            }
+
+            _ => {}
+        }
+    }
+}
+
+enum Change<'a> {
+    Kept,
+    Removed(&'a str),
+    Added(&'a str),
+}
+
+/// Computes a line-based diff using the longest common subsequence.
+fn diff_lines<'a>(old: &[&'a str], new: &[&'a str]) -> Vec<Change<'a>> {
+    // `lcs[i][j]` is the length of the longest common subsequence of `old[i..]` and `new[j..]`.
+    let mut lcs = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let (mut i, mut j) = (0, 0);
+    let mut changes = vec![];
+
+    while i < old.len() && j < new.len() {
+        if old[i] == new[j] {
+            changes.push(Change::Kept);
+            i += 1;
+            j += 1;
+        } else if lcs[i + 1][j] >= lcs[i][j + 1] {
+            changes.push(Change::Removed(old[i]));
+            i += 1;
+        } else {
+            changes.push(Change::Added(new[j]));
+            j += 1;
+        }
+    }
+
+    changes.extend(old[i..].iter().map(|&line| Change::Removed(line)));
+    changes.extend(new[j..].iter().map(|&line| Change::Added(line)));
+
+    changes
+}
+
+/// Renders the removed and added lines as HTML.
+fn render_diff(changes: &[Change<'_>]) -> String {
+    let mut html = String::new();
+
+    for change in changes {
+        let (tag, line) = match change {
+            Change::Kept => continue,
+            Change::Removed(line) => ("del", line),
+            Change::Added(line) => ("ins", line),
+        };
+
+        html.push_str(&format!(
+            "<p><{tag}>{}</{tag}></p>",
+            html_escape::encode_text(line),
+        ));
+    }
+
+    html
+}
+
+/// Emits an entry whenever a region of the page changes.
+pub struct WatchExtractor {
+    selector: Option<CssSelector>,
+    mode: WatchMode,
+    title: Option<String>,
+}
+
+impl WatchExtractor {
+    pub fn from_cfg(cfg: &config::WatchExtractorConfig) -> Self {
+        Self {
+            selector: cfg.selector.clone(),
+            mode: cfg.mode,
+            title: cfg.title.clone(),
+        }
+    }
+
+    /// Retrieves the text of the region as of the previous update.
+    ///
+    /// Nothing is stored when trying out the extractor, so every version is new.
+    fn load_text(ctx: &ExtractorContext<'_>) -> Result<Option<String>> {
+        let (Some(storage), Some(feed_name)) = (ctx.storage(), ctx.feed_name()) else {
+            return Ok(None);
+        };
+
+        let value = Handle::try_current()?.block_on(async {
+            let mut tx = storage.begin().await?;
+            let value = tx.get_kv(feed_name, TEXT_KEY).await?;
+            tx.commit().await?;
+
+            anyhow::Ok(value)
+        })?;
+
+        value
+            .map(|value| serde_json::from_str(&value))
+            .transpose()
+            .context("the stored text of the watched region is malformed")
+    }
+
+    fn store_text(ctx: &ExtractorContext<'_>, text: &str) -> Result<()> {
+        let (Some(storage), Some(feed_name)) = (ctx.storage(), ctx.feed_name()) else {
+            return Ok(());
+        };
+
+        let value = serde_json::to_string(text)?;
+
+        Handle::try_current()?.block_on(async {
+            let mut tx = storage.begin().await?;
+            tx.set_kv(feed_name, TEXT_KEY, Some(&value)).await?;
+            tx.commit().await
+        })
+    }
+}
+
+impl Extractor for WatchExtractor {
+    fn extract(&mut self, ctx: ExtractorContext<'_>, html: &str) -> Result<Extraction> {
+        let html = Html::parse_document(html);
+
+        let region = match &self.selector {
+            Some(selector) => html
+                .select(selector)
+                .next()
+                .with_context(|| format!("the selector `{}` matched nothing", selector.as_str()))?,
+
+            None => html
+                .root_element()
+                .children()
+                .filter_map(ElementRef::wrap)
+                .find(|element| element.value().name() == "body")
+                .unwrap_or(html.root_element()),
+        };
+
+        let mut text = String::new();
+        collect_text(*region, &mut text);
+        let text = text
+            .lines()
+            .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" "))
+            .filter(|line| !line.is_empty())
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        let previous = Self::load_text(&ctx).context("could not retrieve the previous version")?;
+
+        if previous.as_ref() == Some(&text) {
+            debug!("The watched region is unchanged");
+
+            return Ok(vec![].into());
+        }
+
+        Self::store_text(&ctx, &text).context("could not store the new version")?;
+
+        let title = self
+            .title
+            .clone()
+            .or_else(|| {
+                html.root_element()
+                    .descendants()
+                    .filter_map(ElementRef::wrap)
+                    .find(|element| element.value().name() == "title")
+                    .map(|element| element.text().collect::<String>().trim().to_owned())
+                    .filter(|title| !title.is_empty())
+            })
+            .unwrap_or_else(|| ctx.base_url().to_string());
+
+        let description = match &previous {
+            Some(previous) if self.mode == WatchMode::Diff => {
+                let old = previous.lines().collect::<Vec<_>>();
+                let new = text.lines().collect::<Vec<_>>();
+
+                if old.len().saturating_mul(new.len()) <= MAX_DIFF_SIZE {
+                    render_diff(&diff_lines(&old, &new))
+                } else {
+                    debug!("The region is too large to diff; using the new content instead");
+
+                    region.inner_html()
+                }
+            }
+
+            _ => region.inner_html(),
+        };
+
+        let hash = Sha256::digest(text.as_bytes())
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect::<String>();
+
+        Ok(vec![Entry {
+            // the region may return to an earlier version, which is still a change.
+            id: format!("{}-{hash}", ctx.fetch_time().unix_timestamp()),
+            title: if previous.is_some() {
+                format!("{title} changed")
+            } else {
+                title
+            },
+            description,
+            content: None,
+            url: ctx.base_url().clone(),
+            author: None,
+            pub_date: Some(ctx.fetch_time()),
+            enclosure: None,
+            image: None,
+            comments: None,
+            categories: vec![],
+            guid: None,
+            guid_is_permalink: false,
+            extra: Default::default(),
+        }]
+        .into())
+    }
+}
@@ -1,5 +1,5 @@
 use std::borrow::Cow;
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 
 use anyhow::{bail, Context as _, Result};
 use derive_more::From;
@@ -13,17 +13,20 @@ use sxd_document::dom::{
     Root, Text,
 };
 use sxd_document::{Package, QName};
+use sxd_xpath::nodeset::{Node, Nodeset};
 use sxd_xpath::{Context, Value};
-use time::format_description::well_known::Rfc3339;
-use time::OffsetDateTime;
 use tracing::{debug, warn};
 
-use crate::config;
-use crate::xpath::XPath;
+use crate::config::{self, DateTimeFormats, XPathVersion};
+use crate::reldate::{self, Locale};
+#[cfg(feature = "xpath3")]
+use crate::xpath::xpath3;
+use crate::xpath::{XPath, XPathList};
 
-use super::{Context as ExtractorContext, Entry, Extractor};
+use super::{Context as ExtractorContext, Enclosure, Entry, Extraction, Extractor};
 
 const HTTP_XMLNS_URI: &str = "http://www.w3.org/1999/xhtml";
+const DEFAULT_ENCLOSURE_TYPE: &str = "application/octet-stream";
 
 #[derive(Default)]
 struct SxdSinkStorage {
@@ -506,103 +509,519 @@ fn parse_html(html: &str) -> Package {
     storage.into_package()
 }
 
-fn xpath_value_to_string(value: Value<'_>) -> String {
-    if let Value::Nodeset(nodes) = value {
-        // concatenate all nodes
-        let mut s = String::new();
+/// Elements that have no end tag.
+const VOID_ELEMENTS: &[&str] = &[
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "source", "track",
+    "wbr",
+];
+
+fn escape_html(s: &str, attr: bool, out: &mut String) {
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' if !attr => out.push_str("&lt;"),
+            '>' if !attr => out.push_str("&gt;"),
+            '"' if attr => out.push_str("&quot;"),
+            c => out.push(c),
+        }
+    }
+}
 
-        for node in nodes.document_order() {
-            s.push_str(&node.string_value());
+fn write_html(element: Element<'_>, out: &mut String) {
+    let name = element.name().local_part();
+
+    // their contents would be escaped as text.
+    if name == "script" || name == "style" {
+        return;
+    }
+
+    out.push('<');
+    out.push_str(name);
+
+    for attr in element.attributes() {
+        out.push(' ');
+        out.push_str(attr.name().local_part());
+        out.push_str("=\"");
+        escape_html(attr.value(), true, out);
+        out.push('"');
+    }
+
+    out.push('>');
+
+    if VOID_ELEMENTS.contains(&name) {
+        return;
+    }
+
+    for child in element.children() {
+        match child {
+            ChildOfElement::Element(element) => write_html(element, out),
+            ChildOfElement::Text(text) => escape_html(text.text(), false, out),
+            ChildOfElement::Comment(_) | ChildOfElement::ProcessingInstruction(_) => {}
         }
+    }
 
-        s
-    } else {
-        value.into_string()
+    out.push_str("</");
+    out.push_str(name);
+    out.push('>');
+}
+
+/// Whether `s` is a name XML allows for an element or attribute without a prefix.
+#[cfg(feature = "xpath3")]
+fn is_xml_name(s: &str) -> bool {
+    let mut chars = s.chars();
+
+    chars.next().is_some_and(|c| c.is_alphabetic() || c == '_')
+        && chars.all(|c| c.is_alphanumeric() || matches!(c, '-' | '.' | '_'))
+}
+
+#[cfg(feature = "xpath3")]
+fn escape_xml(s: &str, out: &mut String) {
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\t' | '\n' | '\r' => out.push(c),
+
+            // not allowed in XML documents.
+            '\0'..='\x1f' | '\u{fffe}' | '\u{ffff}' => {}
+
+            c => out.push(c),
+        }
     }
 }
 
-pub struct XPathExtractor {
-    entry: XPath,
-    id: XPath,
-    title: XPath,
-    description: XPath,
-    url: XPath,
-    author: Option<XPath>,
-    pub_date: Option<(
-        XPath,
-        Box<dyn time::parsing::Parsable + Send + Sync + 'static>,
-    )>,
+/// Writes the element as XML for the XPath 3.1 engine. Comments, processing instructions, and
+/// names XML does not allow (such as `<a"b>`) are left out, as are namespaced attributes (such
+/// as `xlink:href`), whose prefixes would have to be declared.
+#[cfg(feature = "xpath3")]
+fn write_xml(element: Element<'_>, parent_ns: Option<&str>, out: &mut String) {
+    let name = element.name();
+    let ns = name.namespace_uri();
+
+    if !is_xml_name(name.local_part()) {
+        for child in element.children() {
+            match child {
+                ChildOfElement::Element(element) => write_xml(element, parent_ns, out),
+                ChildOfElement::Text(text) => escape_xml(text.text(), out),
+                ChildOfElement::Comment(_) | ChildOfElement::ProcessingInstruction(_) => {}
+            }
+        }
+
+        return;
+    }
+
+    out.push('<');
+    out.push_str(name.local_part());
+
+    if ns != parent_ns {
+        out.push_str(" xmlns=\"");
+        escape_xml(ns.unwrap_or_default(), out);
+        out.push('"');
+    }
+
+    for attr in element.attributes() {
+        let attr_name = attr.name();
+
+        if attr_name.namespace_uri().is_some()
+            || attr_name.local_part() == "xmlns"
+            || !is_xml_name(attr_name.local_part())
+        {
+            continue;
+        }
+
+        out.push(' ');
+        out.push_str(attr_name.local_part());
+        out.push_str("=\"");
+        escape_xml(attr.value(), out);
+        out.push('"');
+    }
+
+    out.push('>');
+
+    for child in element.children() {
+        match child {
+            ChildOfElement::Element(element) => write_xml(element, ns, out),
+            ChildOfElement::Text(text) => escape_xml(text.text(), out),
+            ChildOfElement::Comment(_) | ChildOfElement::ProcessingInstruction(_) => {}
+        }
+    }
+
+    out.push_str("</");
+    out.push_str(name.local_part());
+    out.push('>');
 }
 
-impl XPathExtractor {
-    pub fn from_cfg(cfg: &config::XPathExtractorConfig) -> Self {
-        Self {
-            entry: cfg.entry.clone(),
-            id: cfg.id.clone(),
-            title: cfg.title.clone(),
-            description: cfg.description.clone(),
-            url: cfg.url.clone(),
-            author: cfg.author.clone(),
-            pub_date: cfg.pub_date.clone().map(|xpath| {
-                (
-                    xpath,
-                    if let Some(fmt) = &cfg.pub_date_format {
-                        Box::new(fmt.clone().into_inner()) as _
-                    } else {
-                        Box::new(Rfc3339) as _
-                    },
-                )
-            }),
+/// Serializes the document as XML for the XPath 3.1 engine.
+#[cfg(feature = "xpath3")]
+fn to_xml(document: Document<'_>) -> String {
+    let mut out = String::new();
+
+    for child in document.root().children() {
+        if let ChildOfRoot::Element(element) = child {
+            write_xml(element, None, &mut out);
         }
     }
+
+    out
 }
 
-impl Extractor for XPathExtractor {
-    fn extract(&mut self, ctx: ExtractorContext<'_>, html: &str) -> Result<Vec<Entry>> {
-        let html = parse_html(html);
+/// Returns the HTML of the nodes selected in the document by the first expression that selects
+/// any, or `None` if none does.
+pub fn select_html(html: &str, xpaths: &XPathList) -> Result<Option<String>> {
+    let html = parse_html(html);
+    let mut xpath_ctx = Context::new();
+    xpath_ctx.set_namespace("html", HTTP_XMLNS_URI);
+    xpath_ctx.set_default_namespace_uri(Some(HTTP_XMLNS_URI.into()));
+
+    for xpath in xpaths.iter() {
+        let value = xpath
+            .evaluate(&xpath_ctx, html.as_document().root())
+            .with_context(|| {
+                format!("could not apply the XPath expression `{}`", xpath.as_str())
+            })?;
+        let Value::Nodeset(nodes) = value else {
+            bail!(
+                "the XPath expression `{}` did not return a node set",
+                xpath.as_str()
+            );
+        };
+
+        let mut s = String::new();
+
+        for node in nodes.document_order() {
+            match node {
+                Node::Element(element) => write_html(element, &mut s),
+                node => escape_html(&node.string_value(), false, &mut s),
+            }
+        }
+
+        if !s.trim().is_empty() {
+            return Ok(Some(s));
+        }
+    }
+
+    Ok(None)
+}
+
+/// Evaluates the expressions of an extractor on a page.
+trait Evaluator {
+    type Node;
+
+    /// Returns the nodes selected by any of the entry expressions, in document order.
+    fn entries(&self, xpaths: &XPathList) -> Result<Vec<Self::Node>>;
+
+    /// Returns the string values of the items the expression selects relative to `node` (or of
+    /// the single value it returns).
+    fn evaluate(&self, xpath: &XPath, node: &Self::Node) -> Result<Vec<String>>;
+}
+
+struct XPath1Evaluator<'d> {
+    document: Document<'d>,
+    xpath_ctx: Context<'d>,
+}
+
+impl<'d> XPath1Evaluator<'d> {
+    fn new(document: Document<'d>) -> Self {
         let mut xpath_ctx = Context::new();
         xpath_ctx.set_namespace("html", HTTP_XMLNS_URI);
         xpath_ctx.set_default_namespace_uri(Some(HTTP_XMLNS_URI.into()));
 
-        let entries = self
-            .entry
-            .evaluate(&xpath_ctx, html.as_document().root())
-            .context("could not apply the entry XPath expression")?;
-        let entries = 'entries: {
-            let expected = match entries {
+        Self {
+            document,
+            xpath_ctx,
+        }
+    }
+}
+
+impl<'d> Evaluator for XPath1Evaluator<'d> {
+    type Node = Node<'d>;
+
+    fn entries(&self, xpaths: &XPathList) -> Result<Vec<Node<'d>>> {
+        // the node sets of all entry expressions are merged (the duplicates are removed).
+        let mut entries = Nodeset::new();
+
+        for xpath in xpaths.iter() {
+            let value = xpath
+                .evaluate(&self.xpath_ctx, self.document.root())
+                .with_context(|| {
+                    format!(
+                        "could not apply the entry XPath expression `{}`",
+                        xpath.as_str()
+                    )
+                })?;
+            let expected = match value {
                 Value::Number(_) => "number",
                 Value::String(_) => "string",
                 Value::Boolean(_) => "boolean",
-                Value::Nodeset(nodes) => break 'entries nodes,
+
+                Value::Nodeset(nodes) => {
+                    for node in nodes.iter() {
+                        entries.add(node);
+                    }
+
+                    continue;
+                }
             };
 
-            bail!("the entry XPath expression returned a {expected} instead of a node set");
-        };
+            bail!(
+                "the entry XPath expression `{}` returned a {expected} instead of a node set",
+                xpath.as_str(),
+            );
+        }
+
+        Ok(entries.document_order())
+    }
+
+    fn evaluate(&self, xpath: &XPath, node: &Node<'d>) -> Result<Vec<String>> {
+        Ok(match xpath.evaluate(&self.xpath_ctx, *node)? {
+            Value::Nodeset(nodes) => nodes
+                .document_order()
+                .into_iter()
+                .map(|node| node.string_value())
+                .collect(),
 
+            value => vec![value.into_string()],
+        })
+    }
+}
+
+#[cfg(feature = "xpath3")]
+struct XPath3Evaluator(xpath3::Document);
+
+#[cfg(feature = "xpath3")]
+impl Evaluator for XPath3Evaluator {
+    type Node = xpath3::Item;
+
+    fn entries(&self, xpaths: &XPathList) -> Result<Vec<xpath3::Item>> {
+        let mut results = vec![];
+
+        for xpath in xpaths.iter() {
+            let items = self.0.evaluate(xpath, self.0.root()).with_context(|| {
+                format!(
+                    "could not apply the entry XPath expression `{}`",
+                    xpath.as_str()
+                )
+            })?;
+            let expected = match items
+                .iter()
+                .find(|item| !matches!(item, xpath3::Item::Node(_)))
+            {
+                Some(xpath3::Item::Function(_)) => "a function",
+                Some(_) => "an atomic value",
+                None => {
+                    results.push(items);
+
+                    continue;
+                }
+            };
+
+            bail!(
+                "the entry XPath expression `{}` returned {expected} instead of nodes",
+                xpath.as_str(),
+            );
+        }
+
+        if results.len() == 1 {
+            return Ok(results.pop().unwrap());
+        }
+
+        // the union operator merges the nodes in document order (and removes the duplicates).
+        let union = xpaths
+            .iter()
+            .map(|xpath| format!("({})", xpath.as_str()))
+            .collect::<Vec<_>>()
+            .join(" | ");
+
+        self.0
+            .evaluate_str(&union, self.0.root())
+            .context("could not merge the entries")
+    }
+
+    fn evaluate(&self, xpath: &XPath, node: &xpath3::Item) -> Result<Vec<String>> {
+        self.0
+            .evaluate(xpath, node)?
+            .iter()
+            .map(|item| self.0.string_value(item))
+            .collect()
+    }
+}
+
+pub struct XPathExtractor {
+    version: XPathVersion,
+    entry: XPathList,
+    id: XPathList,
+    title: XPathList,
+    description: XPathList,
+    content: Option<XPathList>,
+    url: XPathList,
+    author: Option<XPathList>,
+    pub_date: Option<(XPathList, DateTimeFormats)>,
+    relative_date_locales: Vec<Locale>,
+    enclosure: Option<EnclosureXPaths>,
+    image: Option<XPathList>,
+    comments: Option<XPathList>,
+    categories: Option<XPathList>,
+    guid: Option<XPathList>,
+    guid_is_permalink: bool,
+    extra: BTreeMap<String, XPathList>,
+}
+
+struct EnclosureXPaths {
+    url: XPathList,
+    mime_type: Option<XPathList>,
+    length: Option<XPathList>,
+}
+
+/// All the expressions of the extractor.
+fn xpath_lists(cfg: &config::XPathExtractorConfig) -> impl Iterator<Item = &XPathList> {
+    let optional = [
+        &cfg.content,
+        &cfg.author,
+        &cfg.pub_date,
+        &cfg.enclosure_url,
+        &cfg.enclosure_type,
+        &cfg.enclosure_length,
+        &cfg.image,
+        &cfg.comments,
+        &cfg.categories,
+        &cfg.guid,
+    ];
+
+    [&cfg.entry, &cfg.id, &cfg.title, &cfg.description, &cfg.url]
+        .into_iter()
+        .chain(optional.into_iter().flatten())
+        .chain(cfg.extra.values())
+}
+
+impl XPathExtractor {
+    pub fn from_cfg(cfg: &config::XPathExtractorConfig) -> Result<Self> {
+        match cfg.xpath_version {
+            XPathVersion::V1_0 => {
+                for xpaths in xpath_lists(cfg) {
+                    if let Err(e) = xpaths.check_xpath1() {
+                        bail!("{e} (set `xpath-version = \"3.1\"` to use XPath 3.1)");
+                    }
+                }
+            }
+
+            #[cfg(feature = "xpath3")]
+            XPathVersion::V3_1 => {
+                for xpath in xpath_lists(cfg).flat_map(XPathList::iter) {
+                    xpath3::check(xpath.as_str()).with_context(|| {
+                        format!("invalid XPath expression `{}`", xpath.as_str())
+                    })?;
+                }
+            }
+
+            #[cfg(not(feature = "xpath3"))]
+            XPathVersion::V3_1 => {
+                bail!("XPath 3.1 needs feedgen to be built with the `xpath3` feature")
+            }
+        }
+
+        Ok(Self {
+            version: cfg.xpath_version,
+            entry: cfg.entry.clone(),
+            id: cfg.id.clone(),
+            title: cfg.title.clone(),
+            description: cfg.description.clone(),
+            content: cfg.content.clone(),
+            url: cfg.url.clone(),
+            author: cfg.author.clone(),
+            pub_date: cfg
+                .pub_date
+                .clone()
+                .map(|xpath| (xpath, cfg.pub_date_format.clone().unwrap_or_default())),
+            relative_date_locales: cfg.relative_date_locales.clone(),
+            enclosure: cfg.enclosure_url.clone().map(|url| EnclosureXPaths {
+                url,
+                mime_type: cfg.enclosure_type.clone(),
+                length: cfg.enclosure_length.clone(),
+            }),
+            image: cfg.image.clone(),
+            comments: cfg.comments.clone(),
+            categories: cfg.categories.clone(),
+            guid: cfg.guid.clone(),
+            guid_is_permalink: cfg.guid_is_permalink,
+            extra: cfg.extra.clone(),
+        })
+    }
+
+    fn extract_with<E: Evaluator>(
+        &self,
+        ctx: &ExtractorContext<'_>,
+        evaluator: &E,
+    ) -> Result<Extraction> {
+        let entries = evaluator.entries(&self.entry)?;
         let mut result = vec![];
 
-        for (idx, entry) in entries.document_order().into_iter().enumerate() {
+        for (idx, entry) in entries.iter().enumerate() {
             let idx = idx + 1;
 
-            let find_one = |xpath: &XPath, what: &str, allow_empty: bool| {
-                let value = match xpath.evaluate(&xpath_ctx, entry) {
-                    Ok(value) => value,
+            // the expressions are tried in order until one returns a non-empty string.
+            let find_one = |xpaths: &XPathList, what: &str, allow_empty: bool| {
+                let mut found = false;
 
-                    Err(e) => {
-                        warn!("Could not apply the {what} XPath expression to entry #{idx}: {e:#}");
-                        return None;
-                    }
-                };
+                for xpath in xpaths.iter() {
+                    match evaluator.evaluate(xpath, entry) {
+                        Ok(values) => {
+                            found = true;
+                            let s = values.concat();
 
-                let s = xpath_value_to_string(value);
+                            if !s.is_empty() {
+                                return Some(s);
+                            }
+                        }
 
-                if s.is_empty() && !allow_empty {
-                    warn!("The {what} XPath expression returned an empty string");
+                        Err(e) => warn!(
+                            "Could not apply the {what} XPath expression `{}` to entry #{idx}: \
+                                {e:#}",
+                            xpath.as_str(),
+                        ),
+                    }
+                }
 
+                if !found {
                     None
+                } else if allow_empty {
+                    Some(String::new())
                 } else {
-                    Some(s)
+                    warn!("The {what} XPath expression returned an empty string for entry #{idx}");
+
+                    None
+                }
+            };
+
+            // unlike `find_one`, every node of the result is a separate value.
+            let find_all = |xpaths: &XPathList, what: &str| {
+                for xpath in xpaths.iter() {
+                    let values = match evaluator.evaluate(xpath, entry) {
+                        Ok(values) => values
+                            .iter()
+                            .map(|s| s.trim())
+                            .filter(|s| !s.is_empty())
+                            .map(str::to_owned)
+                            .collect::<Vec<_>>(),
+
+                        Err(e) => {
+                            warn!(
+                                "Could not apply the {what} XPath expression `{}` to entry \
+                                    #{idx}: {e:#}",
+                                xpath.as_str(),
+                            );
+
+                            continue;
+                        }
+                    };
+
+                    if !values.is_empty() {
+                        return values;
+                    }
                 }
+
+                vec![]
             };
 
             let Some(id) = find_one(&self.id, "id", false) else {
@@ -614,10 +1033,14 @@ impl Extractor for XPathExtractor {
             let Some(description) = find_one(&self.description, "description", true) else {
                 continue;
             };
+            let content = self
+                .content
+                .as_ref()
+                .and_then(|xpath| find_one(xpath, "content", false));
             let Some(url) = find_one(&self.url, "url", false) else {
                 continue;
             };
-            let url = match ctx.fetch_url().join(&url) {
+            let url = match ctx.base_url().join(&url) {
                 Ok(url) => url,
                 Err(e) => {
                     warn!(
@@ -634,7 +1057,11 @@ impl Extractor for XPathExtractor {
 
             let pub_date = if let Some((xpath, fmt)) = &self.pub_date {
                 find_one(xpath, "pub_date", false).and_then(|s| {
-                    OffsetDateTime::parse(&s, fmt)
+                    fmt.parse(&s, ctx.timezone())
+                        .or_else(|e| {
+                            reldate::parse(&s, ctx.fetch_time(), &self.relative_date_locales)
+                                .ok_or(e)
+                        })
                         .inspect_err(|e| warn!("The date `{s}` could not be parsed: {e:#}"))
                         .ok()
                 })
@@ -642,16 +1069,126 @@ impl Extractor for XPathExtractor {
                 None
             };
 
+            let enclosure = self.enclosure.as_ref().and_then(|xpaths| {
+                let url = find_one(&xpaths.url, "enclosure_url", false)?;
+                let url = ctx
+                    .base_url()
+                    .join(&url)
+                    .inspect_err(|e| {
+                        warn!(
+                            "The enclosure URL for entry #{idx} could not be parsed \
+                                as an URL: {e:#}"
+                        )
+                    })
+                    .ok()?;
+                let mime_type = match &xpaths.mime_type {
+                    Some(xpath) => find_one(xpath, "enclosure_type", false)?,
+                    None => DEFAULT_ENCLOSURE_TYPE.into(),
+                };
+                let length = xpaths
+                    .length
+                    .as_ref()
+                    .and_then(|xpath| find_one(xpath, "enclosure_length", false))
+                    .and_then(|s| {
+                        s.trim()
+                            .parse()
+                            .inspect_err(|e| {
+                                warn!("The enclosure length `{s}` could not be parsed: {e:#}")
+                            })
+                            .ok()
+                    });
+
+                Some(Enclosure {
+                    url,
+                    mime_type,
+                    length,
+                })
+            });
+
+            let image = self.image.as_ref().and_then(|xpath| {
+                let url = find_one(xpath, "image", false)?;
+
+                ctx.base_url()
+                    .join(&url)
+                    .inspect_err(|e| {
+                        warn!("The image URL for entry #{idx} could not be parsed: {e:#}")
+                    })
+                    .ok()
+            });
+
+            let comments = self.comments.as_ref().and_then(|xpath| {
+                let url = find_one(xpath, "comments", false)?;
+
+                ctx.base_url()
+                    .join(&url)
+                    .inspect_err(|e| {
+                        warn!("The comments URL for entry #{idx} could not be parsed: {e:#}")
+                    })
+                    .ok()
+            });
+
+            let categories = self
+                .categories
+                .as_ref()
+                .map(|xpaths| find_all(xpaths, "categories"))
+                .unwrap_or_default();
+
+            let guid = self
+                .guid
+                .as_ref()
+                .and_then(|xpath| find_one(xpath, "guid", false));
+
+            let extra = self
+                .extra
+                .iter()
+                .filter_map(|(name, xpath)| {
+                    find_one(xpath, name, false).map(|value| (name.clone(), value))
+                })
+                .collect();
+
             result.push(Entry {
                 id,
                 title,
                 description,
+                content,
                 url,
                 author,
                 pub_date,
+                enclosure,
+                image,
+                comments,
+                categories,
+                guid,
+                guid_is_permalink: self.guid_is_permalink,
+                extra,
             });
         }
 
-        Ok(result)
+        let skipped = entries.len() - result.len();
+
+        Ok(Extraction::from(result).with_skipped(skipped))
+    }
+}
+
+impl Extractor for XPathExtractor {
+    fn extract(&mut self, ctx: ExtractorContext<'_>, html: &str) -> Result<Extraction> {
+        let html = parse_html(html);
+
+        match self.version {
+            XPathVersion::V1_0 => {
+                self.extract_with(&ctx, &XPath1Evaluator::new(html.as_document()))
+            }
+
+            #[cfg(feature = "xpath3")]
+            XPathVersion::V3_1 => {
+                let document = xpath3::Document::new(&to_xml(html.as_document()))
+                    .context("could not convert the page to XML")?;
+
+                self.extract_with(&ctx, &XPath3Evaluator(document))
+            }
+
+            #[cfg(not(feature = "xpath3"))]
+            XPathVersion::V3_1 => unreachable!("rejected by `from_cfg`"),
+        }
     }
 }
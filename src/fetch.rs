@@ -1,81 +1,205 @@
-use std::collections::HashMap;
+mod article;
+mod cookies;
+mod credentials;
+mod queue;
+mod webhook;
+
+use std::collections::{HashMap, HashSet};
 use std::path::PathBuf;
 use std::pin::pin;
+use std::sync::atomic::Ordering;
 use std::sync::Arc;
 use std::time::Duration;
 
 use ::time::OffsetDateTime;
-use anyhow::{anyhow, Context, Result};
+use anyhow::{anyhow, bail, Context, Result};
 use http_cache_reqwest::{CACacheManager, Cache, HttpCache, MokaCache, MokaManager};
 use rand::rngs::SmallRng;
 use rand::{thread_rng, Rng, SeedableRng};
+use reqwest::header::{HeaderMap, AUTHORIZATION};
+use reqwest::Url;
 use reqwest_middleware::{ClientBuilder, ClientWithMiddleware};
 use tokio::time::Instant;
 use tokio::{select, time};
 use tokio_util::sync::CancellationToken;
-use tracing::{debug, error, info, info_span, trace, Instrument};
-
-use crate::extractor::Context as ExtractorContext;
-use crate::state::Feed;
+use tracing::{debug, error, info, info_span, trace, warn, Instrument};
+
+use crate::config::{self, Config, ExtractionFailurePolicy};
+use crate::extractor::{
+    ChannelInfo, Context as ExtractorContext, Entry, Extraction, LoginRequired, Response,
+};
+use crate::imageproxy;
+use crate::state::{Feed, FetchOutcome};
 use crate::storage::Storage;
+use crate::validate;
+
+pub use self::cookies::CookieJar;
+pub use self::credentials::Credentials;
+pub use self::queue::FetchQueue;
 
 const CONNECT_TIMEOUT: Duration = Duration::from_secs(30);
 const READ_TIMEOUT: Duration = Duration::from_secs(10);
 const TOTAL_TIMEOUT: Duration = Duration::from_secs(300);
 
+/// The number of consecutive updates without new entries after which an adaptive fetch interval
+/// is lengthened.
+const IDLE_UPDATES_BEFORE_BACKOFF: u32 = 3;
+
+/// The factor an adaptive fetch interval is multiplied by after idle updates.
+const BACKOFF_FACTOR: f64 = 1.5;
+
+/// The factor an adaptive fetch interval is multiplied by after an update with new entries.
+const TIGHTEN_FACTOR: f64 = 0.75;
+
+pub fn make_http_client(cfg: &Config) -> Result<ClientWithMiddleware> {
+    build_http_client(
+        cfg.cache_dir.clone(),
+        cfg.proxy.as_ref(),
+        &HeaderMap::new(),
+        &CookieJar::default(),
+        None,
+    )
+}
+
+/// Creates an HTTP client that sends `headers` with every request (through `proxy` if set) and
+/// keeps its cookies in `cookie_jar`.
+fn build_http_client(
+    cache_dir: Option<PathBuf>,
+    proxy: Option<&Url>,
+    headers: &HeaderMap,
+    cookie_jar: &CookieJar,
+    credentials: Option<Credentials>,
+) -> Result<ClientWithMiddleware> {
+    let mut builder = reqwest::Client::builder()
+        .default_headers(headers.clone())
+        .cookie_provider(cookie_jar.store())
+        .connect_timeout(CONNECT_TIMEOUT)
+        .read_timeout(READ_TIMEOUT)
+        .timeout(TOTAL_TIMEOUT);
+
+    if let Some(proxy) = proxy {
+        debug!("Sending requests through the proxy `{proxy}`");
+        builder = builder.proxy(
+            reqwest::Proxy::all(proxy.clone())
+                .with_context(|| anyhow!("the proxy `{proxy}` is not supported"))?,
+        );
+    }
+
+    let mut builder =
+        ClientBuilder::new(builder.build().context("could not create an HTTP client")?);
+
+    if let Some(credentials) = credentials {
+        builder = builder.with(credentials);
+    }
+
+    let builder = if let Some(path) = cache_dir {
+        debug!("Using a file cache at {}", path.display());
+        builder.with(Cache(HttpCache {
+            mode: Default::default(),
+            manager: CACacheManager { path },
+            options: Default::default(),
+        }))
+    } else {
+        debug!("Using an in-memory cache");
+        builder.with(Cache(HttpCache {
+            mode: Default::default(),
+            manager: MokaManager::new(MokaCache::builder().max_capacity(8192).build()),
+            options: Default::default(),
+        }))
+    };
+
+    Ok(builder.build())
+}
+
+/// Returns the client for the feed's requests along with its cookie jar.
+///
+/// The client is `http_client` (sharing its cookies) unless the feed has custom headers,
+/// credentials, cookies, or a proxy of its own.
+pub fn make_feed_http_client(
+    http_client: &ClientWithMiddleware,
+    cfg: &Config,
+    feed: &config::Feed,
+) -> Result<(ClientWithMiddleware, Option<CookieJar>)> {
+    if feed.headers.is_empty()
+        && feed.credentials.is_none()
+        && feed.proxy.is_none()
+        && feed.cookies.is_empty()
+        && feed.cookie_file.is_none()
+    {
+        return Ok((http_client.clone(), None));
+    }
+
+    let headers = feed.headers.as_header_map();
+    let credentials = match &feed.credentials {
+        Some(_) if headers.contains_key(AUTHORIZATION) => {
+            bail!("`credentials` cannot be used along with an `Authorization` header");
+        }
+
+        Some(credentials) => Some(Credentials::new(credentials, &feed.request_url)?),
+        None => None,
+    };
+
+    let cookie_jar = CookieJar::load(feed.cookie_file.clone(), &feed.cookies, &feed.request_url)?;
+    let http_client = build_http_client(
+        cfg.cache_dir.clone(),
+        feed.proxy.as_ref().or(cfg.proxy.as_ref()),
+        headers,
+        &cookie_jar,
+        credentials,
+    )?;
+
+    Ok((http_client, Some(cookie_jar)))
+}
+
+/// Retrieves a source page, returning the response metadata and the body.
+pub async fn fetch_page(
+    http_client: &ClientWithMiddleware,
+    url: &Url,
+) -> Result<(Response, String)> {
+    let response = http_client
+        .get(url.clone())
+        .send()
+        .await
+        .map_err(Into::into)
+        .and_then(|r| r.error_for_status().context("server returned an error"))
+        .with_context(|| anyhow!("could not fetch `{url}`"))?;
+
+    let meta = Response::new(&response);
+    let body = response
+        .text()
+        .await
+        .with_context(|| anyhow!("could not read the response when fetching `{url}`"))?;
+
+    Ok((meta, body))
+}
+
 pub struct Fetcher {
     feeds: Arc<HashMap<String, Feed>>,
-    cache_dir: Option<PathBuf>,
+    http_client: ClientWithMiddleware,
     storage: Arc<Storage>,
+    fetch_queue: Arc<FetchQueue>,
     max_initial_sleep: Duration,
 }
 
 impl Fetcher {
     pub fn new(
         feeds: Arc<HashMap<String, Feed>>,
-        cache_dir: Option<PathBuf>,
+        http_client: ClientWithMiddleware,
         storage: Arc<Storage>,
+        fetch_queue: Arc<FetchQueue>,
         max_initial_sleep: Duration,
     ) -> Self {
         Self {
             feeds,
-            cache_dir,
+            http_client,
             storage,
+            fetch_queue,
             max_initial_sleep,
         }
     }
 
     pub async fn run(self, cancel: CancellationToken) -> Result<()> {
         async move {
-            let http_client = {
-                let builder = ClientBuilder::new(
-                    reqwest::Client::builder()
-                        .connect_timeout(CONNECT_TIMEOUT)
-                        .read_timeout(READ_TIMEOUT)
-                        .timeout(TOTAL_TIMEOUT)
-                        .build()
-                        .context("could not create an HTTP client")?,
-                );
-
-                let builder = if let Some(path) = self.cache_dir {
-                    debug!("Using a file cache at {}", path.display());
-                    builder.with(Cache(HttpCache {
-                        mode: Default::default(),
-                        manager: CACacheManager { path },
-                        options: Default::default(),
-                    }))
-                } else {
-                    debug!("Using an in-memory cache");
-                    builder.with(Cache(HttpCache {
-                        mode: Default::default(),
-                        manager: MokaManager::new(MokaCache::builder().max_capacity(8192).build()),
-                        options: Default::default(),
-                    }))
-                };
-
-                builder.build()
-            };
-
             {
                 let mut thread_rng = thread_rng();
 
@@ -90,11 +214,15 @@ impl Fetcher {
                     let task = Task {
                         feeds: self.feeds.clone(),
                         storage: self.storage.clone(),
+                        fetch_queue: self.fetch_queue.clone(),
                         name: name.into(),
                         rng,
                         cancel: cancel.clone(),
-                        http_client: http_client.clone(),
+                        http_client: self.http_client.clone(),
                         max_initial_sleep: self.max_initial_sleep,
+                        prepared: false,
+                        fetch_interval: feed.fetch_interval,
+                        idle_updates: 0,
                     };
 
                     tokio::spawn(task.run().instrument(info_span!("run", feed_name = %name)));
@@ -113,15 +241,52 @@ impl Fetcher {
 struct Task {
     feeds: Arc<HashMap<String, Feed>>,
     storage: Arc<Storage>,
+    fetch_queue: Arc<FetchQueue>,
     name: String,
     rng: SmallRng,
     cancel: CancellationToken,
+
+    /// The client for webhook notifications (the feed's own client is used for its pages).
     http_client: ClientWithMiddleware,
+
     max_initial_sleep: Duration,
+
+    /// Whether the extractor's preparation (e.g., logging in) has run successfully.
+    prepared: bool,
+
+    /// The current interval between updates (adjusted if the feed's is adaptive).
+    fetch_interval: Duration,
+
+    /// The number of consecutive updates without new entries since the fetch interval was last
+    /// adjusted.
+    idle_updates: u32,
+}
+
+/// Marks the feed's fetcher task as dead when dropped (even if the task panics).
+struct AliveGuard {
+    feeds: Arc<HashMap<String, Feed>>,
+    name: String,
+}
+
+impl AliveGuard {
+    fn new(feeds: Arc<HashMap<String, Feed>>, name: String) -> Self {
+        feeds[&name].task_alive.store(true, Ordering::Relaxed);
+
+        Self { feeds, name }
+    }
+}
+
+impl Drop for AliveGuard {
+    fn drop(&mut self) {
+        self.feeds[&self.name]
+            .task_alive
+            .store(false, Ordering::Relaxed);
+    }
 }
 
 impl Task {
     async fn run(mut self) {
+        let _alive = AliveGuard::new(self.feeds.clone(), self.name.clone());
         let offset = self.rng.gen_range(Duration::ZERO..self.max_initial_sleep);
 
         let initial_sleep = if let Ok(Some(last_update)) = self.last_update().await {
@@ -135,12 +300,13 @@ impl Task {
         };
 
         debug!("Scheduling the next update in {}s", initial_sleep.as_secs());
+        self.publish_next_fetch(Some(initial_sleep));
         let mut next_fetch = pin!(time::sleep(initial_sleep));
         let force_update_notify = self.feed().force_update.clone().unwrap();
         let mut force_update = pin!(force_update_notify.notified());
 
         loop {
-            select! {
+            let forced = select! {
                 _ = self.cancel.cancelled() => {
                     debug!("Received a cancellation signal; exiting");
                     break;
@@ -156,26 +322,73 @@ impl Task {
                         "Received a forced feed update request \
                             (preempted the next scheduled update by {preempted_by}s)"
                     );
+
+                    true
+                }
+
+                _ = &mut next_fetch => false,
+            };
+
+            // forced updates are run regardless.
+            let quiet_hours_delay = if forced {
+                None
+            } else {
+                self.quiet_hours_delay()
+            };
+
+            if let Some(delay) = quiet_hours_delay {
+                self.publish_next_fetch(Some(delay));
+                next_fetch.as_mut().reset(Instant::now() + delay);
+
+                continue;
+            }
+
+            self.publish_next_fetch(None);
+            let queue_depth = self.fetch_queue.depth();
+
+            if queue_depth > 0 {
+                debug!("Waiting for a fetch slot ({queue_depth} feeds queued)");
+            }
+
+            let permit = select! {
+                _ = self.cancel.cancelled() => {
+                    debug!("Received a cancellation signal; exiting");
+                    break;
                 }
 
-                _ = &mut next_fetch => {}
+                permit = self.fetch_queue.acquire(self.feed().priority) => permit,
+            };
+
+            let outcome = self.update(forced).await;
+
+            if let Some(cookie_jar) = &self.feed().cookie_jar {
+                if let Err(e) = cookie_jar.save().await {
+                    warn!(
+                        "Could not save the cookies of the feed `{}`: {e:#}",
+                        self.name
+                    );
+                }
             }
 
-            if let Err(e) = self.update().await {
+            if let Some(message) = outcome.error_message() {
                 error!(
-                    "Encountered a failure while updating the feed `{}`: {e:#}",
+                    "Encountered a failure while updating the feed `{}`: {message}",
                     self.name
                 );
             }
 
-            let fetch_interval = self.feed().fetch_interval;
+            self.adapt_fetch_interval(&outcome);
+            self.feed().status.lock().unwrap().record(outcome);
+
+            drop(permit);
+
+            let fetch_interval = self.fetch_interval;
             debug!(
                 "Scheduling the next update in {}s",
                 fetch_interval.as_secs()
             );
-            next_fetch
-                .as_mut()
-                .reset(Instant::now() + self.feed().fetch_interval);
+            self.publish_next_fetch(Some(fetch_interval));
+            next_fetch.as_mut().reset(Instant::now() + fetch_interval);
         }
     }
 
@@ -183,6 +396,67 @@ impl Task {
         &self.feeds[&self.name]
     }
 
+    /// Adjusts the fetch interval to how often the feed gets new entries (if it's adaptive).
+    ///
+    /// Failed updates leave it as is.
+    fn adapt_fetch_interval(&mut self, outcome: &FetchOutcome) {
+        let Some((min, max)) = self.feed().adaptive_fetch_interval else {
+            return;
+        };
+        let new_entry_count = match *outcome {
+            FetchOutcome::Success {
+                new_entry_count, ..
+            } => new_entry_count,
+            FetchOutcome::Unchanged => 0,
+            _ => return,
+        };
+
+        let fetch_interval = if new_entry_count > 0 {
+            self.idle_updates = 0;
+
+            self.fetch_interval.mul_f64(TIGHTEN_FACTOR).max(min)
+        } else {
+            self.idle_updates += 1;
+
+            if self.idle_updates < IDLE_UPDATES_BEFORE_BACKOFF {
+                return;
+            }
+
+            self.idle_updates = 0;
+
+            self.fetch_interval.mul_f64(BACKOFF_FACTOR).min(max)
+        };
+
+        if fetch_interval != self.fetch_interval {
+            debug!(
+                "Adjusted the fetch interval from {}s to {}s",
+                self.fetch_interval.as_secs(),
+                fetch_interval.as_secs(),
+            );
+            self.fetch_interval = fetch_interval;
+        }
+    }
+
+    /// If fetching is paused at the moment (by `no_fetch_between`), returns how long to defer the
+    /// update by.
+    fn quiet_hours_delay(&self) -> Option<Duration> {
+        let window = self.feed().no_fetch_between?;
+        let remaining = window.remaining(OffsetDateTime::now_utc())?;
+        let delay = remaining.try_into().unwrap_or(Duration::ZERO);
+        info!(
+            "Deferring the update by {}s: fetching is paused between {window}",
+            delay.as_secs(),
+        );
+
+        Some(delay)
+    }
+
+    /// Records when the next update is scheduled (`None` if it's in progress).
+    fn publish_next_fetch(&self, delay: Option<Duration>) {
+        self.feed().status.lock().unwrap().next_fetch =
+            delay.map(|delay| OffsetDateTime::now_utc() + delay);
+    }
+
     async fn last_update(&self) -> Result<Option<OffsetDateTime>> {
         let mut tx = self.storage.begin().await?;
         let last_update = tx.get_feed_last_updated(&self.name).await?;
@@ -191,53 +465,452 @@ impl Task {
         Ok(last_update)
     }
 
-    async fn update(&mut self) -> Result<()> {
-        let url = self.feed().request_url.clone();
+    /// Updates the feed. Unless the update is `forced`, the source page is not extracted if it's
+    /// unchanged.
+    async fn update(&mut self, forced: bool) -> FetchOutcome {
+        if !self.prepared {
+            if let Err(e) = self.prepare().await {
+                return FetchOutcome::ExtractionError {
+                    message: format!("{e:#}"),
+                };
+            }
+        }
 
-        let response = self
-            .http_client
-            .get(url)
-            .send()
-            .await
-            .map_err(Into::into)
-            .and_then(|r| r.error_for_status().context("server returned an error"))
-            .with_context(|| anyhow!("could not fetch `{}`", self.feed().request_url))?;
-        let body = response.text().await.with_context(|| {
-            anyhow!(
-                "could not read the response when fetching `{}`",
-                self.feed().request_url
-            )
-        })?;
+        let max_pages = self.feed().max_pages;
+        let mut url = self.feed().request_url.clone();
+        let mut visited = HashSet::new();
+        let mut entries = vec![];
+        let mut channel = None;
 
-        let entries = {
-            let feeds = self.feeds.clone();
-            let name = self.name.clone();
-            let span = info_span!("extractor");
-
-            tokio::task::spawn_blocking(move || {
-                let _span = span.enter();
-                let feed = &feeds[&name];
-
-                feed.extractor
-                    .lock()
-                    .unwrap()
-                    .extract(ExtractorContext::new(&feed.request_url), &body)
-                    .context("could not extract feed entries")
-            })
-            .await
-            .context("running the extractor failed")??
+        // the number of entries extracted before post-processing (which may filter them out).
+        let mut extracted_count = 0;
+
+        // the first page is kept for `feedgen verify`.
+        let mut first_page = None;
+
+        // the extraction failures the feed's policy applies to.
+        let mut failures = vec![];
+
+        // whether the preparation has been rerun because the first page asked to log in.
+        let mut reprepared = false;
+        let mut page = 1;
+
+        while page <= max_pages {
+            visited.insert(url.clone());
+
+            let (response, body) = match fetch_page(&self.feed().http_client, &url).await {
+                Ok(page) => page,
+                Err(e) if page == 1 => return FetchOutcome::http_error(&e),
+
+                Err(e) => {
+                    warn!(
+                        "Could not fetch page {page}; keeping the entries of the previous \
+                            pages: {e:#}",
+                    );
+                    failures.push(format!("could not fetch page {page}: {e:#}"));
+                    break;
+                }
+            };
+
+            if page == 1 && !forced {
+                match self.is_unchanged(&body).await {
+                    Ok(false) => {}
+
+                    Ok(true) => {
+                        info!("The source page is unchanged; skipping the extraction");
+
+                        return match self.touch().await {
+                            Ok(()) => FetchOutcome::Unchanged,
+
+                            Err(e) => FetchOutcome::StorageError {
+                                message: format!("{e:#}"),
+                            },
+                        };
+                    }
+
+                    Err(e) => warn!("Could not check whether the source page has changed: {e:#}"),
+                }
+            }
+
+            // relative dates are resolved against the same time when the first page is replayed.
+            let fetch_time = OffsetDateTime::now_utc();
+
+            if page == 1 {
+                first_page = Some((response.clone(), body.clone(), fetch_time));
+            }
+
+            let extraction = match self.extract(url.clone(), response, body, fetch_time).await {
+                Ok((extraction, count)) => {
+                    extracted_count += count;
+
+                    extraction
+                }
+
+                Err(e) if page == 1 && !reprepared && e.is::<LoginRequired>() => {
+                    info!("The source page asks to log in; running the preparation again");
+                    reprepared = true;
+
+                    if let Err(e) = self.prepare().await {
+                        return FetchOutcome::ExtractionError {
+                            message: format!("{e:#}"),
+                        };
+                    }
+
+                    continue;
+                }
+
+                Err(e) if page == 1 => {
+                    return FetchOutcome::ExtractionError {
+                        message: format!("{e:#}"),
+                    }
+                }
+
+                Err(e) => {
+                    warn!(
+                        "Could not extract page {page}; keeping the entries of the previous \
+                            pages: {e:#}",
+                    );
+                    failures.push(format!("could not extract page {page}: {e:#}"));
+                    break;
+                }
+            };
+
+            if extraction.skipped > 0 {
+                failures.push(format!(
+                    "{} entries of page {page} could not be extracted",
+                    extraction.skipped,
+                ));
+            }
+
+            entries.extend(extraction.entries);
+
+            if page == 1 {
+                channel = extraction.channel;
+            }
+
+            match extraction.next_page {
+                None => break,
+
+                Some(next_page) if visited.contains(&next_page) => {
+                    warn!(
+                        "Page {page} links to an already fetched page `{next_page}` as the \
+                            next one",
+                    );
+                    break;
+                }
+
+                Some(next_page) if page == max_pages => {
+                    debug!(
+                        "Not fetching the next page `{next_page}`: reached the limit of \
+                            {max_pages} pages",
+                    );
+                    break;
+                }
+
+                Some(next_page) => url = next_page,
+            }
+
+            page += 1;
+        }
+
+        // too few entries usually means the layout of the source page has changed.
+        let min_entries = self.feed().min_entries;
+
+        if extracted_count < min_entries {
+            return FetchOutcome::ExtractionError {
+                message: format!(
+                    "extracted {extracted_count} entries, fewer than the minimum of {min_entries}",
+                ),
+            };
+        }
+
+        if extracted_count == 0 {
+            failures.push("no entries were extracted".into());
+        }
+
+        if !failures.is_empty() {
+            let message = failures.join("; ");
+
+            match self.feed().on_extraction_failure {
+                ExtractionFailurePolicy::Fail => {
+                    return FetchOutcome::ExtractionError { message };
+                }
+
+                ExtractionFailurePolicy::Skip => {
+                    return FetchOutcome::Discarded {
+                        entry_count: entries.len(),
+                        message,
+                    };
+                }
+
+                ExtractionFailurePolicy::Accept => {}
+            }
+        }
+
+        let mut entries = self.check(entries);
+        self.fetch_full_content(&mut entries).await;
+
+        let entry_count = entries.len();
+
+        match self.store(entries, channel, first_page).await {
+            Ok(new_entry_count) => FetchOutcome::Success {
+                entry_count,
+                new_entry_count,
+            },
+
+            Err(e) => FetchOutcome::StorageError {
+                message: format!("{e:#}"),
+            },
+        }
+    }
+
+    /// Whether the source page (and the feed's config) is the same as in the previous update.
+    ///
+    /// Always `false` if the feed's unchanged pages are extracted anyway.
+    async fn is_unchanged(&self, body: &str) -> Result<bool> {
+        let Some(page_hash) = self.feed().page_hash(body) else {
+            return Ok(false);
+        };
+
+        let mut tx = self.storage.begin().await?;
+        let stored_hash = tx.get_response_body_hash(&self.name).await?;
+        tx.commit().await?;
+
+        Ok(stored_hash == Some(page_hash))
+    }
+
+    /// Records the update of a feed whose source page is unchanged.
+    async fn touch(&self) -> Result<()> {
+        let mut tx = self.storage.begin().await?;
+        tx.touch_feed(&self.name).await?;
+        tx.commit().await
+    }
+
+    /// Extracts the page retrieved from `url`, returning the post-processed entries along with the
+    /// number of entries extracted.
+    async fn extract(
+        &mut self,
+        url: Url,
+        response: Response,
+        body: String,
+        fetch_time: OffsetDateTime,
+    ) -> Result<(Extraction, usize)> {
+        let feeds = self.feeds.clone();
+        let name = self.name.clone();
+        let cancel = self.cancel.clone();
+        let storage = self.storage.clone();
+        let span = info_span!("extractor", %url);
+
+        tokio::task::spawn_blocking(move || -> Result<(Extraction, usize)> {
+            let _span = span.enter();
+            let feed = &feeds[&name];
+            response.check_content_type(feed.expected_content)?;
+
+            let ctx = ExtractorContext::new(&url)
+                .with_feed_name(&name)
+                .with_response(Some(&response))
+                .with_fetch_time(fetch_time)
+                .with_timezone(feed.timezone)
+                .with_http_client(&feed.http_client, Some(&cancel))
+                .with_storage(&storage);
+            let base_url = ctx.base_url();
+
+            let mut extraction = feed
+                .extractor
+                .lock()
+                .unwrap()
+                .extract(ctx, &body)
+                .context("could not extract feed entries")?;
+            let count = extraction.entries.len();
+            feed.postprocessor
+                .process(base_url, &mut extraction.entries);
+
+            Ok((extraction, count))
+        })
+        .await
+        .context("running the extractor failed")?
+    }
+
+    /// Runs the extractor's preparation before the source page is fetched.
+    async fn prepare(&mut self) -> Result<()> {
+        let feeds = self.feeds.clone();
+        let name = self.name.clone();
+        let cancel = self.cancel.clone();
+        let storage = self.storage.clone();
+        let span = info_span!("prepare");
+
+        tokio::task::spawn_blocking(move || -> Result<()> {
+            let _span = span.enter();
+            let feed = &feeds[&name];
+
+            let ctx = ExtractorContext::new(&feed.request_url)
+                .with_feed_name(&name)
+                .with_timezone(feed.timezone)
+                .with_http_client(&feed.http_client, Some(&cancel))
+                .with_storage(&storage);
+
+            feed.extractor
+                .lock()
+                .unwrap()
+                .prepare(ctx)
+                .context("could not prepare fetching the feed")
+        })
+        .await
+        .context("running the extractor failed")??;
+
+        self.prepared = true;
+
+        Ok(())
+    }
+
+    /// Replaces the contents of new entries with the ones extracted from their pages.
+    ///
+    /// Stored entries keep their stored contents so that their pages are only retrieved once.
+    async fn fetch_full_content(&self, entries: &mut [Entry]) {
+        let Some(cfg) = &self.feed().full_content else {
+            return;
         };
 
+        for entry in entries {
+            let stored = async {
+                let mut tx = self.storage.begin().await?;
+                let content = tx.get_entry_content(&self.name, &entry.id).await?;
+                tx.commit().await?;
+
+                Ok::<_, anyhow::Error>(content)
+            }
+            .await;
+
+            match stored {
+                Ok(Some(content)) => {
+                    entry.content = content.or(entry.content.take());
+
+                    continue;
+                }
+
+                Ok(None) => {}
+
+                Err(e) => {
+                    warn!(
+                        entry_id = %entry.id,
+                        "Could not check whether the entry is stored: {e:#}",
+                    );
+
+                    continue;
+                }
+            }
+
+            let span = info_span!("full_content", entry_id = %entry.id, url = %entry.url);
+
+            match article::fetch(&self.feed().http_client, cfg, &entry.url)
+                .instrument(span)
+                .await
+            {
+                Ok(Some(mut content)) => {
+                    self.feed().postprocessor.process_html(
+                        &entry.id,
+                        "content",
+                        &mut content,
+                        &entry.url,
+                    );
+                    entry.content = Some(content);
+                }
+
+                Ok(None) => warn!(
+                    entry_id = %entry.id,
+                    "Found no content on the entry's page `{}`",
+                    entry.url,
+                ),
+
+                Err(e) => warn!(
+                    entry_id = %entry.id,
+                    "Could not retrieve the full content of the entry: {e:#}",
+                ),
+            }
+        }
+    }
+
+    /// Validates the entries, dropping the invalid ones if the output is strict.
+    fn check(&self, mut entries: Vec<Entry>) -> Vec<Entry> {
+        let problems = validate::check(&entries);
+
+        for problem in &problems {
+            // non-fatal problems are found again on every update, which would flood the log.
+            if problem.fatal {
+                warn!("Found a problem in the extracted entries: {problem}");
+            } else {
+                debug!("Found a problem in the extracted entries: {problem}");
+            }
+        }
+
+        if self.feed().strict_output && problems.iter().any(|problem| problem.fatal) {
+            let count = entries.len();
+            entries = validate::reject(entries, &problems);
+            warn!("Rejected {} invalid entries", count - entries.len());
+        }
+
+        entries
+    }
+
+    /// Stores the entries, the feed metadata, and the first source page, returning the number of
+    /// new entries.
+    async fn store(
+        &mut self,
+        entries: Vec<Entry>,
+        channel: Option<ChannelInfo>,
+        first_page: Option<(Response, String, OffsetDateTime)>,
+    ) -> Result<usize> {
         let count = entries.len();
+        let image_urls = if self.feed().proxy_images {
+            entries.iter().flat_map(imageproxy::image_urls).collect()
+        } else {
+            vec![]
+        };
 
         let mut tx = self.storage.begin().await?;
-        tx.store_entries(&self.name, entries)
+        let new_entries = tx
+            .store_entries(
+                &self.name,
+                entries,
+                self.feed().postprocessor.dedups_by_content(),
+            )
             .await
             .context("could not store entries to the DB")?;
+
+        if self.feed().proxy_images {
+            tx.register_images(&self.name, &image_urls).await?;
+            tx.prune_images(&self.name).await?;
+        }
+
+        tx.store_channel_info(&self.name, channel.as_ref())
+            .await
+            .context("could not store the feed metadata to the DB")?;
+
+        if let Some((response, body, fetched)) = &first_page {
+            let body_hash = self.feed().page_hash(body);
+            tx.store_response(&self.name, response, body, body_hash.as_deref(), *fetched)
+                .await
+                .context("could not store the source page to the DB")?;
+        }
         tx.commit().await?;
 
-        info!("Retrieved {count} entries");
+        info!("Retrieved {count} entries ({} new)", new_entries.len());
+        let new_entry_count = new_entries.len();
 
-        Ok(())
+        if !new_entries.is_empty() && !self.feed().webhooks.is_empty() {
+            let feeds = self.feeds.clone();
+            let name = self.name.clone();
+            let http_client = self.http_client.clone();
+
+            // deliver in the background so that slow webhooks don't hold up the fetch queue.
+            tokio::spawn(
+                async move {
+                    webhook::notify(&http_client, &feeds[&name].webhooks, &name, &new_entries).await
+                }
+                .instrument(info_span!("webhook")),
+            );
+        }
+
+        Ok(new_entry_count)
     }
 }
@@ -0,0 +1,38 @@
+use anyhow::{Context, Result};
+use reqwest::Url;
+use reqwest_middleware::ClientWithMiddleware;
+use scraper::Html;
+
+use crate::config::FullContentConfig;
+use crate::extractor::select_html_by_xpath;
+use crate::readability;
+
+use super::fetch_page;
+
+/// Extracts the full content of an entry from its page.
+pub fn extract(cfg: &FullContentConfig, html: &str) -> Result<Option<String>> {
+    match cfg {
+        FullContentConfig::Css { selector } => Ok(Html::parse_document(html)
+            .select(selector)
+            .next()
+            .map(|element| element.inner_html())
+            .filter(|s| !s.trim().is_empty())),
+
+        FullContentConfig::XPath { xpath } => select_html_by_xpath(html, xpath),
+        FullContentConfig::Readability => Ok(readability::extract(html)),
+    }
+}
+
+/// Retrieves the entry's page at `url` and extracts its full content.
+pub async fn fetch(
+    http_client: &ClientWithMiddleware,
+    cfg: &FullContentConfig,
+    url: &Url,
+) -> Result<Option<String>> {
+    let (_, body) = fetch_page(http_client, url).await?;
+    let cfg = cfg.clone();
+
+    tokio::task::spawn_blocking(move || extract(&cfg, &body))
+        .await
+        .context("running the content extractor failed")?
+}
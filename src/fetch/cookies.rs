@@ -0,0 +1,98 @@
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{self, BufReader};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use anyhow::{anyhow, bail, Context, Result};
+use reqwest::Url;
+use reqwest_cookie_store::{CookieStore, CookieStoreMutex};
+use tracing::debug;
+
+/// The cookies of an HTTP client, optionally persisted in a file.
+#[derive(Clone)]
+pub struct CookieJar {
+    store: Arc<CookieStoreMutex>,
+    path: Option<PathBuf>,
+}
+
+/// An empty jar kept in memory.
+impl Default for CookieJar {
+    fn default() -> Self {
+        Self {
+            store: Arc::new(CookieStoreMutex::new(CookieStore::default())),
+            path: None,
+        }
+    }
+}
+
+impl CookieJar {
+    /// Loads the cookies stored in `path` (if it exists) and adds the static `cookies` for `url`.
+    pub fn load(
+        path: Option<PathBuf>,
+        cookies: &BTreeMap<String, String>,
+        url: &Url,
+    ) -> Result<Self> {
+        let mut store = match &path {
+            Some(path) => match File::open(path) {
+                Ok(f) => cookie_store::serde::json::load(BufReader::new(f))
+                    .map_err(|e| anyhow!(e))
+                    .with_context(|| anyhow!("could not load cookies from `{}`", path.display()))?,
+
+                Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                    debug!("The cookie file `{}` does not exist yet", path.display());
+
+                    CookieStore::default()
+                }
+
+                Err(e) => {
+                    return Err(e).with_context(|| anyhow!("could not open `{}`", path.display()));
+                }
+            },
+
+            None => CookieStore::default(),
+        };
+
+        // the static cookies replace the stored ones with the same names.
+        for (name, value) in cookies {
+            if name.contains(['=', ';']) || value.contains(';') {
+                bail!("invalid cookie `{name}`: must not contain `;` (or `=` in the name)");
+            }
+
+            store
+                .parse(&format!("{name}={value}"), url)
+                .with_context(|| anyhow!("invalid cookie `{name}`"))?;
+        }
+
+        Ok(Self {
+            store: Arc::new(CookieStoreMutex::new(store)),
+            path,
+        })
+    }
+
+    /// The cookie store to give to an HTTP client.
+    pub fn store(&self) -> Arc<CookieStoreMutex> {
+        self.store.clone()
+    }
+
+    /// Writes the cookies to the file they were loaded from (if any).
+    ///
+    /// Session cookies are saved as well since the feed's session outlives the process.
+    pub async fn save(&self) -> Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+
+        let mut json = vec![];
+        cookie_store::serde::json::save_incl_expired_and_nonpersistent(
+            &self.store.lock().unwrap(),
+            &mut json,
+        )
+        .map_err(|e| anyhow!(e))
+        .context("could not serialize the cookies")?;
+
+        tokio::fs::write(path, json)
+            .await
+            .with_context(|| anyhow!("could not write `{}`", path.display()))
+    }
+}
@@ -0,0 +1,71 @@
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use base64::prelude::*;
+use http::Extensions;
+use reqwest::header::{HeaderValue, AUTHORIZATION};
+use reqwest::{Request, Response, Url};
+use reqwest_middleware::{Middleware, Next};
+
+use crate::config;
+
+/// Sends the feed's credentials in the `Authorization` header, but only to the origin of its
+/// `request_url` (and not, say, to the sites of the entries or a next page on another host).
+pub struct Credentials {
+    url: Url,
+    authorization: HeaderValue,
+}
+
+impl Credentials {
+    pub fn new(credentials: &config::Credentials, url: &Url) -> Result<Self> {
+        Ok(Self {
+            url: url.clone(),
+            authorization: authorization(credentials)?,
+        })
+    }
+}
+
+#[async_trait]
+impl Middleware for Credentials {
+    async fn handle(
+        &self,
+        mut req: Request,
+        extensions: &mut Extensions,
+        next: Next<'_>,
+    ) -> reqwest_middleware::Result<Response> {
+        if req.url().origin() == self.url.origin() {
+            req.headers_mut()
+                .insert(AUTHORIZATION, self.authorization.clone());
+        }
+
+        next.run(req, extensions).await
+    }
+}
+
+/// Makes the value of the `Authorization` header.
+fn authorization(credentials: &config::Credentials) -> Result<HeaderValue> {
+    let value = match credentials {
+        config::Credentials::Basic { username, password } => {
+            let password = password
+                .as_ref()
+                .map(|password| password.read())
+                .transpose()
+                .context("could not read the password")?
+                .unwrap_or_default();
+
+            let encoded = BASE64_STANDARD.encode(format!("{username}:{password}"));
+
+            format!("Basic {encoded}")
+        }
+
+        config::Credentials::Bearer { token } => {
+            let token = token.read().context("could not read the token")?;
+
+            format!("Bearer {token}")
+        }
+    };
+
+    let mut value = HeaderValue::try_from(value).context("the credentials are malformed")?;
+    value.set_sensitive(true);
+
+    Ok(value)
+}
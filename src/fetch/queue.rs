@@ -0,0 +1,132 @@
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::num::NonZeroUsize;
+use std::sync::{Arc, Mutex};
+
+use tokio::sync::oneshot;
+
+struct Waiter {
+    priority: i32,
+    seq: u64,
+    tx: oneshot::Sender<FetchPermit>,
+}
+
+impl PartialEq for Waiter {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Waiter {}
+
+impl PartialOrd for Waiter {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Waiter {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // higher priorities first; FIFO among equal priorities.
+        self.priority
+            .cmp(&other.priority)
+            .then_with(|| other.seq.cmp(&self.seq))
+    }
+}
+
+struct FetchQueueInner {
+    available: usize,
+    active: usize,
+    next_seq: u64,
+    waiters: BinaryHeap<Waiter>,
+}
+
+/// Limits the number of concurrently running fetches.
+///
+/// When all slots are taken, waiting feeds are admitted in the order of their priority.
+pub struct FetchQueue {
+    inner: Mutex<FetchQueueInner>,
+}
+
+impl FetchQueue {
+    pub fn new(max_concurrent_fetches: NonZeroUsize) -> Self {
+        Self {
+            inner: Mutex::new(FetchQueueInner {
+                available: max_concurrent_fetches.get(),
+                active: 0,
+                next_seq: 0,
+                waiters: BinaryHeap::new(),
+            }),
+        }
+    }
+
+    pub async fn acquire(self: &Arc<Self>, priority: i32) -> FetchPermit {
+        let rx = {
+            let mut inner = self.inner.lock().unwrap();
+
+            if inner.available > 0 && inner.waiters.is_empty() {
+                inner.available -= 1;
+                inner.active += 1;
+
+                return FetchPermit {
+                    queue: self.clone(),
+                };
+            }
+
+            let (tx, rx) = oneshot::channel();
+            let seq = inner.next_seq;
+            inner.next_seq += 1;
+            inner.waiters.push(Waiter { priority, seq, tx });
+
+            rx
+        };
+
+        // the sender is only dropped along with the queue, which we hold a reference to.
+        rx.await.unwrap()
+    }
+
+    /// Returns the number of feeds waiting for a free fetch slot.
+    pub fn depth(&self) -> usize {
+        let inner = self.inner.lock().unwrap();
+
+        inner
+            .waiters
+            .iter()
+            .filter(|waiter| !waiter.tx.is_closed())
+            .count()
+    }
+
+    /// Returns the number of fetches currently in progress.
+    pub fn active(&self) -> usize {
+        self.inner.lock().unwrap().active
+    }
+
+    fn release(self: &Arc<Self>) {
+        let mut inner = self.inner.lock().unwrap();
+
+        let Some(waiter) = inner.waiters.pop() else {
+            inner.available += 1;
+            inner.active -= 1;
+
+            return;
+        };
+
+        drop(inner);
+
+        // if the waiter is gone, the permit is returned and dropped, which releases it again.
+        let _ = waiter.tx.send(FetchPermit {
+            queue: self.clone(),
+        });
+    }
+}
+
+/// A fetch slot. Released when dropped.
+pub struct FetchPermit {
+    queue: Arc<FetchQueue>,
+}
+
+impl Drop for FetchPermit {
+    fn drop(&mut self) {
+        self.queue.release();
+    }
+}
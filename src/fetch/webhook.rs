@@ -0,0 +1,62 @@
+use anyhow::{anyhow, Context, Result};
+use reqwest::header;
+use reqwest::Url;
+use reqwest_middleware::ClientWithMiddleware;
+use serde::Serialize;
+use tracing::{debug, error};
+
+use crate::extractor::Entry;
+
+#[derive(Serialize, Debug, Clone)]
+struct Payload<'a> {
+    feed: &'a str,
+    entries: &'a [Entry],
+}
+
+/// POSTs the new entries of a feed to each of the webhook URLs.
+///
+/// Delivery failures are logged and otherwise ignored.
+pub async fn notify(
+    http_client: &ClientWithMiddleware,
+    urls: &[Url],
+    feed_name: &str,
+    entries: &[Entry],
+) {
+    if urls.is_empty() || entries.is_empty() {
+        return;
+    }
+
+    let body = match serde_json::to_vec(&Payload {
+        feed: feed_name,
+        entries,
+    }) {
+        Ok(body) => body,
+
+        Err(e) => {
+            error!("Could not serialize the webhook payload: {e:#}");
+
+            return;
+        }
+    };
+
+    for url in urls {
+        match post(http_client, url, body.clone()).await {
+            Ok(()) => debug!(%url, "Delivered {} new entries to a webhook", entries.len()),
+            Err(e) => error!("Could not deliver new entries to a webhook: {e:#}"),
+        }
+    }
+}
+
+async fn post(http_client: &ClientWithMiddleware, url: &Url, body: Vec<u8>) -> Result<()> {
+    http_client
+        .post(url.clone())
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(body)
+        .send()
+        .await
+        .map_err(Into::into)
+        .and_then(|r| r.error_for_status().context("server returned an error"))
+        .with_context(|| anyhow!("could not send a request to `{url}`"))?;
+
+    Ok(())
+}
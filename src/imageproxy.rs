@@ -0,0 +1,112 @@
+use lol_html::errors::RewritingError;
+use lol_html::{element, rewrite_str, RewriteStrSettings};
+use reqwest::Url;
+use sha2::{Digest, Sha256};
+use tracing::warn;
+
+use crate::extractor::Entry;
+
+/// Returns the key identifying the image at `url` in the `/img/:feed/:hash` route.
+pub fn hash(url: &Url) -> String {
+    Sha256::digest(url.as_str().as_bytes())
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+/// Returns the URLs of the entry's preview image and the images in its description and content.
+///
+/// Relative URLs in the HTML are skipped, as there's nothing to resolve them against at the time
+/// the feed is served.
+pub fn image_urls(entry: &Entry) -> Vec<Url> {
+    let mut urls = entry.image.iter().cloned().collect::<Vec<_>>();
+
+    for (field, html) in html_fields(entry) {
+        if let Err(e) = find_images(html, &mut urls) {
+            warn!(entry_id = %entry.id, "Could not find images in the {field}: {e:#}");
+        }
+    }
+
+    urls
+}
+
+/// Replaces the entry's image URLs with the URLs of the proxy (`proxy_url` is the URL of the
+/// `/img/:feed` route). The access token of a private feed is added to them.
+pub fn rewrite(entry: &mut Entry, proxy_url: &str, token: Option<&str>) {
+    let query = token
+        .map(|token| format!("?token={}", urlencoding::encode(token)))
+        .unwrap_or_default();
+    let proxied = |url: &Url| format!("{proxy_url}/{}{query}", hash(url));
+
+    if let Some(image) = &mut entry.image {
+        match Url::parse(&proxied(image)) {
+            Ok(url) => *image = url,
+            Err(e) => warn!("Could not construct the proxied image URL: {e:#}"),
+        }
+    }
+
+    let fields = [
+        ("description", Some(&mut entry.description)),
+        ("content", entry.content.as_mut()),
+    ];
+
+    for (field, html) in fields {
+        let Some(html) = html else {
+            continue;
+        };
+
+        match rewrite_images(html, proxied) {
+            Ok(rewritten) => *html = rewritten,
+            Err(e) => {
+                warn!(entry_id = %entry.id, "Could not rewrite image URLs in the {field}: {e:#}")
+            }
+        }
+    }
+}
+
+fn html_fields(entry: &Entry) -> impl Iterator<Item = (&'static str, &str)> {
+    std::iter::once(("description", entry.description.as_str()))
+        .chain(entry.content.as_deref().map(|content| ("content", content)))
+}
+
+fn find_images(html: &str, urls: &mut Vec<Url>) -> Result<(), RewritingError> {
+    rewrite_str(
+        html,
+        RewriteStrSettings {
+            element_content_handlers: vec![element!("img[src]", |el| {
+                if let Some(url) = el
+                    .get_attribute("src")
+                    .and_then(|src| Url::parse(src.trim()).ok())
+                {
+                    urls.push(url);
+                }
+
+                Ok(())
+            })],
+            ..RewriteStrSettings::default()
+        },
+    )
+    .map(drop)
+}
+
+fn rewrite_images(html: &str, proxied: impl Fn(&Url) -> String) -> Result<String, RewritingError> {
+    rewrite_str(
+        html,
+        RewriteStrSettings {
+            element_content_handlers: vec![element!("img[src]", |el| {
+                if let Some(url) = el
+                    .get_attribute("src")
+                    .and_then(|src| Url::parse(src.trim()).ok())
+                {
+                    el.set_attribute("src", &proxied(&url))?;
+
+                    // otherwise the browser would load the image from the source site anyway.
+                    el.remove_attribute("srcset");
+                }
+
+                Ok(())
+            })],
+            ..RewriteStrSettings::default()
+        },
+    )
+}
@@ -0,0 +1,74 @@
+use std::fmt;
+use std::fmt::Formatter;
+
+use anyhow::{anyhow, Result};
+use serde::de::Visitor;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::Value;
+
+/// A JSONPath expression (RFC 9535), written in configs as a string.
+#[derive(Debug, Clone)]
+pub struct JsonPath {
+    src: String,
+    path: serde_json_path::JsonPath,
+}
+
+impl JsonPath {
+    pub fn new(s: String) -> Result<Self> {
+        let path = serde_json_path::JsonPath::parse(&s)
+            .map_err(|e| anyhow!("could not parse the JSONPath expression: {e}"))?;
+
+        Ok(Self { src: s, path })
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.src
+    }
+
+    /// Returns the nodes the expression selects in `value`.
+    pub fn query<'v>(&self, value: &'v Value) -> Vec<&'v Value> {
+        self.path.query(value).all()
+    }
+}
+
+impl<'de> Deserialize<'de> for JsonPath {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct JsonPathVisitor;
+
+        impl<'de> Visitor<'de> for JsonPathVisitor {
+            type Value = JsonPath;
+
+            fn expecting(&self, formatter: &mut Formatter<'_>) -> fmt::Result {
+                write!(formatter, "a JSONPath expression")
+            }
+
+            fn visit_string<E>(self, v: String) -> Result<Self::Value, E>
+            where
+                E: serde::de::Error,
+            {
+                JsonPath::new(v).map_err(|e| E::custom(format!("{e:#}")))
+            }
+
+            fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+            where
+                E: serde::de::Error,
+            {
+                self.visit_string(v.into())
+            }
+        }
+
+        deserializer.deserialize_string(JsonPathVisitor)
+    }
+}
+
+impl Serialize for JsonPath {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(self.as_str())
+    }
+}
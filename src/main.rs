@@ -1,24 +1,39 @@
 mod cli;
 mod config;
+mod css;
 mod extractor;
 mod fetch;
+mod imageproxy;
+mod jsonpath;
+mod metadata;
+mod postprocess;
+mod readability;
+mod reldate;
 mod server;
 mod state;
 mod storage;
+mod systemd;
 mod template;
+mod validate;
+mod verify;
 mod xpath;
 
+use std::fs;
 use std::process::ExitCode;
 
-use anyhow::Result;
-use cli::Args;
+use anyhow::{anyhow, Context, Result};
+use cli::{Args, Command, LuaArgs};
+use config::{Config, ExtractorConfig};
+use extractor::Context as ExtractorContext;
 use fetch::Fetcher;
+use reqwest::Url;
 use server::Server;
 use state::State;
+use storage::Storage;
 use tokio::task::JoinSet;
 use tokio_util::sync::CancellationToken;
-use tracing::error;
 use tracing::Level;
+use tracing::{debug, error, warn};
 use tracing_subscriber::prelude::*;
 use tracing_subscriber::EnvFilter;
 
@@ -75,6 +90,7 @@ async fn main() -> ExitCode {
 
 async fn start(cancel: CancellationToken) -> Result<JoinSet<Result<()>>> {
     let mut args = Args::parse();
+    let command = args.command.take();
     let config_paths = args
         .config_path
         .take()
@@ -83,12 +99,30 @@ async fn start(cancel: CancellationToken) -> Result<JoinSet<Result<()>>> {
         .collect::<Vec<_>>();
     let mut config = config::load(&config_paths)?;
     config.update(args);
+
+    match command {
+        Some(Command::Lua(lua_args)) => {
+            run_lua_repl(config, lua_args).await?;
+
+            return Ok(JoinSet::new());
+        }
+
+        Some(Command::Verify(verify_args)) => {
+            verify::run(config, verify_args).await?;
+
+            return Ok(JoinSet::new());
+        }
+
+        None => {}
+    }
+
     let state = State::new(config).await?;
 
     let fetcher = Fetcher::new(
         state.feeds.clone(),
-        state.cfg.cache_dir.clone(),
+        state.http_client.clone(),
         state.storage.clone(),
+        state.fetch_queue.clone(),
         state.cfg.max_initial_fetch_sleep.into(),
     );
     let server = Server::new(state).await?;
@@ -97,5 +131,122 @@ async fn start(cancel: CancellationToken) -> Result<JoinSet<Result<()>>> {
     tasks.spawn(fetcher.run(cancel.clone()));
     tasks.spawn(server.serve(cancel.clone()));
 
+    notify_systemd(cancel);
+
     Ok(tasks)
 }
+
+/// Runs `feedgen lua`.
+async fn run_lua_repl(mut config: Config, args: LuaArgs) -> Result<()> {
+    if args.feed.is_some() {
+        let storage = Storage::new(&config.db_path).await?;
+        State::apply_saved_feeds(&mut config, &storage).await?;
+    }
+
+    let feed = match &args.feed {
+        Some(name) => Some(
+            config
+                .feeds
+                .get(name)
+                .ok_or_else(|| anyhow!("unknown feed `{name}`"))?,
+        ),
+
+        None => None,
+    };
+
+    let extractor_cfg = match feed.map(|feed| &feed.extractor) {
+        Some(ExtractorConfig::Lua(cfg)) => Some(cfg.clone()),
+
+        Some(_) => {
+            warn!("The feed does not use a Lua extractor; only its page is loaded");
+            None
+        }
+
+        None => None,
+    };
+
+    let http_client = fetch::make_http_client(&config)?;
+    let http_client = match feed {
+        Some(feed) => fetch::make_feed_http_client(&http_client, &config, feed)?.0,
+        None => http_client,
+    };
+    let (url, response, page) = match (&args.input, feed) {
+        (Some(path), _) => {
+            let page = fs::read_to_string(path)
+                .with_context(|| anyhow!("could not read `{}`", path.display()))?;
+            // resolve relative links against the feed's page if the file is a copy of it.
+            let url = match feed {
+                Some(feed) => feed.request_url.clone(),
+
+                None => {
+                    let path = fs::canonicalize(path)
+                        .with_context(|| anyhow!("could not resolve `{}`", path.display()))?;
+
+                    Url::from_file_path(&path)
+                        .map_err(|_| anyhow!("cannot convert `{}` to a URL", path.display()))?
+                }
+            };
+
+            (url, None, Some(page))
+        }
+
+        (None, Some(feed)) => {
+            let (response, page) = fetch::fetch_page(&http_client, &feed.request_url).await?;
+
+            (feed.request_url.clone(), Some(response), Some(page))
+        }
+
+        (None, None) => (Url::parse("about:blank").unwrap(), None, None),
+    };
+
+    let timezone = feed.and_then(|feed| feed.timezone);
+
+    tokio::task::spawn_blocking(move || {
+        let ctx = ExtractorContext::new(&url)
+            .with_response(response.as_ref())
+            .with_timezone(timezone)
+            .with_http_client(&http_client, None);
+
+        extractor::run_lua_repl(&config.lua, extractor_cfg.as_ref(), ctx, page.as_deref())
+    })
+    .await
+    .context("the Lua session failed")?
+}
+
+/// Reports readiness to systemd and keeps its watchdog fed until shutdown.
+fn notify_systemd(cancel: CancellationToken) {
+    if let Err(e) = systemd::notify("READY=1") {
+        warn!("{e:#}");
+    }
+
+    let watchdog_interval = systemd::watchdog_interval();
+
+    tokio::spawn(async move {
+        if let Some(watchdog_interval) = watchdog_interval {
+            let period = watchdog_interval / 2;
+            debug!(
+                "Sending watchdog notifications every {}ms",
+                period.as_millis()
+            );
+            let mut interval = tokio::time::interval(period);
+
+            loop {
+                tokio::select! {
+                    _ = cancel.cancelled() => break,
+
+                    _ = interval.tick() => {
+                        if let Err(e) = systemd::notify("WATCHDOG=1") {
+                            warn!("{e:#}");
+                        }
+                    }
+                }
+            }
+        } else {
+            cancel.cancelled().await;
+        }
+
+        if let Err(e) = systemd::notify("STOPPING=1") {
+            warn!("{e:#}");
+        }
+    });
+}
@@ -0,0 +1,393 @@
+//! Extraction of the structured metadata pages annotate themselves with: JSON-LD, microdata,
+//! and OpenGraph tags.
+
+use std::collections::HashSet;
+
+use scraper::{ElementRef, Html};
+use serde_json::Value;
+use time::format_description::well_known::Rfc3339;
+use time::macros::format_description;
+use time::{Date, OffsetDateTime, PrimitiveDateTime};
+use tracing::{debug, warn};
+
+use crate::config::Timezone;
+
+/// An article described by a page's metadata.
+#[derive(Debug, Clone, Default)]
+pub struct Article {
+    pub title: Option<String>,
+
+    /// The URL of the article as written in the page (possibly relative).
+    pub url: Option<String>,
+
+    /// A plain-text summary.
+    pub description: Option<String>,
+
+    pub author: Option<String>,
+    pub published: Option<OffsetDateTime>,
+
+    /// The URL of a preview image (possibly relative).
+    pub image: Option<String>,
+
+    pub keywords: Vec<String>,
+}
+
+fn non_empty(s: &str) -> Option<String> {
+    Some(s.trim()).filter(|s| !s.is_empty()).map(Into::into)
+}
+
+/// Parses an ISO 8601 datetime. Dates without a time are midnight, and datetimes without an
+/// offset are assumed to be in `tz` (UTC if unset).
+fn parse_date(s: &str, tz: Option<Timezone>) -> Option<OffsetDateTime> {
+    let s = s.trim();
+    let datetime_format = format_description!("[year]-[month]-[day]T[hour]:[minute]:[second]");
+
+    let assume_tz = |datetime: PrimitiveDateTime| match tz {
+        Some(tz) => tz.assume(datetime),
+        None => Some(datetime.assume_utc()),
+    };
+
+    OffsetDateTime::parse(s, &Rfc3339)
+        .ok()
+        .or_else(|| {
+            PrimitiveDateTime::parse(s, datetime_format)
+                .ok()
+                .and_then(assume_tz)
+        })
+        .or_else(|| {
+            Date::parse(s, format_description!("[year]-[month]-[day]"))
+                .ok()
+                .and_then(|date| assume_tz(date.midnight()))
+        })
+        .or_else(|| {
+            warn!("The date `{s}` could not be parsed");
+
+            None
+        })
+}
+
+/// Whether a schema.org type (e.g., `NewsArticle` or `https://schema.org/BlogPosting`) describes
+/// an article.
+fn is_article_type(ty: &str) -> bool {
+    let ty = ty.rsplit(['/', ':']).next().unwrap_or(ty);
+
+    ty.ends_with("Article")
+        || matches!(
+            ty,
+            "BlogPosting" | "SocialMediaPosting" | "LiveBlogPosting" | "Report" | "Review"
+        )
+}
+
+fn is_item_list_type(ty: &str) -> bool {
+    ty.rsplit(['/', ':']).next().unwrap_or(ty) == "ItemList"
+}
+
+fn json_types(node: &Value) -> Vec<&str> {
+    match node.get("@type") {
+        Some(Value::String(ty)) => vec![ty.as_str()],
+        Some(Value::Array(types)) => types.iter().filter_map(Value::as_str).collect(),
+        _ => vec![],
+    }
+}
+
+/// Returns a string value, the first of an array, or the `field` of an object (recursively).
+fn json_string(value: &Value, field: &str) -> Option<String> {
+    match value {
+        Value::String(s) => non_empty(s),
+        Value::Array(values) => values.iter().find_map(|value| json_string(value, field)),
+        Value::Object(_) => value.get(field).and_then(|value| json_string(value, field)),
+        _ => None,
+    }
+}
+
+fn json_article(node: &Value, tz: Option<Timezone>) -> Article {
+    let get = |key: &str| node.get(key);
+
+    Article {
+        title: get("headline")
+            .or(get("name"))
+            .and_then(|value| json_string(value, "name")),
+        url: get("url")
+            .or(get("mainEntityOfPage"))
+            .and_then(|value| json_string(value, "@id"))
+            .or_else(|| get("@id").and_then(Value::as_str).and_then(non_empty)),
+        description: get("description").and_then(|value| json_string(value, "name")),
+        author: get("author")
+            .or(get("creator"))
+            .and_then(|value| json_string(value, "name")),
+        published: get("datePublished")
+            .or(get("dateCreated"))
+            .or(get("dateModified"))
+            .and_then(Value::as_str)
+            .and_then(|s| parse_date(s, tz)),
+        image: get("image")
+            .or(get("thumbnailUrl"))
+            .and_then(|value| json_string(value, "url")),
+        keywords: match get("keywords") {
+            Some(Value::String(s)) => s.split(',').filter_map(non_empty).collect(),
+            Some(Value::Array(keywords)) => keywords
+                .iter()
+                .filter_map(Value::as_str)
+                .filter_map(non_empty)
+                .collect(),
+            _ => vec![],
+        },
+    }
+}
+
+/// Collects the articles described by a JSON-LD node, including those in `@graph`s and lists.
+fn collect_json_articles(node: &Value, tz: Option<Timezone>, articles: &mut Vec<Article>) {
+    match node {
+        Value::Array(nodes) => {
+            for node in nodes {
+                collect_json_articles(node, tz, articles);
+            }
+        }
+
+        Value::Object(object) => {
+            if let Some(graph) = object.get("@graph") {
+                collect_json_articles(graph, tz, articles);
+            }
+
+            let types = json_types(node);
+
+            if types.iter().any(|ty| is_article_type(ty)) {
+                articles.push(json_article(node, tz));
+            } else if types.iter().any(|ty| is_item_list_type(ty)) {
+                for item in node
+                    .get("itemListElement")
+                    .and_then(Value::as_array)
+                    .map(Vec::as_slice)
+                    .unwrap_or_default()
+                {
+                    // a `ListItem` either refers to the article or embeds it in `item`.
+                    let mut article = match item.get("item") {
+                        Some(embedded @ Value::Object(_)) => json_article(embedded, tz),
+                        _ => json_article(item, tz),
+                    };
+
+                    if article.url.is_none() {
+                        article.url = item
+                            .get("url")
+                            .or(item.get("item"))
+                            .and_then(Value::as_str)
+                            .and_then(non_empty);
+                    }
+
+                    if article.title.is_none() {
+                        article.title =
+                            item.get("name").and_then(Value::as_str).and_then(non_empty);
+                    }
+
+                    articles.push(article);
+                }
+            } else if let Some(main_entity) = object.get("mainEntity") {
+                collect_json_articles(main_entity, tz, articles);
+            }
+        }
+
+        _ => {}
+    }
+}
+
+fn json_ld_articles(html: &Html, tz: Option<Timezone>) -> Vec<Article> {
+    let mut articles = vec![];
+
+    for element in html
+        .root_element()
+        .descendants()
+        .filter_map(ElementRef::wrap)
+    {
+        let element_value = element.value();
+
+        if element_value.name() != "script"
+            || !element_value
+                .attr("type")
+                .is_some_and(|ty| ty.trim().eq_ignore_ascii_case("application/ld+json"))
+        {
+            continue;
+        }
+
+        let json = element.text().collect::<String>();
+
+        match serde_json::from_str::<Value>(&json) {
+            Ok(node) => collect_json_articles(&node, tz, &mut articles),
+            Err(e) => warn!("Could not parse a JSON-LD script: {e:#}"),
+        }
+    }
+
+    articles
+}
+
+/// The value of a microdata property.
+fn microdata_value(element: ElementRef<'_>) -> Option<String> {
+    let value = element.value();
+
+    // a nested item (e.g., a `Person` as the author) is represented by its name.
+    if value.attr("itemscope").is_some() {
+        return microdata_props(element)
+            .into_iter()
+            .find(|(name, _)| name == "name")
+            .map(|(_, value)| value)
+            .or_else(|| non_empty(&element.text().collect::<String>()));
+    }
+
+    let attr = match value.name() {
+        "meta" => Some("content"),
+        "a" | "link" | "area" => Some("href"),
+        "img" | "audio" | "video" | "source" | "iframe" | "embed" => Some("src"),
+        "time" => value.attr("datetime").map(|_| "datetime"),
+        "data" | "meter" => Some("value"),
+        _ => None,
+    };
+
+    match attr {
+        Some(attr) => value.attr(attr).and_then(non_empty),
+        None => non_empty(&element.text().collect::<String>()),
+    }
+}
+
+/// Collects the properties of a microdata item, excluding those of nested items.
+fn microdata_props(item: ElementRef<'_>) -> Vec<(String, String)> {
+    let mut props = vec![];
+    let mut stack = item
+        .children()
+        .rev()
+        .filter_map(ElementRef::wrap)
+        .collect::<Vec<_>>();
+
+    while let Some(element) = stack.pop() {
+        if let Some(names) = element.value().attr("itemprop") {
+            if let Some(value) = microdata_value(element) {
+                for name in names.split_whitespace() {
+                    props.push((name.to_owned(), value.clone()));
+                }
+            }
+        }
+
+        if element.value().attr("itemscope").is_none() {
+            stack.extend(element.children().rev().filter_map(ElementRef::wrap));
+        }
+    }
+
+    props
+}
+
+fn microdata_articles(html: &Html, tz: Option<Timezone>) -> Vec<Article> {
+    let mut articles = vec![];
+
+    for element in html
+        .root_element()
+        .descendants()
+        .filter_map(ElementRef::wrap)
+    {
+        let value = element.value();
+
+        if value.attr("itemscope").is_none()
+            || !value
+                .attr("itemtype")
+                .is_some_and(|types| types.split_whitespace().any(is_article_type))
+        {
+            continue;
+        }
+
+        let props = microdata_props(element);
+        let get = |names: &[&str]| {
+            names.iter().find_map(|&name| {
+                props
+                    .iter()
+                    .find(|(prop, _)| prop == name)
+                    .map(|(_, value)| value.clone())
+            })
+        };
+
+        articles.push(Article {
+            title: get(&["headline", "name"]),
+            url: get(&["url", "mainEntityOfPage"]),
+            description: get(&["description"]),
+            author: get(&["author", "creator"]),
+            published: get(&["datePublished", "dateCreated", "dateModified"])
+                .and_then(|s| parse_date(&s, tz)),
+            image: get(&["image", "thumbnailUrl"]),
+            keywords: props
+                .iter()
+                .filter(|(prop, _)| prop == "keywords")
+                .flat_map(|(_, value)| value.split(','))
+                .filter_map(non_empty)
+                .collect(),
+        });
+    }
+
+    articles
+}
+
+/// Returns the article described by the OpenGraph tags of the page, if it has a title.
+fn open_graph_article(html: &Html, tz: Option<Timezone>) -> Option<Article> {
+    let mut props: Vec<(&str, &str)> = vec![];
+
+    for element in html
+        .root_element()
+        .descendants()
+        .filter_map(ElementRef::wrap)
+    {
+        let value = element.value();
+
+        if value.name() != "meta" {
+            continue;
+        }
+
+        if let (Some(name), Some(content)) = (
+            value.attr("property").or(value.attr("name")),
+            value.attr("content"),
+        ) {
+            props.push((name, content));
+        }
+    }
+
+    let get = |name: &str| {
+        props
+            .iter()
+            .find(|(prop, _)| *prop == name)
+            .and_then(|(_, value)| non_empty(value))
+    };
+
+    Some(Article {
+        title: Some(get("og:title")?),
+        url: get("og:url"),
+        description: get("og:description").or_else(|| get("description")),
+
+        // `article:author` is usually a profile URL.
+        author: get("article:author")
+            .filter(|author| !author.starts_with("http"))
+            .or_else(|| get("author")),
+
+        published: get("article:published_time").and_then(|s| parse_date(&s, tz)),
+        image: get("og:image"),
+        keywords: props
+            .iter()
+            .filter(|(prop, _)| *prop == "article:tag")
+            .filter_map(|(_, value)| non_empty(value))
+            .collect(),
+    })
+}
+
+/// Finds the articles described by the page's metadata. Dates without a UTC offset are assumed to
+/// be in `tz` (UTC if unset).
+///
+/// JSON-LD and microdata items are combined (the duplicates, by URL, are removed); the OpenGraph
+/// tags are only used if neither describes an article.
+pub fn extract(html: &Html, tz: Option<Timezone>) -> Vec<Article> {
+    let mut articles = json_ld_articles(html, tz);
+    articles.extend(microdata_articles(html, tz));
+
+    let mut seen = HashSet::new();
+    articles.retain(|article| article.url.is_none() || seen.insert(article.url.clone()));
+
+    if articles.is_empty() {
+        articles.extend(open_graph_article(html, tz));
+    }
+
+    debug!("Found {} articles in the page metadata", articles.len());
+
+    articles
+}
@@ -0,0 +1,208 @@
+mod pipeline;
+
+use lol_html::{element, rewrite_str, RewriteStrSettings};
+use reqwest::Url;
+use scraper::Html;
+use sha2::{Digest, Sha256};
+use tracing::warn;
+
+use crate::config;
+use crate::extractor::Entry;
+
+/// Cleans up extracted entries before they're stored.
+#[derive(Debug, Clone)]
+pub struct PostProcessor {
+    rewrite_relative_urls: bool,
+    sanitize_html: bool,
+
+    /// Whether to remove tracking query parameters from entry URLs.
+    strip_tracking_params: bool,
+
+    /// Query parameters removed in addition to the built-in ones.
+    tracking_params: Vec<String>,
+
+    pipeline: Vec<config::PipelineStage>,
+}
+
+impl PostProcessor {
+    pub fn from_cfg(cfg: &config::Feed) -> Self {
+        Self {
+            rewrite_relative_urls: cfg.rewrite_relative_urls,
+            sanitize_html: cfg.sanitize_html,
+            strip_tracking_params: cfg.strip_tracking_params,
+            tracking_params: cfg.tracking_params.clone(),
+            pipeline: cfg.pipeline.clone(),
+        }
+    }
+
+    /// Processes the entries of a page retrieved from `base_url`.
+    pub fn process(&self, base_url: &Url, entries: &mut Vec<Entry>) {
+        if self.strip_tracking_params {
+            for entry in entries.iter_mut() {
+                self.strip_tracking_params(entry);
+            }
+        }
+
+        for stage in &self.pipeline {
+            pipeline::apply(stage, entries);
+        }
+
+        // runs after the pipeline so that the markup its stages produce is sanitized as well.
+        for entry in entries.iter_mut() {
+            self.process_html(&entry.id, "description", &mut entry.description, base_url);
+
+            if let Some(content) = &mut entry.content {
+                self.process_html(&entry.id, "content", content, base_url);
+            }
+        }
+    }
+
+    /// Whether entries matching the content hash of a stored entry must be dropped.
+    pub fn dedups_by_content(&self) -> bool {
+        self.pipeline
+            .iter()
+            .any(|stage| matches!(stage, config::PipelineStage::DedupContent))
+    }
+
+    /// Rewrites relative URLs and sanitizes an HTML field of an entry, as configured.
+    pub fn process_html(&self, entry_id: &str, field: &str, html: &mut String, base_url: &Url) {
+        if self.rewrite_relative_urls {
+            match resolve_relative_urls(html, base_url) {
+                Ok(rewritten) => *html = rewritten,
+                Err(e) => warn!(
+                    entry_id,
+                    "Could not rewrite relative URLs in the {field}: {e:#}",
+                ),
+            }
+        }
+
+        if self.sanitize_html {
+            *html = ammonia::clean(html);
+        }
+    }
+}
+
+impl Default for PostProcessor {
+    fn default() -> Self {
+        Self {
+            rewrite_relative_urls: true,
+            sanitize_html: true,
+            strip_tracking_params: false,
+            tracking_params: vec![],
+            pipeline: vec![],
+        }
+    }
+}
+
+impl PostProcessor {
+    fn is_tracking_param(&self, name: &str) -> bool {
+        name.starts_with("utm_")
+            || name == "fbclid"
+            || self.tracking_params.iter().any(|param| param == name)
+    }
+
+    fn strip_tracking_params(&self, entry: &mut Entry) {
+        if !entry
+            .url
+            .query_pairs()
+            .any(|(name, _)| self.is_tracking_param(&name))
+        {
+            return;
+        }
+
+        let query = entry
+            .url
+            .query_pairs()
+            .filter(|(name, _)| !self.is_tracking_param(name))
+            .map(|(name, value)| (name.into_owned(), value.into_owned()))
+            .collect::<Vec<_>>();
+
+        // entries identified by their URLs would otherwise still be duplicated.
+        let id_is_url = entry.id == entry.url.as_str();
+
+        if query.is_empty() {
+            entry.url.set_query(None);
+        } else {
+            entry.url.query_pairs_mut().clear().extend_pairs(query);
+        }
+
+        if id_is_url {
+            entry.id = entry.url.to_string();
+        }
+    }
+}
+
+/// Returns a hash of the entry's title and text (the content or, if missing, the description),
+/// ignoring markup, case, and whitespace differences.
+pub fn content_hash(entry: &Entry) -> String {
+    let normalize = |s: &str| {
+        s.split_whitespace()
+            .map(str::to_lowercase)
+            .collect::<Vec<_>>()
+            .join(" ")
+    };
+
+    let html = entry.content.as_deref().unwrap_or(&entry.description);
+    let text = Html::parse_fragment(html)
+        .root_element()
+        .text()
+        .collect::<String>();
+
+    Sha256::digest(format!("{}\n{}", normalize(&entry.title), normalize(&text)).as_bytes())
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+fn resolve_url(base_url: &Url, url: &str) -> Option<String> {
+    base_url.join(url.trim()).ok().map(String::from)
+}
+
+/// Resolves URLs in `srcset` attributes (comma-separated lists of `<url> <descriptor>`).
+fn resolve_srcset(base_url: &Url, srcset: &str) -> String {
+    srcset
+        .split(',')
+        .map(|candidate| {
+            let candidate = candidate.trim();
+            let (url, descriptor) = candidate.split_once(' ').unwrap_or((candidate, ""));
+
+            match resolve_url(base_url, url) {
+                Some(url) if descriptor.is_empty() => url,
+                Some(url) => format!("{url} {}", descriptor.trim()),
+                None => candidate.into(),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Makes the URLs in the HTML fragment absolute.
+pub fn resolve_relative_urls(html: &str, base_url: &Url) -> anyhow::Result<String> {
+    Ok(rewrite_str(
+        html,
+        RewriteStrSettings {
+            element_content_handlers: vec![
+                element!("[href], [src], [poster]", |el| {
+                    for attr in ["href", "src", "poster"] {
+                        if let Some(url) = el
+                            .get_attribute(attr)
+                            .and_then(|value| resolve_url(base_url, &value))
+                        {
+                            el.set_attribute(attr, &url)?;
+                        }
+                    }
+
+                    Ok(())
+                }),
+                element!("[srcset]", |el| {
+                    if let Some(srcset) = el.get_attribute("srcset") {
+                        el.set_attribute("srcset", &resolve_srcset(base_url, &srcset))?;
+                    }
+
+                    Ok(())
+                }),
+            ],
+            ..RewriteStrSettings::default()
+        },
+    )?)
+}
@@ -0,0 +1,130 @@
+use std::borrow::Cow;
+use std::collections::HashSet;
+
+use handlebars::Handlebars;
+use reqwest::Url;
+use tracing::warn;
+
+use crate::config::{EntryField, PipelineStage, TextMatcher};
+use crate::extractor::Entry;
+
+fn get_field(entry: &Entry, field: EntryField) -> &str {
+    match field {
+        EntryField::Id => &entry.id,
+        EntryField::Title => &entry.title,
+        EntryField::Description => &entry.description,
+        EntryField::Content => entry.content.as_deref().unwrap_or_default(),
+        EntryField::Url => entry.url.as_str(),
+        EntryField::Author => entry.author.as_deref().unwrap_or_default(),
+    }
+}
+
+fn set_field(entry: &mut Entry, field: EntryField, value: String) {
+    match field {
+        EntryField::Id => entry.id = value,
+        EntryField::Title => entry.title = value,
+        EntryField::Description => entry.description = value,
+        EntryField::Content => entry.content = Some(value).filter(|s| !s.is_empty()),
+        EntryField::Author => entry.author = Some(value).filter(|s| !s.is_empty()),
+
+        EntryField::Url => match Url::parse(&value) {
+            Ok(url) => entry.url = url,
+            Err(e) => warn!(
+                entry_id = %entry.id,
+                "The rewritten URL `{value}` is invalid, keeping the original one: {e:#}",
+            ),
+        },
+    }
+}
+
+/// Applies a pipeline stage to the entries of a page.
+pub fn apply(stage: &PipelineStage, entries: &mut Vec<Entry>) {
+    match stage {
+        PipelineStage::Filter {
+            include_title,
+            exclude_title,
+            include_url,
+            exclude_url,
+            include_author,
+            exclude_author,
+            include_category,
+            exclude_category,
+        } => entries.retain(|entry| {
+            let title = [entry.title.as_str()];
+            let url = [entry.url.as_str()];
+            let author = entry.author.as_deref();
+            let categories = entry
+                .categories
+                .iter()
+                .map(String::as_str)
+                .collect::<Vec<_>>();
+            let rules: [(_, _, &[&str]); 4] = [
+                (include_title, exclude_title, &title),
+                (include_url, exclude_url, &url),
+                (include_author, exclude_author, author.as_slice()),
+                (include_category, exclude_category, &categories),
+            ];
+
+            rules.iter().all(|&(include, exclude, values)| {
+                let matches = |matcher: &TextMatcher| values.iter().any(|s| matcher.is_match(s));
+
+                include.as_ref().is_none_or(matches) && !exclude.as_ref().is_some_and(matches)
+            })
+        }),
+
+        PipelineStage::Rewrite {
+            field,
+            pattern,
+            replacement,
+        } => {
+            for entry in entries {
+                let value = match pattern
+                    .as_regex()
+                    .replace_all(get_field(entry, *field), replacement.as_str())
+                {
+                    Cow::Owned(value) => value,
+                    Cow::Borrowed(_) => continue,
+                };
+
+                set_field(entry, *field, value);
+            }
+        }
+
+        PipelineStage::Template { field, template } => {
+            let mut handlebars = Handlebars::new();
+            handlebars.register_escape_fn(handlebars::no_escape);
+
+            for entry in entries {
+                match handlebars.render_template(template.as_str(), &*entry) {
+                    Ok(value) => set_field(entry, *field, value),
+
+                    Err(e) => warn!(
+                        entry_id = %entry.id,
+                        "Could not render the template `{}`: {e:#}",
+                        template.as_str(),
+                    ),
+                }
+            }
+        }
+
+        PipelineStage::Dedup { by } => {
+            let mut seen = HashSet::new();
+            entries.retain(|entry| seen.insert(get_field(entry, *by).to_owned()));
+        }
+
+        PipelineStage::DedupContent => {
+            let mut seen = HashSet::new();
+            entries.retain(|entry| seen.insert(super::content_hash(entry)));
+        }
+
+        PipelineStage::Sanitize => {
+            for entry in entries {
+                entry.description = ammonia::clean(&entry.description);
+
+                if let Some(content) = &mut entry.content {
+                    *content = ammonia::clean(content);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,239 @@
+//! A readability-style heuristic for finding the main content of an article page.
+//!
+//! Paragraphs are scored by their length and punctuation, and the scores are propagated to their
+//! ancestors; the highest-scoring element (adjusted by its link density) is the content. Scripts,
+//! navigation, comment sections, and the like are removed from it.
+
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+use ego_tree::NodeId;
+use regex_lite::Regex;
+use scraper::{ElementRef, Html};
+
+/// The minimum length of a paragraph for it to be scored.
+const MIN_PARAGRAPH_LEN: usize = 25;
+
+/// Elements that are never part of the content.
+const REMOVED_ELEMENTS: &[&str] = &[
+    "script", "style", "noscript", "template", "iframe", "object", "embed", "form", "button",
+    "input", "select", "textarea", "nav", "aside", "footer", "svg",
+];
+
+/// Containers that are removed from the content if they look like boilerplate.
+const CONDITIONALLY_REMOVED_ELEMENTS: &[&str] =
+    &["div", "section", "header", "ul", "ol", "table", "p"];
+
+fn unlikely_regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+
+    REGEX.get_or_init(|| {
+        Regex::new(concat!(
+            r"(?i)ad-break|agegate|banner|breadcrumb|combx|comment|community|cookie|disqus|",
+            r"extra|foot|header|menu|modal|newsletter|pager|pagination|popup|related|remark|rss|",
+            r"share|shoutbox|sidebar|skyscraper|social|sponsor|subscribe",
+        ))
+        .unwrap()
+    })
+}
+
+fn maybe_candidate_regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+
+    REGEX.get_or_init(|| Regex::new(r"(?i)and|article|body|column|content|main|shadow").unwrap())
+}
+
+fn positive_regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+
+    REGEX.get_or_init(|| {
+        Regex::new(r"(?i)article|body|content|entry|hentry|main|page|post|text|blog|story").unwrap()
+    })
+}
+
+fn negative_regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+
+    REGEX.get_or_init(|| {
+        Regex::new(concat!(
+            r"(?i)-ad-|hidden|banner|combx|comment|com-|contact|foot|masthead|media|meta|",
+            r"outbrain|promo|related|scroll|share|shoutbox|sidebar|skyscraper|sponsor|shopping|",
+            r"tags|tool|widget",
+        ))
+        .unwrap()
+    })
+}
+
+/// Returns the element's classes and id, separated by a space.
+fn class_and_id(element: ElementRef<'_>) -> String {
+    let element = element.value();
+
+    format!(
+        "{} {}",
+        element.attr("class").unwrap_or_default(),
+        element.id().unwrap_or_default(),
+    )
+}
+
+/// Whether the element's classes or id suggest it's not part of the content.
+fn is_unlikely(element: ElementRef<'_>) -> bool {
+    let name = element.value().name();
+    let class_and_id = class_and_id(element);
+
+    name != "body"
+        && name != "article"
+        && unlikely_regex().is_match(&class_and_id)
+        && !maybe_candidate_regex().is_match(&class_and_id)
+}
+
+fn class_weight(element: ElementRef<'_>) -> f64 {
+    let element = element.value();
+    let mut weight = 0.0;
+
+    for s in [element.attr("class"), element.id()].into_iter().flatten() {
+        if negative_regex().is_match(s) {
+            weight -= 25.0;
+        }
+
+        if positive_regex().is_match(s) {
+            weight += 25.0;
+        }
+    }
+
+    weight
+}
+
+/// The score of an element before its paragraphs are counted.
+fn initial_score(element: ElementRef<'_>) -> f64 {
+    let tag_score = match element.value().name() {
+        "div" | "article" => 5.0,
+        "pre" | "td" | "blockquote" => 3.0,
+        "address" | "ol" | "ul" | "dl" | "dd" | "dt" | "li" | "form" => -3.0,
+        "h1" | "h2" | "h3" | "h4" | "h5" | "h6" | "th" => -5.0,
+        _ => 0.0,
+    };
+
+    tag_score + class_weight(element)
+}
+
+fn text_len(element: ElementRef<'_>) -> usize {
+    element
+        .text()
+        .flat_map(str::split_whitespace)
+        .map(|word| word.chars().count() + 1)
+        .sum()
+}
+
+/// The proportion of the element's text inside links.
+fn link_density(element: ElementRef<'_>) -> f64 {
+    let len = text_len(element);
+
+    if len == 0 {
+        return 0.0;
+    }
+
+    let link_len: usize = element
+        .descendants()
+        .filter_map(ElementRef::wrap)
+        .filter(|element| element.value().name() == "a")
+        .map(text_len)
+        .sum();
+
+    link_len as f64 / len as f64
+}
+
+fn is_removed(element: ElementRef<'_>) -> bool {
+    let name = element.value().name();
+
+    if REMOVED_ELEMENTS.contains(&name) {
+        return true;
+    }
+
+    CONDITIONALLY_REMOVED_ELEMENTS.contains(&name)
+        && (is_unlikely(element) || class_weight(element) < 0.0 || link_density(element) > 0.5)
+}
+
+/// Finds the main content of an HTML document, returning its HTML.
+///
+/// Returns `None` if the document has no paragraphs long enough to tell.
+pub fn extract(html: &str) -> Option<String> {
+    let mut html = Html::parse_document(html);
+    let mut scores = HashMap::<NodeId, f64>::new();
+
+    // the candidates in document order so that ties are resolved deterministically.
+    let mut candidates = vec![];
+
+    for element in html
+        .root_element()
+        .descendants()
+        .filter_map(ElementRef::wrap)
+    {
+        if !matches!(element.value().name(), "p" | "pre" | "td" | "blockquote") {
+            continue;
+        }
+
+        if element
+            .ancestors()
+            .filter_map(ElementRef::wrap)
+            .any(|ancestor| is_unlikely(ancestor) || is_removed(ancestor))
+        {
+            continue;
+        }
+
+        let text = element.text().collect::<String>();
+        let len = text.trim().chars().count();
+
+        if len < MIN_PARAGRAPH_LEN {
+            continue;
+        }
+
+        let score = 1.0 + text.matches(',').count() as f64 + (len / 100).min(3) as f64;
+
+        // the parent gets the full score, the grandparent half, and so on.
+        for (level, ancestor) in element
+            .ancestors()
+            .filter_map(ElementRef::wrap)
+            .take(3)
+            .enumerate()
+        {
+            let divider = match level {
+                0 => 1.0,
+                1 => 2.0,
+                _ => level as f64 * 3.0,
+            };
+
+            *scores.entry(ancestor.id()).or_insert_with(|| {
+                candidates.push(ancestor.id());
+
+                initial_score(ancestor)
+            }) += score / divider;
+        }
+    }
+
+    let top = candidates
+        .into_iter()
+        .filter_map(|id| ElementRef::wrap(html.tree.get(id)?))
+        .map(|element| {
+            (
+                element.id(),
+                scores[&element.id()] * (1.0 - link_density(element)),
+            )
+        })
+        .reduce(|top, candidate| if candidate.1 > top.1 { candidate } else { top })?
+        .0;
+
+    let removed = ElementRef::wrap(html.tree.get(top)?)?
+        .descendants()
+        .filter_map(ElementRef::wrap)
+        .filter(|&element| is_removed(element))
+        .map(|element| element.id())
+        .collect::<Vec<_>>();
+
+    for id in removed {
+        if let Some(mut node) = html.tree.get_mut(id) {
+            node.detach();
+        }
+    }
+
+    Some(ElementRef::wrap(html.tree.get(top)?)?.inner_html()).filter(|s| !s.trim().is_empty())
+}
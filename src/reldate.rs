@@ -0,0 +1,133 @@
+//! Parsing of human-relative timestamps ("5 min ago", "yesterday", "vor 2 Tagen").
+
+use std::sync::OnceLock;
+
+use regex_lite::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+use time::{Duration, OffsetDateTime};
+
+/// A language relative timestamps are written in.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum Locale {
+    En,
+    De,
+}
+
+impl Locale {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "en" => Some(Self::En),
+            "de" => Some(Self::De),
+            _ => None,
+        }
+    }
+
+    /// Words denoting a point in time relative to now, as offsets in days.
+    fn keywords(self) -> &'static [(&'static str, i64)] {
+        match self {
+            Self::En => &[
+                ("now", 0),
+                ("just now", 0),
+                ("today", 0),
+                ("yesterday", -1),
+                ("day before yesterday", -2),
+            ],
+
+            Self::De => &[
+                ("jetzt", 0),
+                ("gerade eben", 0),
+                ("soeben", 0),
+                ("heute", 0),
+                ("gestern", -1),
+                ("vorgestern", -2),
+            ],
+        }
+    }
+
+    /// A regex matching `<count> <unit>` in the past, with the groups `count` and `unit`.
+    fn regex(self) -> &'static Regex {
+        static EN: OnceLock<Regex> = OnceLock::new();
+        static DE: OnceLock<Regex> = OnceLock::new();
+
+        let (cell, pattern) = match self {
+            Self::En => (&EN, r"^(?<count>\d+|an?|one)\s*(?<unit>[a-z]+)\.?\s+ago$"),
+
+            Self::De => (
+                &DE,
+                r"^vor\s+(?<count>\d+|einer|einem|ein|eine)\s*(?<unit>[a-z]+)\.?$",
+            ),
+        };
+
+        cell.get_or_init(|| {
+            RegexBuilder::new(pattern)
+                .case_insensitive(true)
+                .build()
+                .unwrap()
+        })
+    }
+
+    fn unit(self, unit: &str) -> Option<Duration> {
+        let unit = unit.to_lowercase();
+
+        Some(match self {
+            Self::En => match &*unit {
+                "s" | "sec" | "secs" | "second" | "seconds" => Duration::SECOND,
+                "m" | "min" | "mins" | "minute" | "minutes" => Duration::MINUTE,
+                "h" | "hr" | "hrs" | "hour" | "hours" => Duration::HOUR,
+                "d" | "day" | "days" => Duration::DAY,
+                "w" | "wk" | "wks" | "week" | "weeks" => Duration::WEEK,
+                "mo" | "mon" | "month" | "months" => Duration::days(30),
+                "y" | "yr" | "yrs" | "year" | "years" => Duration::days(365),
+                _ => return None,
+            },
+
+            Self::De => match &*unit {
+                "s" | "sek" | "sekunde" | "sekunden" => Duration::SECOND,
+                "min" | "minute" | "minuten" => Duration::MINUTE,
+                "std" | "stunde" | "stunden" => Duration::HOUR,
+                "tag" | "tage" | "tagen" => Duration::DAY,
+                "woche" | "wochen" => Duration::WEEK,
+                "monat" | "monate" | "monaten" => Duration::days(30),
+                "jahr" | "jahre" | "jahren" => Duration::days(365),
+                _ => return None,
+            },
+        })
+    }
+}
+
+/// Converts a relative timestamp to an absolute datetime relative to `now`.
+///
+/// Returns `None` if the string is not recognized in any of the locales. Months and years are
+/// approximated as 30 and 365 days.
+pub fn parse(s: &str, now: OffsetDateTime, locales: &[Locale]) -> Option<OffsetDateTime> {
+    let s = s.split_whitespace().collect::<Vec<_>>().join(" ");
+
+    for &locale in locales {
+        if let Some(&(_, days)) = locale
+            .keywords()
+            .iter()
+            .find(|(keyword, _)| keyword.eq_ignore_ascii_case(&s))
+        {
+            return now.checked_add(Duration::days(days));
+        }
+
+        let Some(captures) = locale.regex().captures(&s) else {
+            continue;
+        };
+
+        // the count is either a number or an indefinite article.
+        let count = match &captures["count"] {
+            count if count.starts_with(|c: char| c.is_ascii_digit()) => {
+                count.parse::<i32>().ok()?
+            }
+            _ => 1,
+        };
+
+        if let Some(unit) = locale.unit(&captures["unit"]) {
+            return now.checked_sub(unit.checked_mul(count)?);
+        }
+    }
+
+    None
+}
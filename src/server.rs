@@ -1,18 +1,39 @@
+mod access_log;
+mod api;
+mod atom;
+mod auth;
+mod channel;
+mod conditional;
+mod jsonfeed;
+mod ratelimit;
 mod responses;
 mod routes;
+mod rssfeed;
 
 use std::future::Future;
+use std::net::SocketAddr;
 
-use anyhow::{anyhow, Context, Result};
-use axum::Router;
+use anyhow::{anyhow, bail, Context, Result};
+use axum::http::{header, HeaderValue, Method};
+use axum::{middleware, Router};
+use axum_server::tls_rustls::RustlsConfig;
 use reqwest::StatusCode;
 use tokio::net::TcpListener;
+use tokio::task::JoinSet;
 use tokio_util::sync::CancellationToken;
 use tower::ServiceBuilder;
+use tower_http::cors::{AllowOrigin, CorsLayer};
 use tower_http::trace::{DefaultMakeSpan, DefaultOnRequest, TraceLayer};
 use tracing::{error, info, warn, Level};
 
+use crate::config::CorsConfig;
 use crate::state::State;
+use crate::systemd;
+
+use self::access_log::AccessLog;
+use self::ratelimit::RateLimiter;
+
+pub use self::rssfeed::BUILTIN_NAMESPACES;
 
 async fn convert_errors<F, R>(fut: F) -> axum::response::Result<R>
 where
@@ -29,29 +50,183 @@ where
     }
 }
 
+fn make_cors_layer(cfg: &CorsConfig) -> Result<Option<CorsLayer>> {
+    if cfg.allowed_origins.is_empty() {
+        return Ok(None);
+    }
+
+    let allow_origin = if cfg.allowed_origins.iter().any(|origin| origin == "*") {
+        AllowOrigin::any()
+    } else {
+        AllowOrigin::list(
+            cfg.allowed_origins
+                .iter()
+                .map(|origin| {
+                    HeaderValue::from_str(origin)
+                        .with_context(|| anyhow!("the CORS origin `{origin}` is invalid"))
+                })
+                .collect::<Result<Vec<_>>>()?,
+        )
+    };
+
+    let allow_methods = cfg
+        .allowed_methods
+        .iter()
+        .map(|method| {
+            Method::from_bytes(method.as_bytes())
+                .with_context(|| anyhow!("the CORS method `{method}` is invalid"))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(Some(
+        CorsLayer::new()
+            .allow_origin(allow_origin)
+            .allow_methods(allow_methods)
+            .allow_headers([header::AUTHORIZATION, header::CONTENT_TYPE]),
+    ))
+}
+
 pub struct Server {
-    socket: TcpListener,
+    sockets: Vec<TcpListener>,
     app: Router,
+    tls: Option<RustlsConfig>,
 }
 
 impl Server {
     pub async fn new(state: State) -> Result<Self> {
         use axum::routing::{get, post};
 
-        let bind_addr = &state.cfg.bind_addr;
-        let socket = TcpListener::bind(bind_addr)
-            .await
-            .with_context(|| anyhow!("could not bind to `{bind_addr}`"))?;
+        let activated =
+            systemd::take_listeners().context("could not use the sockets passed by systemd")?;
+        let mut sockets = Vec::new();
 
-        match socket.local_addr() {
-            Ok(addr) => info!("Created a socket for the HTTP server bound to {addr}"),
-            Err(e) => warn!("Created a socket for the HTTP server but could not retrieve its local address: {e}"),
+        if !activated.is_empty() {
+            info!(
+                "Using {} sockets passed by systemd; ignoring `bind-addr`",
+                activated.len()
+            );
+
+            for socket in activated {
+                sockets.push(
+                    TcpListener::from_std(socket)
+                        .context("could not use the socket passed by systemd")?,
+                );
+            }
+        } else {
+            for bind_addr in state.cfg.bind_addr.as_slice() {
+                sockets.push(
+                    TcpListener::bind(bind_addr)
+                        .await
+                        .with_context(|| anyhow!("could not bind to `{bind_addr}`"))?,
+                );
+            }
         }
 
-        let app = Router::new()
+        for socket in &sockets {
+            match socket.local_addr() {
+                Ok(addr) => info!("Created a socket for the HTTP server bound to {addr}"),
+                Err(e) => warn!("Created a socket for the HTTP server but could not retrieve its local address: {e}"),
+            }
+        }
+
+        let tls = match (&state.cfg.tls_cert, &state.cfg.tls_key) {
+            (Some(cert), Some(key)) => {
+                let tls = RustlsConfig::from_pem_file(cert, key)
+                    .await
+                    .with_context(|| {
+                        anyhow!(
+                            "could not load the TLS certificate `{}` and key `{}`",
+                            cert.display(),
+                            key.display(),
+                        )
+                    })?;
+                info!("Serving HTTPS with the certificate `{}`", cert.display());
+
+                Some(tls)
+            }
+
+            (None, None) => None,
+
+            _ => bail!("both `tls-cert` and `tls-key` must be set to enable TLS"),
+        };
+
+        if !state.cfg.auth.is_enabled() {
+            warn!("Authentication is not configured: anyone can trigger feed updates");
+        }
+
+        let require_auth = || middleware::from_fn_with_state(state.clone(), auth::require_auth);
+
+        let rate_limit = |limiter| middleware::from_fn_with_state(limiter, ratelimit::limit);
+
+        let admin = Router::new()
+            .route("/admin", get(routes::admin))
+            .route("/feeds/:name/update", post(routes::update_feed))
+            .route(
+                "/api/config/feeds/:name",
+                get(api::get_feed_config)
+                    .put(api::save_feed_config)
+                    .delete(api::delete_saved_feed_config),
+            )
+            .route("/api/config/validate", post(api::validate_feed_config))
+            .route("/api/preview", post(api::preview))
+            .route_layer(require_auth())
+            .route_layer(middleware::from_fn_with_state(
+                state.clone(),
+                auth::check_origin,
+            ));
+        let admin = match state.cfg.rate_limit.admin_requests_per_minute {
+            Some(requests) if requests > 0 => {
+                admin.route_layer(rate_limit(RateLimiter::per_minute(requests)))
+            }
+            _ => admin,
+        };
+
+        let feeds = Router::new()
             .route("/", get(routes::index))
             .route("/feeds/:name", get(routes::get_feed))
-            .route("/feeds/:name/update", post(routes::update_feed))
+            .route("/aggregates/:name", get(routes::get_aggregate))
+            .route("/opml", get(routes::opml))
+            .route("/img/:feed/:hash", get(routes::get_image))
+            .route("/api/feeds", get(api::list_feeds))
+            .route("/api/feeds/:name/entries", get(api::get_feed_entries))
+            .route("/api/feeds/:name/status", get(api::get_feed_status));
+        let feeds = if state.cfg.auth.protect_feeds {
+            feeds.route_layer(require_auth())
+        } else {
+            feeds
+        };
+
+        let app = Router::new()
+            .route("/metrics", get(routes::metrics))
+            .route("/healthz", get(routes::health))
+            .route("/feed.xsl", get(routes::feed_stylesheet))
+            .merge(feeds)
+            .merge(admin);
+        let app = match state.cfg.rate_limit.requests_per_minute {
+            Some(requests) if requests > 0 => {
+                app.route_layer(rate_limit(RateLimiter::per_minute(requests)))
+            }
+            _ => app,
+        };
+        let app = match &state.cfg.access_log {
+            Some(cfg) => {
+                let format = cfg
+                    .format
+                    .clone()
+                    .unwrap_or_else(|| access_log::DEFAULT_FORMAT.into());
+                let access_log = AccessLog::open(&cfg.path, format).await?;
+                info!("Writing the access log to `{}`", cfg.path.display());
+
+                app.layer(middleware::from_fn_with_state(access_log, access_log::log))
+            }
+
+            None => app,
+        };
+        let app = match make_cors_layer(&state.cfg.cors)? {
+            Some(cors) => app.layer(cors),
+            None => app,
+        };
+        let app = app
             .layer(
                 ServiceBuilder::new().layer(
                     TraceLayer::new_for_http()
@@ -61,13 +236,64 @@ impl Server {
             )
             .with_state(state);
 
-        Ok(Self { socket, app })
+        Ok(Self { sockets, app, tls })
     }
 
+    /// Serves requests on all sockets until cancelled or until any of them fails.
     pub async fn serve(self, cancel: CancellationToken) -> Result<()> {
-        axum::serve(self.socket, self.app)
-            .with_graceful_shutdown(cancel.cancelled_owned())
-            .await
-            .context("the HTTP server encountered a failure")
+        let mut tasks = JoinSet::new();
+
+        for socket in self.sockets {
+            tasks.spawn(serve_socket(
+                socket,
+                self.app.clone(),
+                self.tls.clone(),
+                cancel.clone(),
+            ));
+        }
+
+        while let Some(result) = tasks.join_next().await {
+            result.context("the HTTP server task panicked")??;
+        }
+
+        Ok(())
     }
 }
+
+async fn serve_socket(
+    socket: TcpListener,
+    app: Router,
+    tls: Option<RustlsConfig>,
+    cancel: CancellationToken,
+) -> Result<()> {
+    let Some(tls) = tls else {
+        return axum::serve(
+            socket,
+            app.into_make_service_with_connect_info::<SocketAddr>(),
+        )
+        .with_graceful_shutdown(cancel.cancelled_owned())
+        .await
+        .context("the HTTP server encountered a failure");
+    };
+
+    let handle = axum_server::Handle::new();
+
+    tokio::spawn({
+        let handle = handle.clone();
+
+        async move {
+            cancel.cancelled().await;
+            handle.graceful_shutdown(None);
+        }
+    });
+
+    let socket = socket
+        .into_std()
+        .context("could not convert the server socket")?;
+
+    axum_server::from_tcp_rustls(socket, tls)
+        .handle(handle)
+        .serve(app.into_make_service_with_connect_info::<SocketAddr>())
+        .await
+        .context("the HTTP server encountered a failure")
+}
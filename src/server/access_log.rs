@@ -0,0 +1,195 @@
+use std::io;
+use std::net::SocketAddr;
+use std::path::Path;
+use std::sync::Arc;
+
+use anyhow::{anyhow, Context, Result};
+use axum::body::HttpBody as _;
+use axum::extract::{ConnectInfo, Request, State};
+use axum::http::header::{self, HeaderName};
+use axum::http::HeaderMap;
+use axum::middleware::Next;
+use axum::response::Response;
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
+use tokio::fs::OpenOptions;
+use tokio::io::{AsyncWriteExt, BufWriter};
+use tokio::sync::mpsc;
+use tokio::time::Instant;
+use tracing::error;
+
+/// The format used if none is configured: similar to the Combined Log Format.
+pub const DEFAULT_FORMAT: &str = r#"{remote_addr} - [{time}] "{method} {uri} {version}" {status} {bytes} "{referer}" "{user_agent}" {duration_ms}ms"#;
+
+/// Writes a line per request to a file.
+pub struct AccessLog {
+    format: String,
+    tx: mpsc::UnboundedSender<String>,
+}
+
+impl AccessLog {
+    /// Opens the log file for appending and spawns a task that writes to it.
+    pub async fn open(path: &Path, format: String) -> Result<Arc<Self>> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .await
+            .with_context(|| anyhow!("could not open the access log `{}`", path.display()))?;
+        let (tx, mut rx) = mpsc::unbounded_channel::<String>();
+        let path = path.to_owned();
+
+        tokio::spawn(async move {
+            let mut writer = BufWriter::new(file);
+
+            while let Some(line) = rx.recv().await {
+                let result: io::Result<()> = async {
+                    writer.write_all(line.as_bytes()).await?;
+
+                    // write out whatever else is queued before flushing.
+                    while let Ok(line) = rx.try_recv() {
+                        writer.write_all(line.as_bytes()).await?;
+                    }
+
+                    writer.flush().await
+                }
+                .await;
+
+                if let Err(e) = result {
+                    error!(
+                        "Could not write to the access log `{}`: {e:#}",
+                        path.display()
+                    );
+                }
+            }
+        });
+
+        Ok(Arc::new(Self { format, tx }))
+    }
+
+    fn format_line(&self, fields: &Fields<'_>) -> String {
+        let mut line = String::with_capacity(self.format.len() * 2);
+        let mut rest = self.format.as_str();
+
+        while let Some(start) = rest.find('{') {
+            line.push_str(&rest[..start]);
+            rest = &rest[start..];
+
+            let Some(end) = rest.find('}') else {
+                break;
+            };
+
+            match fields.get(&rest[1..end]) {
+                Some(value) => line.push_str(&value),
+                None => line.push_str(&rest[..=end]),
+            }
+
+            rest = &rest[end + 1..];
+        }
+
+        line.push_str(rest);
+        line.push('\n');
+
+        line
+    }
+}
+
+struct Fields<'a> {
+    remote_addr: SocketAddr,
+    time: OffsetDateTime,
+    method: &'a str,
+    uri: &'a str,
+    version: &'a str,
+    status: u16,
+    bytes: Option<u64>,
+    referer: Option<&'a str>,
+    user_agent: Option<&'a str>,
+    duration_ms: u128,
+}
+
+impl Fields<'_> {
+    fn get(&self, name: &str) -> Option<String> {
+        let or_dash = |value: Option<&str>| value.unwrap_or("-").to_owned();
+
+        Some(match name {
+            "remote_addr" => self.remote_addr.ip().to_string(),
+            "time" => self.time.format(&Rfc3339).unwrap_or_default(),
+            "method" => self.method.to_owned(),
+            "uri" => self.uri.to_owned(),
+            "version" => self.version.to_owned(),
+            "status" => self.status.to_string(),
+            "bytes" => self.bytes.map_or("-".into(), |bytes| bytes.to_string()),
+            "referer" => or_dash(self.referer),
+            "user_agent" => or_dash(self.user_agent),
+            "duration_ms" => self.duration_ms.to_string(),
+            _ => return None,
+        })
+    }
+}
+
+fn header_value(headers: &HeaderMap, name: HeaderName) -> Option<String> {
+    headers
+        .get(name)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_owned)
+}
+
+/// Replaces the values of `token` query parameters (the access tokens of private feeds) in a
+/// request URI or URL so that they don't end up in the log.
+fn redact_token(uri: &str) -> String {
+    let Some((path, query)) = uri.split_once('?') else {
+        return uri.to_owned();
+    };
+
+    let query = query
+        .split('&')
+        .map(|pair| {
+            let name = pair.split_once('=').map_or(pair, |(name, _)| name);
+
+            if urlencoding::decode(name).is_ok_and(|name| name == "token") {
+                "token=<redacted>"
+            } else {
+                pair
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("&");
+
+    format!("{path}?{query}")
+}
+
+pub async fn log(
+    State(access_log): State<Arc<AccessLog>>,
+    ConnectInfo(remote_addr): ConnectInfo<SocketAddr>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let started = Instant::now();
+    let time = OffsetDateTime::now_utc();
+    let method = request.method().clone();
+    let uri = request.uri().clone();
+    let version = request.version();
+    let referer = header_value(request.headers(), header::REFERER).map(|url| redact_token(&url));
+    let user_agent = header_value(request.headers(), header::USER_AGENT);
+
+    let response = next.run(request).await;
+
+    let bytes = response.body().size_hint().exact();
+    let line = access_log.format_line(&Fields {
+        remote_addr,
+        time,
+        method: method.as_str(),
+        uri: &redact_token(&uri.to_string()),
+        version: &format!("{version:?}"),
+        status: response.status().as_u16(),
+        bytes,
+        referer: referer.as_deref(),
+        user_agent: user_agent.as_deref(),
+        duration_ms: started.elapsed().as_millis(),
+    });
+
+    // the writer task only exits if the runtime is shutting down.
+    let _ = access_log.tx.send(line);
+
+    response
+}
@@ -0,0 +1,526 @@
+use std::collections::HashMap;
+use std::mem;
+use std::sync::atomic::Ordering;
+
+use anyhow::{anyhow, bail, Context as _};
+use axum::extract::{Path, Query, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response, Result};
+use axum::Json;
+use reqwest::Url;
+use reqwest_middleware::ClientWithMiddleware;
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+
+use crate::config;
+use crate::extractor::{
+    ChannelInfo, Context as ExtractorContext, Entry, Extraction, Extractor,
+    Response as PageResponse,
+};
+use crate::fetch::{self, fetch_page};
+use crate::postprocess::PostProcessor;
+use crate::server::convert_errors;
+use crate::state::{check_feed, make_extractor, FetchOutcome, State as AppState};
+use crate::validate;
+
+use super::auth::has_feed_access;
+
+const DEFAULT_ENTRY_LIMIT: usize = 20;
+
+#[derive(Serialize, Debug, Clone)]
+pub struct FeedSummary {
+    name: String,
+    group: Option<String>,
+    enabled: bool,
+    request_url: String,
+
+    #[serde(with = "time::serde::rfc3339::option")]
+    last_updated: Option<OffsetDateTime>,
+
+    entry_count: usize,
+}
+
+pub async fn list_feeds(State(state): State<AppState>) -> Result<Json<Vec<FeedSummary>>> {
+    convert_errors(async move {
+        let mut tx = state.storage.begin().await?;
+        let stored_feeds = tx.get_feeds().await?;
+        tx.commit().await?;
+
+        let stored_feeds = stored_feeds
+            .into_iter()
+            .map(|mut feed| (mem::take(&mut feed.name), feed))
+            .collect::<HashMap<_, _>>();
+
+        let mut feeds = state
+            .feeds
+            .iter()
+            .filter(|(_, feed)| feed.token.is_none())
+            .map(|(name, feed)| {
+                let feed_info = stored_feeds.get(name);
+
+                FeedSummary {
+                    name: name.clone(),
+                    group: feed.group.clone(),
+                    enabled: feed.enabled,
+                    request_url: feed.request_url.to_string(),
+                    last_updated: feed_info.map(|feed_info| feed_info.last_updated),
+                    entry_count: feed_info.map_or(0, |feed_info| feed_info.entry_count),
+                }
+            })
+            .collect::<Vec<_>>();
+        feeds.sort_unstable_by(|lhs, rhs| lhs.name.cmp(&rhs.name));
+
+        Ok(Json(feeds))
+    })
+    .await
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct FeedStatus {
+    task_alive: bool,
+
+    #[serde(with = "time::serde::rfc3339::option")]
+    last_fetched: Option<OffsetDateTime>,
+
+    last_fetch: Option<FetchOutcome>,
+
+    #[serde(with = "time::serde::rfc3339::option")]
+    last_success: Option<OffsetDateTime>,
+
+    consecutive_failures: u32,
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct StatusParams {
+    /// The access token of a private feed.
+    token: Option<String>,
+}
+
+/// Reports the outcome of the feed's last update.
+pub async fn get_feed_status(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+    Query(params): Query<StatusParams>,
+) -> Result<Json<FeedStatus>> {
+    let feed = state.feeds.get(&name).ok_or(StatusCode::NOT_FOUND)?;
+
+    if !has_feed_access(feed, params.token.as_deref()) {
+        return Err(StatusCode::FORBIDDEN.into());
+    }
+    let status = feed.status.lock().unwrap().clone();
+    let (last_fetched, last_fetch) = status.last_fetch.unzip();
+
+    Ok(Json(FeedStatus {
+        task_alive: feed.task_alive.load(Ordering::Relaxed),
+        last_fetched,
+        last_fetch,
+        last_success: status.last_success,
+        consecutive_failures: status.consecutive_failures,
+    }))
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct EntriesParams {
+    limit: Option<usize>,
+
+    #[serde(default)]
+    offset: usize,
+
+    /// The access token of a private feed.
+    token: Option<String>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct EntriesPage {
+    limit: usize,
+    offset: usize,
+    entries: Vec<Entry>,
+}
+
+/// Returns the stored entries of a feed, newest first.
+pub async fn get_feed_entries(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+    Query(params): Query<EntriesParams>,
+) -> Result<Json<EntriesPage>> {
+    let feed = state.feeds.get(&name).ok_or(StatusCode::NOT_FOUND)?;
+
+    if !has_feed_access(feed, params.token.as_deref()) {
+        return Err(StatusCode::FORBIDDEN.into());
+    }
+
+    // a page holds no more entries than the feed itself.
+    let limit = params
+        .limit
+        .unwrap_or(DEFAULT_ENTRY_LIMIT)
+        .min(feed.max_entries);
+    let offset = params.offset;
+
+    convert_errors(async move {
+        let mut tx = state.storage.begin().await?;
+        let entries = tx.get_feed_entries(&name, limit, offset, None).await?;
+        tx.commit().await?;
+
+        Ok(Json(EntriesPage {
+            limit,
+            offset,
+            entries,
+        }))
+    })
+    .await
+}
+
+/// Returns the definition of a feed, with its secrets redacted.
+pub async fn get_feed_config(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+) -> Result<Json<serde_json::Value>> {
+    let feed = state.cfg.feeds.get(&name).ok_or(StatusCode::NOT_FOUND)?;
+
+    convert_errors(async move { Ok(Json(feed.to_redacted_json()?)) }).await
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct ValidateParams {
+    /// Whether to fetch the source page and run the extractor on it.
+    #[serde(default)]
+    fetch: bool,
+
+    /// The name of the feed being defined. If it exists, the definition is taken as an edited
+    /// version of its own (see [`parse_feed_definition`]).
+    name: Option<String>,
+}
+
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct ValidationReport {
+    valid: bool,
+    errors: Vec<String>,
+
+    /// Problems with the extracted entries (see `validate::check`).
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    warnings: Vec<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    entries: Option<Vec<Entry>>,
+
+    /// The next page returned by the extractor (not fetched when validating).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    next_page: Option<Url>,
+
+    /// The feed metadata returned by the extractor.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    channel: Option<ChannelInfo>,
+}
+
+impl ValidationReport {
+    fn error(e: anyhow::Error) -> Self {
+        Self {
+            valid: false,
+            errors: vec![format!("{e:#}")],
+            warnings: vec![],
+            entries: None,
+            next_page: None,
+            channel: None,
+        }
+    }
+}
+
+/// Deserializes a submitted feed definition. If it's the definition of the existing feed `name`,
+/// the secrets redacted from it are restored, and the feed keeps the credentials and the cookie
+/// file of the config file (which submitted definitions cannot set).
+fn parse_feed_definition(
+    state: &AppState,
+    name: Option<&str>,
+    mut definition: serde_json::Value,
+) -> anyhow::Result<(config::Feed, serde_json::Value)> {
+    let current = name.and_then(|name| state.cfg.feeds.get(name));
+
+    if let Some(fields) = definition.as_object_mut() {
+        for field in ["credentials", "cookie-file"] {
+            match fields.remove(field) {
+                None | Some(serde_json::Value::Null) => {}
+                Some(value) if value == config::REDACTED => {}
+                Some(_) => bail!("`{field}` can only be set in the config file"),
+            }
+        }
+    }
+
+    let original = current
+        .map(serde_json::to_value)
+        .transpose()
+        .context("could not serialize the feed config")?;
+    config::restore_redacted(&mut definition, original.as_ref(), "")?;
+
+    let mut feed: config::Feed =
+        serde_json::from_value(definition.clone()).context("the feed definition is malformed")?;
+    feed.resolve_relative_paths(&state.cfg.config_dir);
+    feed.extractor.check_file_paths(&state.cfg.config_dir)?;
+
+    if let Some(current) = current {
+        feed.credentials = current.credentials.clone();
+        feed.cookie_file = current.cookie_file.clone();
+    }
+
+    Ok((feed, definition))
+}
+
+/// Rejects routes that run extractors when auth is disabled: anyone could otherwise run them
+/// (Lua scripts included) on the server.
+fn require_auth_enabled(state: &AppState) -> Result<(), StatusCode> {
+    if state.cfg.auth.is_enabled() {
+        Ok(())
+    } else {
+        Err(StatusCode::FORBIDDEN)
+    }
+}
+
+/// Checks a proposed feed definition without applying it.
+pub async fn validate_feed_config(
+    State(state): State<AppState>,
+    Query(params): Query<ValidateParams>,
+    Json(definition): Json<serde_json::Value>,
+) -> Result<impl IntoResponse> {
+    require_auth_enabled(&state)?;
+
+    let report = convert_errors(async move {
+        let feed = match parse_feed_definition(&state, params.name.as_deref(), definition) {
+            Ok((feed, _)) => feed,
+            Err(e) => return Ok(ValidationReport::error(e)),
+        };
+
+        if let Some(name) = &params.name {
+            if let Err(e) = check_feed(&state.cfg, name, &feed) {
+                return Ok(ValidationReport::error(e));
+            }
+        }
+
+        let extractor = match make_extractor(&feed.extractor, &state.cfg.lua) {
+            Ok(extractor) => extractor,
+            Err(e) => {
+                return Ok(ValidationReport::error(
+                    e.context("could not create the extractor"),
+                ))
+            }
+        };
+
+        if !params.fetch {
+            return Ok(ValidationReport {
+                valid: true,
+                ..Default::default()
+            });
+        }
+
+        let http_client = fetch::make_feed_http_client(&state.http_client, &state.cfg, &feed);
+        let http_client = match http_client {
+            Ok((http_client, _)) => http_client,
+            Err(e) => return Ok(ValidationReport::error(e)),
+        };
+
+        let (response, body) = match fetch_page(&http_client, &feed.request_url).await {
+            Ok(page) => page,
+            Err(e) => return Ok(ValidationReport::error(e)),
+        };
+
+        if let Err(e) = response.check_content_type(feed.content_kind()) {
+            return Ok(ValidationReport::error(e));
+        }
+
+        let postprocessor = PostProcessor::from_cfg(&feed);
+
+        run_extractor(
+            extractor,
+            postprocessor,
+            http_client,
+            feed.request_url,
+            Some(response),
+            feed.timezone,
+            body,
+        )
+        .await
+    })
+    .await?;
+
+    Ok(report.into_response())
+}
+
+/// Saves a feed definition (a new one or an edited one, possibly with redacted secrets), which
+/// replaces the config file's definition of the feed from the next start.
+pub async fn save_feed_config(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+    Json(definition): Json<serde_json::Value>,
+) -> Result<impl IntoResponse> {
+    require_auth_enabled(&state)?;
+
+    let report = convert_errors(async move {
+        if name.trim().is_empty() {
+            return Ok(ValidationReport::error(anyhow!("the feed name is empty")));
+        }
+
+        let (feed, definition) = match parse_feed_definition(&state, Some(&name), definition) {
+            Ok(parsed) => parsed,
+            Err(e) => return Ok(ValidationReport::error(e)),
+        };
+
+        let checked = check_feed(&state.cfg, &name, &feed).and_then(|_| {
+            make_extractor(&feed.extractor, &state.cfg.lua)
+                .context("could not create the extractor")
+        });
+
+        if let Err(e) = checked {
+            return Ok(ValidationReport::error(e));
+        }
+
+        let definition =
+            serde_json::to_string(&definition).context("could not serialize the definition")?;
+        let mut tx = state.storage.begin().await?;
+        tx.set_saved_feed(&name, Some(&definition)).await?;
+        tx.commit().await?;
+
+        Ok(ValidationReport {
+            valid: true,
+            ..Default::default()
+        })
+    })
+    .await?;
+
+    Ok(report.into_response())
+}
+
+/// Removes the definition of a feed saved with [`save_feed_config`], so that the config file's
+/// is used again from the next start.
+pub async fn delete_saved_feed_config(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+) -> Result<StatusCode> {
+    convert_errors(async move {
+        let mut tx = state.storage.begin().await?;
+        tx.set_saved_feed(&name, None).await?;
+        tx.commit().await?;
+
+        Ok(StatusCode::NO_CONTENT)
+    })
+    .await
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct PreviewRequest {
+    extractor: config::ExtractorConfig,
+
+    /// The URL of the source page. Relative links are resolved against it.
+    url: Url,
+
+    /// The contents of the source page. If omitted, the page is fetched from `url`.
+    html: Option<String>,
+}
+
+/// Runs an extractor on a page without storing anything.
+pub async fn preview(
+    State(state): State<AppState>,
+    Json(request): Json<PreviewRequest>,
+) -> Result<impl IntoResponse> {
+    require_auth_enabled(&state)?;
+
+    let report = convert_errors(async move {
+        let mut extractor_cfg = request.extractor;
+        extractor_cfg.resolve_relative_paths(&state.cfg.config_dir);
+
+        if let Err(e) = extractor_cfg.check_file_paths(&state.cfg.config_dir) {
+            return Ok(ValidationReport::error(e));
+        }
+
+        let extractor = match make_extractor(&extractor_cfg, &state.cfg.lua) {
+            Ok(extractor) => extractor,
+            Err(e) => {
+                return Ok(ValidationReport::error(
+                    e.context("could not create the extractor"),
+                ))
+            }
+        };
+
+        let (response, body) = match request.html {
+            Some(html) => (None, html),
+
+            None => match fetch_page(&state.http_client, &request.url).await {
+                Ok((response, body)) => (Some(response), body),
+                Err(e) => return Ok(ValidationReport::error(e)),
+            },
+        };
+
+        if let Some(response) = &response {
+            if let Err(e) = response.check_content_type(extractor_cfg.content_kind()) {
+                return Ok(ValidationReport::error(e));
+            }
+        }
+
+        run_extractor(
+            extractor,
+            Default::default(),
+            state.http_client.clone(),
+            request.url,
+            response,
+            None,
+            body,
+        )
+        .await
+    })
+    .await?;
+
+    Ok(report.into_response())
+}
+
+async fn run_extractor(
+    mut extractor: Box<dyn Extractor + Send>,
+    postprocessor: PostProcessor,
+    http_client: ClientWithMiddleware,
+    request_url: Url,
+    response: Option<PageResponse>,
+    timezone: Option<config::Timezone>,
+    body: String,
+) -> anyhow::Result<ValidationReport> {
+    let extraction = tokio::task::spawn_blocking(move || -> anyhow::Result<Extraction> {
+        let ctx = ExtractorContext::new(&request_url)
+            .with_response(response.as_ref())
+            .with_timezone(timezone)
+            .with_http_client(&http_client, None);
+        let base_url = ctx.base_url();
+
+        let mut extraction = extractor
+            .extract(ctx, &body)
+            .context("could not extract feed entries")?;
+        postprocessor.process(base_url, &mut extraction.entries);
+
+        Ok(extraction)
+    })
+    .await
+    .context("running the extractor failed")?;
+
+    Ok(match extraction {
+        Ok(extraction) => ValidationReport {
+            valid: true,
+            errors: vec![],
+            warnings: validate::check(&extraction.entries)
+                .iter()
+                .map(ToString::to_string)
+                .collect(),
+            entries: Some(extraction.entries),
+            next_page: extraction.next_page,
+            channel: extraction.channel,
+        },
+
+        Err(e) => ValidationReport::error(e),
+    })
+}
+
+impl IntoResponse for ValidationReport {
+    fn into_response(self) -> Response {
+        let status = if self.valid {
+            StatusCode::OK
+        } else {
+            StatusCode::UNPROCESSABLE_ENTITY
+        };
+
+        (status, Json(self)).into_response()
+    }
+}
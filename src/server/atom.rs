@@ -0,0 +1,138 @@
+use atom_syndication::{
+    CategoryBuilder, ContentBuilder, EntryBuilder, FeedBuilder, FixedDateTime, GeneratorBuilder,
+    LinkBuilder, PersonBuilder, Text,
+};
+use axum::http::header;
+use axum::response::{IntoResponse, Response};
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
+use tracing::error;
+
+use super::channel::Channel;
+
+fn to_fixed_date_time(datetime: OffsetDateTime) -> Option<FixedDateTime> {
+    datetime
+        .format(&Rfc3339)
+        .ok()
+        .and_then(|s| FixedDateTime::parse_from_rfc3339(&s).ok())
+        .or_else(|| {
+            error!("could not convert the date ({datetime}) to the Atom format");
+
+            None
+        })
+}
+
+/// An Atom feed document.
+pub struct AtomFeed(atom_syndication::Feed);
+
+impl AtomFeed {
+    pub fn new(channel: Channel) -> Self {
+        let now = to_fixed_date_time(OffsetDateTime::now_utc()).unwrap_or_default();
+        let updated = channel
+            .items
+            .iter()
+            .filter_map(|item| item.entry.pub_date)
+            .max()
+            .and_then(to_fixed_date_time)
+            .unwrap_or(now);
+
+        let entries = channel
+            .items
+            .into_iter()
+            .map(|item| {
+                let entry = item.entry;
+                let pub_date = entry.pub_date.and_then(to_fixed_date_time);
+
+                EntryBuilder::default()
+                    .id(item.guid)
+                    .title(entry.title)
+                    .updated(pub_date.unwrap_or(now))
+                    .published(pub_date)
+                    .authors(
+                        entry
+                            .author
+                            .into_iter()
+                            .map(|author| PersonBuilder::default().name(author).build())
+                            .collect::<Vec<_>>(),
+                    )
+                    .categories(
+                        entry
+                            .categories
+                            .into_iter()
+                            .map(|term| CategoryBuilder::default().term(term).build())
+                            .collect::<Vec<_>>(),
+                    )
+                    .links(
+                        std::iter::once(
+                            LinkBuilder::default()
+                                .href(entry.url)
+                                .rel("alternate")
+                                .build(),
+                        )
+                        .chain(entry.enclosure.map(|enclosure| {
+                            LinkBuilder::default()
+                                .href(enclosure.url)
+                                .rel("enclosure")
+                                .mime_type(Some(enclosure.mime_type))
+                                .length(enclosure.length.map(|length| length.to_string()))
+                                .build()
+                        }))
+                        // as defined by RFC 4685 (Atom Threading Extensions).
+                        .chain(entry.comments.map(|comments| {
+                            LinkBuilder::default()
+                                .href(comments)
+                                .rel("replies")
+                                .mime_type(Some("text/html".into()))
+                                .build()
+                        }))
+                        .collect::<Vec<_>>(),
+                    )
+                    .summary(Some(Text::html(entry.description)))
+                    .content(entry.content.map(|content| {
+                        ContentBuilder::default()
+                            .value(Some(content))
+                            .content_type(Some("html".into()))
+                            .build()
+                    }))
+                    .build()
+            })
+            .collect::<Vec<_>>();
+
+        let feed = FeedBuilder::default()
+            .id(channel.id)
+            .title(channel.title)
+            .subtitle(channel.description.map(Text::plain))
+            .lang(channel.language)
+            .rights(channel.copyright.map(Text::plain))
+            .updated(updated)
+            .links(vec![
+                LinkBuilder::default()
+                    .href(channel.link)
+                    .rel("alternate")
+                    .build(),
+                LinkBuilder::default()
+                    .href(channel.self_url)
+                    .rel("self")
+                    .build(),
+            ])
+            .generator(Some(
+                GeneratorBuilder::default()
+                    .value("Feedgen")
+                    .version(Some(env!("CARGO_PKG_VERSION").into()))
+                    .build(),
+            ))
+            .entries(entries)
+            .build();
+
+        Self(feed)
+    }
+}
+
+impl IntoResponse for AtomFeed {
+    fn into_response(self) -> Response {
+        IntoResponse::into_response((
+            [(header::CONTENT_TYPE, "application/atom+xml")],
+            self.0.to_string(),
+        ))
+    }
+}
@@ -0,0 +1,147 @@
+use axum::extract::{Request, State};
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use base64::prelude::*;
+use reqwest::Url;
+
+use crate::config::AuthConfig;
+use crate::state::{Feed, State as AppState};
+
+fn constant_time_eq(lhs: &[u8], rhs: &[u8]) -> bool {
+    lhs.len() == rhs.len() && lhs.iter().zip(rhs).fold(0, |acc, (l, r)| acc | (l ^ r)) == 0
+}
+
+fn is_authorized(cfg: &AuthConfig, headers: &HeaderMap) -> bool {
+    if !cfg.is_enabled() {
+        return true;
+    }
+
+    let Some(authorization) = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+    else {
+        return false;
+    };
+
+    let (scheme, credentials) = authorization.split_once(' ').unwrap_or((authorization, ""));
+    let credentials = credentials.trim();
+
+    if scheme.eq_ignore_ascii_case("bearer") {
+        cfg.bearer_token
+            .as_ref()
+            .is_some_and(|token| constant_time_eq(token.as_bytes(), credentials.as_bytes()))
+    } else if scheme.eq_ignore_ascii_case("basic") {
+        let (Some(username), Some(password)) = (&cfg.username, &cfg.password) else {
+            return false;
+        };
+        let Ok(decoded) = BASE64_STANDARD.decode(credentials) else {
+            return false;
+        };
+        let expected = format!("{username}:{password}");
+
+        constant_time_eq(expected.as_bytes(), &decoded)
+    } else {
+        false
+    }
+}
+
+/// Checks the access token of a private feed. Public feeds are always accessible.
+pub fn has_feed_access(feed: &Feed, token: Option<&str>) -> bool {
+    match (&feed.token, token) {
+        (None, _) => true,
+        (Some(expected), Some(token)) => constant_time_eq(expected.as_bytes(), token.as_bytes()),
+        (Some(_), None) => false,
+    }
+}
+
+pub async fn require_auth(State(state): State<AppState>, request: Request, next: Next) -> Response {
+    if is_authorized(&state.cfg.auth, request.headers()) {
+        return next.run(request).await;
+    }
+
+    let challenge = if state.cfg.auth.username.is_some() {
+        r#"Basic realm="feedgen", charset="UTF-8""#
+    } else {
+        r#"Bearer realm="feedgen""#
+    };
+
+    IntoResponse::into_response((
+        StatusCode::UNAUTHORIZED,
+        [(header::WWW_AUTHENTICATE, challenge)],
+        "Authentication required",
+    ))
+}
+
+/// Whether the request was made by one of our own pages (or by a client other than a browser,
+/// which sends neither `Sec-Fetch-Site` nor `Origin`).
+fn is_same_origin(state: &AppState, headers: &HeaderMap) -> bool {
+    if let Some(site) = headers.get("sec-fetch-site") {
+        return matches!(site.to_str(), Ok("same-origin" | "none"));
+    }
+
+    let Some(origin) = headers.get(header::ORIGIN) else {
+        return true;
+    };
+    let Some(origin) = origin
+        .to_str()
+        .ok()
+        .and_then(|origin| Url::parse(origin).ok())
+    else {
+        return false;
+    };
+
+    if let Some(base_url) = &state.cfg.base_url {
+        if origin.origin() == base_url.origin() {
+            return true;
+        }
+    }
+
+    let Some(host) = headers
+        .get(header::HOST)
+        .and_then(|host| host.to_str().ok())
+    else {
+        return false;
+    };
+
+    match (origin.host_str(), origin.port()) {
+        (Some(origin_host), Some(port)) => host == format!("{origin_host}:{port}"),
+        (Some(origin_host), None) => host == origin_host,
+        (None, _) => false,
+    }
+}
+
+/// Whether the request was made by a page on an origin listed in `[cors] allowed-origins`.
+/// The `*` wildcard is not taken into account: it would let any site act with the admin's
+/// credentials.
+fn is_cors_allowed_origin(state: &AppState, headers: &HeaderMap) -> bool {
+    let Some(origin) = headers
+        .get(header::ORIGIN)
+        .and_then(|origin| origin.to_str().ok())
+    else {
+        return false;
+    };
+
+    state
+        .cfg
+        .cors
+        .allowed_origins
+        .iter()
+        .any(|allowed| allowed != "*" && allowed == origin)
+}
+
+/// Rejects state-changing requests sent from other sites, which the browser would make with the
+/// admin's credentials, unless the site is explicitly allowed by the CORS settings.
+pub async fn check_origin(State(state): State<AppState>, request: Request, next: Next) -> Response {
+    if request.method().is_safe()
+        || is_same_origin(&state, request.headers())
+        || is_cors_allowed_origin(&state, request.headers())
+    {
+        return next.run(request).await;
+    }
+
+    IntoResponse::into_response((
+        StatusCode::FORBIDDEN,
+        "Cross-origin requests are not allowed",
+    ))
+}
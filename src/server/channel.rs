@@ -0,0 +1,49 @@
+use std::collections::BTreeMap;
+
+use crate::extractor::Entry;
+
+/// A feed ready to be rendered in any of the supported formats.
+#[derive(Debug, Clone)]
+pub struct Channel {
+    pub id: String,
+    pub title: String,
+    pub description: Option<String>,
+    pub language: Option<String>,
+    pub copyright: Option<String>,
+    pub link: String,
+    pub self_url: String,
+
+    /// XML namespaces of extra entry elements, keyed by their prefixes.
+    pub namespaces: BTreeMap<String, String>,
+
+    /// An XSLT stylesheet that lets browsers render the feed as a web page.
+    pub stylesheet_url: Option<String>,
+
+    pub items: Vec<Item>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Item {
+    pub guid: String,
+
+    /// Whether `guid` is the URL of the entry.
+    pub guid_is_permalink: bool,
+
+    pub entry: Entry,
+}
+
+impl Item {
+    pub fn new(feed_name: &str, entry: Entry) -> Self {
+        let guid = match (&entry.guid, entry.guid_is_permalink) {
+            (Some(guid), _) => guid.clone(),
+            (None, true) => entry.url.to_string(),
+            (None, false) => format!("feedgen/{}/{}", feed_name, entry.id),
+        };
+
+        Self {
+            guid,
+            guid_is_permalink: entry.guid_is_permalink,
+            entry,
+        }
+    }
+}
@@ -0,0 +1,90 @@
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::time::SystemTime;
+
+use axum::http::{header, HeaderMap, HeaderValue, StatusCode};
+use axum::response::{IntoResponse, Response};
+use time::OffsetDateTime;
+
+/// Cache validators for a response.
+#[derive(Debug, Clone)]
+pub struct Validators {
+    etag: String,
+    last_modified: Option<SystemTime>,
+}
+
+impl Validators {
+    /// Creates validators for a representation that depends on `keys` and was last changed at
+    /// `last_modified`.
+    pub fn new<K: Hash>(keys: K, last_modified: Option<OffsetDateTime>) -> Self {
+        let mut hasher = DefaultHasher::new();
+        keys.hash(&mut hasher);
+        last_modified
+            .map(|last_modified| last_modified.unix_timestamp_nanos())
+            .hash(&mut hasher);
+
+        Self {
+            etag: format!("\"{:016x}\"", hasher.finish()),
+            last_modified: last_modified.map(SystemTime::from),
+        }
+    }
+
+    /// Checks the `If-None-Match` and `If-Modified-Since` request headers against the validators.
+    pub fn is_not_modified(&self, headers: &HeaderMap) -> bool {
+        let if_none_match = headers
+            .get_all(header::IF_NONE_MATCH)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .map(str::trim)
+            .collect::<Vec<_>>();
+
+        // If-Modified-Since must be ignored if If-None-Match is present.
+        if !if_none_match.is_empty() {
+            return if_none_match
+                .into_iter()
+                .any(|tag| tag == "*" || tag.trim_start_matches("W/") == self.etag);
+        }
+
+        let Some(last_modified) = self.last_modified else {
+            return false;
+        };
+        let Some(since) = headers
+            .get(header::IF_MODIFIED_SINCE)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| httpdate::parse_http_date(value).ok())
+        else {
+            return false;
+        };
+
+        // HTTP dates have a resolution of one second.
+        let secs = |t: SystemTime| {
+            t.duration_since(SystemTime::UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0)
+        };
+
+        secs(last_modified) <= secs(since)
+    }
+
+    /// Adds the validators to the response headers.
+    pub fn apply(&self, mut response: Response) -> Response {
+        let headers = response.headers_mut();
+
+        if let Ok(value) = HeaderValue::from_str(&self.etag) {
+            headers.insert(header::ETAG, value);
+        }
+
+        if let Some(last_modified) = self.last_modified {
+            if let Ok(value) = HeaderValue::from_str(&httpdate::fmt_http_date(last_modified)) {
+                headers.insert(header::LAST_MODIFIED, value);
+            }
+        }
+
+        response
+    }
+
+    pub fn not_modified(&self) -> Response {
+        self.apply(StatusCode::NOT_MODIFIED.into_response())
+    }
+}
@@ -0,0 +1,127 @@
+use axum::http::header;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde::Serialize;
+use time::OffsetDateTime;
+
+use super::channel::Channel;
+
+const VERSION: &str = "https://jsonfeed.org/version/1.1";
+
+/// A JSON Feed 1.1 document.
+///
+/// See https://www.jsonfeed.org/version/1.1/ for the specification.
+#[derive(Serialize, Debug, Clone)]
+pub struct JsonFeed {
+    version: &'static str,
+    title: String,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    description: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    language: Option<String>,
+
+    home_page_url: String,
+    feed_url: String,
+    items: Vec<Item>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+struct Item {
+    id: String,
+    url: String,
+    title: String,
+    content_html: String,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    summary: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    image: Option<String>,
+
+    #[serde(
+        with = "time::serde::rfc3339::option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    date_published: Option<OffsetDateTime>,
+
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    authors: Vec<Author>,
+
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tags: Vec<String>,
+
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    attachments: Vec<Attachment>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+struct Attachment {
+    url: String,
+    mime_type: String,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    size_in_bytes: Option<u64>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+struct Author {
+    name: String,
+}
+
+impl JsonFeed {
+    pub fn new(channel: Channel) -> Self {
+        let items = channel
+            .items
+            .into_iter()
+            .map(|item| {
+                let entry = item.entry;
+
+                Item {
+                    id: item.guid,
+                    url: entry.url.into(),
+                    title: entry.title,
+                    summary: entry.content.is_some().then(|| entry.description.clone()),
+                    content_html: entry.content.unwrap_or(entry.description),
+                    image: entry.image.map(Into::into),
+                    date_published: entry.pub_date,
+                    authors: entry
+                        .author
+                        .into_iter()
+                        .map(|name| Author { name })
+                        .collect(),
+                    tags: entry.categories,
+                    attachments: entry
+                        .enclosure
+                        .into_iter()
+                        .map(|enclosure| Attachment {
+                            url: enclosure.url.into(),
+                            mime_type: enclosure.mime_type,
+                            size_in_bytes: enclosure.length,
+                        })
+                        .collect(),
+                }
+            })
+            .collect();
+
+        Self {
+            version: VERSION,
+            title: channel.title,
+            description: channel.description,
+            language: channel.language,
+            home_page_url: channel.link,
+            feed_url: channel.self_url,
+            items,
+        }
+    }
+}
+
+impl IntoResponse for JsonFeed {
+    fn into_response(self) -> Response {
+        IntoResponse::into_response((
+            [(header::CONTENT_TYPE, "application/feed+json")],
+            Json(self),
+        ))
+    }
+}
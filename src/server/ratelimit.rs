@@ -0,0 +1,96 @@
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use axum::extract::{ConnectInfo, Request, State};
+use axum::http::{header, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use tracing::debug;
+
+/// The number of tracked clients above which idle ones are forgotten.
+const MAX_TRACKED_CLIENTS: usize = 4096;
+
+struct Bucket {
+    tokens: f64,
+    last_refill: Instant,
+}
+
+/// A per-IP token bucket rate limiter.
+pub struct RateLimiter {
+    /// The bucket size: the number of requests a client can make in a burst.
+    capacity: f64,
+
+    /// The number of tokens restored per second.
+    refill_rate: f64,
+
+    buckets: Mutex<HashMap<IpAddr, Bucket>>,
+}
+
+impl RateLimiter {
+    pub fn per_minute(requests: u32) -> Arc<Self> {
+        let capacity = requests.max(1) as f64;
+
+        Arc::new(Self {
+            capacity,
+            refill_rate: capacity / 60.0,
+            buckets: Default::default(),
+        })
+    }
+
+    /// Takes a token from the client's bucket, returning how long to wait if it's empty.
+    fn check(&self, ip: IpAddr) -> Result<(), Duration> {
+        let now = Instant::now();
+        let mut buckets = self.buckets.lock().unwrap();
+
+        if buckets.len() >= MAX_TRACKED_CLIENTS {
+            buckets.retain(|_, bucket| {
+                let elapsed = now.duration_since(bucket.last_refill).as_secs_f64();
+
+                bucket.tokens + elapsed * self.refill_rate < self.capacity
+            });
+        }
+
+        let bucket = buckets.entry(ip).or_insert(Bucket {
+            tokens: self.capacity,
+            last_refill: now,
+        });
+        let elapsed = now.duration_since(bucket.last_refill).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * self.refill_rate).min(self.capacity);
+        bucket.last_refill = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64(
+                (1.0 - bucket.tokens) / self.refill_rate,
+            ))
+        }
+    }
+}
+
+pub async fn limit(
+    State(limiter): State<Arc<RateLimiter>>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let retry_after = match limiter.check(addr.ip()) {
+        Ok(()) => return next.run(request).await,
+        Err(retry_after) => retry_after,
+    };
+
+    debug!(client = %addr.ip(), "Rate-limited a request to {}", request.uri());
+
+    IntoResponse::into_response((
+        StatusCode::TOO_MANY_REQUESTS,
+        [(
+            header::RETRY_AFTER,
+            retry_after.as_secs_f64().ceil().to_string(),
+        )],
+        "Too many requests",
+    ))
+}
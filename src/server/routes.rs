@@ -1,49 +1,84 @@
 use std::cmp::Reverse;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt::Write;
 use std::mem;
+use std::sync::atomic::Ordering;
+use std::time::Duration;
 
-use anyhow::{anyhow, Context};
-use axum::extract::{Path, State};
-use axum::http::{header, StatusCode};
-use axum::response::{Html, IntoResponse, Result};
-use rss::{ChannelBuilder, GuidBuilder, ItemBuilder};
-use serde::Serialize;
+use anyhow::{anyhow, bail, Context};
+use axum::extract::{Path, Query, State};
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::response::{Html, IntoResponse, Response, Result};
+use axum::Json;
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
 use time::format_description::well_known::Rfc2822;
 use time::format_description::BorrowedFormatItem;
 use time::macros::format_description;
 use time::OffsetDateTime;
-use tracing::error;
+use tracing::warn;
 
+use crate::imageproxy;
 use crate::server::convert_errors;
-use crate::state::State as AppState;
+use crate::state::{FetchOutcome, State as AppState};
 use crate::template::Template;
 
+use super::atom::AtomFeed;
+use super::auth::has_feed_access;
+use super::channel::{Channel, Item};
+use super::conditional::Validators;
+use super::jsonfeed::JsonFeed;
 use super::responses::FeedCannotBeUpdated;
+use super::rssfeed::RssFeed;
 
-const MAX_FEED_ENTRY_COUNT: usize = 100;
+/// The maximum size of an image served through the image proxy.
+const MAX_IMAGE_SIZE: usize = 16 * 1024 * 1024;
 
-pub async fn index(State(state): State<AppState>) -> Result<Html<String>> {
-    static DATE_FORMAT: &[BorrowedFormatItem<'_>] = format_description!(
-        "[year]-[month]-[day] \
-            [hour]:[minute]:[second].[subsecond digits:3] \
-            [offset_hour sign:mandatory]:[offset_minute]"
-    );
+const IMAGE_CACHE_CONTROL: &str = "public, max-age=604800";
+
+/// Keeps scripts in proxied images (SVGs can have them) from running on our origin if an image
+/// is opened directly.
+const IMAGE_CONTENT_SECURITY_POLICY: &str = "default-src 'none'; sandbox";
 
+static DATE_FORMAT: &[BorrowedFormatItem<'_>] = format_description!(
+    "[year]-[month]-[day] \
+        [hour]:[minute]:[second].[subsecond digits:3] \
+        [offset_hour sign:mandatory]:[offset_minute]"
+);
+
+fn format_date(date: OffsetDateTime) -> anyhow::Result<String> {
+    date.format(DATE_FORMAT)
+        .with_context(|| anyhow!("could not format the date {date}"))
+}
+
+pub async fn index(State(state): State<AppState>) -> Result<Html<String>> {
     #[derive(Serialize, Debug, Clone)]
     struct FeedDescription {
         name: String,
         last_updated: String,
+        last_result: String,
+        failed: bool,
+        next_update: String,
         entry_count: usize,
         rss_url: String,
         fetch_url: String,
     }
 
     #[derive(Serialize, Debug, Clone)]
-    struct Context {
+    struct Group {
+        name: Option<String>,
+        opml_url: String,
         feeds: Vec<FeedDescription>,
     }
 
+    #[derive(Serialize, Debug, Clone)]
+    struct Context {
+        groups: Vec<Group>,
+        opml_url: String,
+    }
+
     convert_errors(async move {
+        let prefix = path_prefix(&state);
         let mut tx = state.storage.begin().await?;
         let stored_feeds = tx.get_feeds().await?;
         tx.commit().await?;
@@ -53,17 +88,13 @@ pub async fn index(State(state): State<AppState>) -> Result<Html<String>> {
             .map(|mut feed| (mem::take(&mut feed.name), feed))
             .collect::<HashMap<_, _>>();
 
-        let mut feeds = Vec::with_capacity(state.feeds.len());
+        let mut groups = BTreeMap::<Option<&str>, Vec<_>>::new();
 
-        for (name, feed) in &*state.feeds {
+        for (name, feed) in state.feeds.iter().filter(|(_, feed)| feed.token.is_none()) {
             let feed_info = stored_feeds.get(name);
 
             let last_updated = if let Some(feed_info) = feed_info {
-                let last_updated = feed_info.last_updated;
-
-                last_updated
-                    .format(DATE_FORMAT)
-                    .with_context(|| anyhow!("could not format the date {last_updated}"))?
+                format_date(feed_info.last_updated)?
             } else {
                 "never".into()
             };
@@ -71,90 +102,599 @@ pub async fn index(State(state): State<AppState>) -> Result<Html<String>> {
             let entry_count = feed_info
                 .map(|feed_info| feed_info.entry_count)
                 .unwrap_or(0);
-            let rss_url = format!("/feeds/{}", urlencoding::encode(name));
+            let rss_url = format!("{prefix}/feeds/{}", urlencoding::encode(name));
+
+            let status = feed.status.lock().unwrap().clone();
+            let (last_result, failed) = match &status.last_fetch {
+                None => ("not fetched since startup".into(), false),
+
+                Some((_, outcome)) => match outcome {
+                    FetchOutcome::Success {
+                        new_entry_count, ..
+                    } => (format!("ok, {new_entry_count} new entries"), false),
+
+                    FetchOutcome::Unchanged => ("ok, the source page is unchanged".into(), false),
+
+                    FetchOutcome::Discarded { message, .. } => {
+                        (format!("entries discarded: {message}"), false)
+                    }
+
+                    _ => (
+                        format!("failed: {}", outcome.error_message().unwrap_or_default()),
+                        true,
+                    ),
+                },
+            };
+            let next_update = if !feed.enabled {
+                "disabled".into()
+            } else if let Some(next_fetch) = status.next_fetch {
+                format_date(next_fetch)?
+            } else if feed.task_alive.load(Ordering::Relaxed) {
+                "in progress".into()
+            } else {
+                "unknown".into()
+            };
+
+            groups
+                .entry(feed.group.as_deref())
+                .or_default()
+                .push(FeedDescription {
+                    name: name.into(),
+                    last_updated,
+                    last_result,
+                    failed,
+                    next_update,
+                    entry_count,
+                    rss_url,
+                    fetch_url: feed.request_url.to_string(),
+                });
+        }
+
+        // ungrouped feeds go last.
+        let ungrouped = groups.remove(&None);
+        let groups = groups
+            .into_iter()
+            .chain(ungrouped.map(|feeds| (None, feeds)))
+            .map(|(name, mut feeds)| {
+                feeds.sort_unstable_by(|lhs, rhs| lhs.name.cmp(&rhs.name));
+
+                Group {
+                    name: name.map(Into::into),
+                    opml_url: match name {
+                        Some(name) => format!("{prefix}/opml?group={}", urlencoding::encode(name)),
+                        None => format!("{prefix}/opml"),
+                    },
+                    feeds,
+                }
+            })
+            .collect();
+
+        let ctx = Context {
+            groups,
+            opml_url: format!("{prefix}/opml"),
+        };
+        let html = state
+            .template
+            .render(Template::Index.as_str(), &ctx)
+            .context("could not render the HTML template")?;
+
+        Ok(Html(html))
+    })
+    .await
+}
+
+pub async fn admin(State(state): State<AppState>) -> Result<Html<String>> {
+    #[derive(Serialize, Debug, Clone)]
+    struct LastError {
+        time: String,
+        message: String,
+    }
+
+    #[derive(Serialize, Debug, Clone)]
+    struct FeedDescription {
+        name: String,
+        enabled: bool,
+        request_url: String,
+        last_updated: String,
+        last_error: Option<LastError>,
+        update_url: String,
+        config: String,
+
+        /// Whether the definition was saved on the admin page.
+        saved: bool,
+    }
+
+    #[derive(Serialize, Debug, Clone)]
+    struct Context {
+        prefix: String,
+        validate_url: String,
+        config_url: String,
+        feeds: Vec<FeedDescription>,
+
+        /// The feeds added on the admin page that start running at the next start.
+        pending_feeds: Vec<String>,
+    }
+
+    convert_errors(async move {
+        let prefix = path_prefix(&state);
+        let mut tx = state.storage.begin().await?;
+        let stored_feeds = tx.get_feeds().await?;
+        let saved_feeds = tx.get_saved_feeds().await?;
+        tx.commit().await?;
+
+        let saved_feeds = saved_feeds
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect::<HashSet<_>>();
+
+        let last_updated = stored_feeds
+            .into_iter()
+            .map(|feed| (feed.name, feed.last_updated))
+            .collect::<HashMap<_, _>>();
+
+        let mut feeds = Vec::with_capacity(state.feeds.len());
+
+        for (name, feed) in &*state.feeds {
+            let last_updated = match last_updated.get(name) {
+                Some(&last_updated) => format_date(last_updated)?,
+                None => "never".into(),
+            };
+            let last_fetch = feed.status.lock().unwrap().last_fetch.clone();
+            let last_error = match last_fetch {
+                Some((time, outcome)) => match outcome.error_message() {
+                    Some(message) => Some(LastError {
+                        time: format_date(time)?,
+                        message: message.into(),
+                    }),
+
+                    None => None,
+                },
+
+                None => None,
+            };
+            let config = serde_json::to_string_pretty(&state.cfg.feeds[name].to_redacted_json()?)
+                .context("could not serialize the feed config")?;
 
             feeds.push(FeedDescription {
                 name: name.into(),
+                enabled: feed.enabled,
+                request_url: feed.request_url.to_string(),
                 last_updated,
-                entry_count,
-                rss_url,
-                fetch_url: feed.request_url.to_string(),
+                last_error,
+                update_url: format!("{prefix}/feeds/{}/update", urlencoding::encode(name)),
+                config,
+                saved: saved_feeds.contains(name),
             });
         }
 
         feeds.sort_unstable_by(|lhs, rhs| lhs.name.cmp(&rhs.name));
-        let ctx = Context { feeds };
+        let mut pending_feeds = saved_feeds
+            .into_iter()
+            .filter(|name| !state.feeds.contains_key(name))
+            .collect::<Vec<_>>();
+        pending_feeds.sort_unstable();
+
+        let ctx = Context {
+            prefix: prefix.into(),
+            validate_url: format!("{prefix}/api/config/validate"),
+            config_url: format!("{prefix}/api/config/feeds"),
+            feeds,
+            pending_feeds,
+        };
         let html = state
             .template
-            .render(Template::Index.as_str(), &ctx)
-            .context("could not render the HTML template")?;
+            .render(Template::Admin.as_str(), &ctx)
+            .context("could not render the admin template")?;
 
         Ok(Html(html))
     })
     .await
 }
 
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum FeedFormat {
+    #[default]
+    Rss,
+    Atom,
+    Json,
+}
+
+impl FeedFormat {
+    const ALL: [Self; 3] = [Self::Rss, Self::Atom, Self::Json];
+
+    fn media_types(&self) -> &'static [&'static str] {
+        match self {
+            Self::Rss => &["application/rss+xml"],
+            Self::Atom => &["application/atom+xml"],
+            Self::Json => &["application/feed+json", "application/json"],
+        }
+    }
+
+    /// Picks a format based on the `Accept` header.
+    ///
+    /// Falls back to RSS unless a single format is preferred over the others.
+    fn negotiate(headers: &HeaderMap) -> Self {
+        let mut weights = [0.0f32; Self::ALL.len()];
+
+        for value in headers.get_all(header::ACCEPT) {
+            let Ok(value) = value.to_str() else {
+                continue;
+            };
+
+            for range in value.split(',') {
+                let mut params = range.split(';').map(str::trim);
+                let media_type = params.next().unwrap_or("");
+                let q = params
+                    .filter_map(|param| param.strip_prefix("q="))
+                    .find_map(|q| q.parse::<f32>().ok())
+                    .unwrap_or(1.0);
+
+                for (format, weight) in Self::ALL.iter().zip(&mut weights) {
+                    if format
+                        .media_types()
+                        .iter()
+                        .any(|ty| ty.eq_ignore_ascii_case(media_type))
+                    {
+                        *weight = weight.max(q);
+                    }
+                }
+            }
+        }
+
+        let max = weights.iter().copied().fold(0.0, f32::max);
+
+        if max <= 0.0 || weights.iter().filter(|&&weight| weight == max).count() > 1 {
+            return Self::Rss;
+        }
+
+        Self::ALL
+            .into_iter()
+            .zip(weights)
+            .find(|&(_, weight)| weight == max)
+            .map(|(format, _)| format)
+            .unwrap_or_default()
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct FeedParams {
+    format: Option<FeedFormat>,
+
+    /// The number of entries per page, up to the feed's `max-entries`.
+    limit: Option<usize>,
+
+    /// The page number, starting from 1.
+    page: Option<usize>,
+
+    /// Only include entries retrieved at or after this time.
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    since: Option<OffsetDateTime>,
+
+    /// The access token of a private feed.
+    token: Option<String>,
+}
+
+impl FeedParams {
+    fn limit(&self, max_entries: usize) -> usize {
+        self.limit
+            .map_or(max_entries, |limit| limit.min(max_entries))
+    }
+
+    fn offset(&self, max_entries: usize) -> usize {
+        self.page
+            .unwrap_or(1)
+            .saturating_sub(1)
+            .saturating_mul(self.limit(max_entries))
+    }
+}
+
 pub async fn get_feed(
     State(state): State<AppState>,
     Path(name): Path<String>,
-) -> Result<impl IntoResponse> {
+    Query(params): Query<FeedParams>,
+    headers: HeaderMap,
+) -> Result<Response> {
     let feed = state.feeds.get(&name).ok_or(StatusCode::NOT_FOUND)?;
 
-    let mut entries = convert_errors(async {
+    if !has_feed_access(feed, params.token.as_deref()) {
+        return Err(StatusCode::FORBIDDEN.into());
+    }
+
+    let (entries, last_updated, channel_info) = convert_errors(async {
         let mut tx = state.storage.begin().await?;
-        let entries = tx.get_feed_entries(&name, MAX_FEED_ENTRY_COUNT).await?;
+        let entries = tx
+            .get_feed_entries(
+                &name,
+                params.limit(feed.max_entries),
+                params.offset(feed.max_entries),
+                params.since,
+            )
+            .await?;
+        let last_updated = tx.get_feed_last_updated(&name).await?;
+        let channel_info = tx.get_channel_info(&name).await?;
         tx.commit().await?;
 
-        Ok(entries)
+        Ok((entries, last_updated, channel_info))
     })
     .await?;
-    entries.sort_by_key(|entry| Reverse(entry.pub_date.unwrap()));
 
-    let now = OffsetDateTime::now_utc();
-    let mut channel = ChannelBuilder::default();
-    channel
-        .title(name.clone())
-        .link(feed.request_url.as_str())
-        .last_build_date(
-            now.format(&Rfc2822)
-                .inspect_err(|e| error!("could not format the last build date ({now}): {e:#}"))
-                .ok(),
-        )
-        .generator(Some(format!("Feedgen {}", env!("CARGO_PKG_VERSION"))));
-
-    for entry in entries {
-        channel.item(
-            ItemBuilder::default()
-                .title(Some(entry.title))
-                .link(Some(entry.url.into()))
-                .description(Some(entry.description))
-                .author(entry.author)
-                .guid(Some(
-                    GuidBuilder::default()
-                        .value(format!("feedgen/{}/{}", name, entry.id))
-                        .permalink(false)
-                        .build(),
-                ))
-                .pub_date(entry.pub_date.and_then(|pub_date| {
-                    pub_date
-                        .format(&Rfc2822)
-                        .inspect_err(|e| {
-                            error!("could not format the publication date ({pub_date}): {e:#}")
-                        })
-                        .ok()
-                }))
-                .build(),
-        );
-    }
-
-    let channel = channel.build();
-
-    Ok((
-        [(header::CONTENT_TYPE, "application/rss+xml")],
-        channel.to_string(),
+    let feed_cfg = &state.cfg.feeds[&name];
+    let proxy_url = format!(
+        "{}/img/{}",
+        base_url(&state, &headers),
+        urlencoding::encode(&name),
+    );
+    let channel = Channel {
+        id: format!("feedgen/{name}"),
+        title: feed_cfg
+            .title
+            .clone()
+            .or(channel_info.title)
+            .unwrap_or_else(|| name.clone()),
+        description: feed_cfg.description.clone().or(channel_info.description),
+        language: feed_cfg.language.clone(),
+        copyright: feed_cfg.copyright.clone(),
+        link: feed_cfg
+            .link
+            .as_ref()
+            .or(channel_info.link.as_ref())
+            .unwrap_or(&feed.request_url)
+            .to_string(),
+        self_url: match &feed.token {
+            Some(token) => format!(
+                "{}/feeds/{}?token={}",
+                base_url(&state, &headers),
+                urlencoding::encode(&name),
+                urlencoding::encode(token),
+            ),
+
+            None => format!(
+                "{}/feeds/{}",
+                base_url(&state, &headers),
+                urlencoding::encode(&name),
+            ),
+        },
+        namespaces: feed_cfg.namespaces.clone(),
+        stylesheet_url: Some(format!("{}/feed.xsl", path_prefix(&state))),
+        items: entries
+            .into_iter()
+            .map(|mut entry| {
+                if feed.proxy_images {
+                    imageproxy::rewrite(&mut entry, &proxy_url, feed.token.as_deref());
+                }
+
+                Item::new(&name, entry)
+            })
+            .collect(),
+    };
+
+    Ok(serve_channel(
+        channel,
+        last_updated,
+        params.format,
+        &headers,
+    ))
+}
+
+pub async fn get_aggregate(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+    Query(params): Query<FeedParams>,
+    headers: HeaderMap,
+) -> Result<Response> {
+    let aggregate = state
+        .cfg
+        .aggregates
+        .get(&name)
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    let (entries, last_updated) = convert_errors(async {
+        let mut tx = state.storage.begin().await?;
+        let entries = tx
+            .get_aggregate_entries(
+                &aggregate.feeds,
+                params.limit(state.cfg.max_entries),
+                params.offset(state.cfg.max_entries),
+                params.since,
+            )
+            .await?;
+        let last_updated = tx
+            .get_feeds()
+            .await?
+            .into_iter()
+            .filter(|feed| aggregate.feeds.contains(&feed.name))
+            .map(|feed| feed.last_updated)
+            .max();
+        tx.commit().await?;
+
+        Ok((entries, last_updated))
+    })
+    .await?;
+
+    let base_url = base_url(&state, &headers);
+    let mut seen_urls = HashSet::new();
+    let channel = Channel {
+        id: format!("feedgen/aggregates/{name}"),
+        title: aggregate.title.clone().unwrap_or_else(|| name.clone()),
+        description: None,
+        language: None,
+        copyright: None,
+        link: format!("{base_url}/"),
+        self_url: format!("{base_url}/aggregates/{}", urlencoding::encode(&name)),
+        namespaces: aggregate
+            .feeds
+            .iter()
+            .flat_map(|feed_name| &state.cfg.feeds[feed_name].namespaces)
+            .map(|(prefix, uri)| (prefix.clone(), uri.clone()))
+            .collect(),
+        stylesheet_url: Some(format!("{}/feed.xsl", path_prefix(&state))),
+        items: entries
+            .into_iter()
+            .filter(|(_, entry)| seen_urls.insert(entry.url.clone()))
+            .map(|(feed_name, mut entry)| {
+                if state.feeds[&feed_name].proxy_images {
+                    let proxy_url = format!("{base_url}/img/{}", urlencoding::encode(&feed_name));
+
+                    // aggregates cannot include private feeds.
+                    imageproxy::rewrite(&mut entry, &proxy_url, None);
+                }
+
+                Item::new(&feed_name, entry)
+            })
+            .collect(),
+    };
+
+    Ok(serve_channel(
+        channel,
+        last_updated,
+        params.format,
+        &headers,
     ))
 }
 
+fn serve_channel(
+    mut channel: Channel,
+    last_updated: Option<OffsetDateTime>,
+    format: Option<FeedFormat>,
+    headers: &HeaderMap,
+) -> Response {
+    channel
+        .items
+        .sort_by_key(|item| Reverse(item.entry.pub_date.unwrap()));
+
+    let format = format.unwrap_or_else(|| FeedFormat::negotiate(headers));
+    let validators = Validators::new(
+        (
+            format,
+            channel
+                .items
+                .iter()
+                .map(|item| &item.guid)
+                .collect::<Vec<_>>(),
+        ),
+        last_updated,
+    );
+
+    if validators.is_not_modified(headers) {
+        return ([(header::VARY, "accept")], validators.not_modified()).into_response();
+    }
+
+    let response = match format {
+        FeedFormat::Rss => RssFeed::new(channel).into_response(),
+
+        FeedFormat::Atom => {
+            push_query(&mut channel.self_url, "format=atom");
+
+            AtomFeed::new(channel).into_response()
+        }
+
+        FeedFormat::Json => {
+            push_query(&mut channel.self_url, "format=json");
+
+            JsonFeed::new(channel).into_response()
+        }
+    };
+
+    ([(header::VARY, "accept")], validators.apply(response)).into_response()
+}
+
+fn push_query(url: &mut String, param: &str) {
+    url.push(if url.contains('?') { '&' } else { '?' });
+    url.push_str(param);
+}
+
+/// Returns the URL the server is reachable at, without a trailing slash.
+///
+/// Uses `base-url` from the config if it's set; otherwise reconstructs the URL from the request
+/// headers.
+fn base_url(state: &AppState, headers: &HeaderMap) -> String {
+    if let Some(base_url) = &state.cfg.base_url {
+        return base_url.as_str().trim_end_matches('/').into();
+    }
+
+    let get = |name: &str| {
+        headers
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.split(',').next().unwrap_or("").trim())
+            .filter(|value| !value.is_empty())
+    };
+
+    let scheme = get("x-forwarded-proto").unwrap_or("http");
+    let host = get("x-forwarded-host")
+        .or_else(|| get(header::HOST.as_str()))
+        .unwrap_or("localhost");
+
+    format!("{scheme}://{host}")
+}
+
+/// Returns the path prefix the server is mounted at (e.g., `/feedgen`), without a trailing slash.
+fn path_prefix(state: &AppState) -> &str {
+    state
+        .cfg
+        .base_url
+        .as_ref()
+        .map_or("", |base_url| base_url.path().trim_end_matches('/'))
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct OpmlParams {
+    group: Option<String>,
+}
+
+pub async fn opml(
+    State(state): State<AppState>,
+    Query(params): Query<OpmlParams>,
+    headers: HeaderMap,
+) -> Result<impl IntoResponse> {
+    #[derive(Serialize, Debug, Clone)]
+    struct FeedDescription {
+        name: String,
+        group: Option<String>,
+        rss_url: String,
+        fetch_url: String,
+    }
+
+    #[derive(Serialize, Debug, Clone)]
+    struct Context {
+        date_created: String,
+        feeds: Vec<FeedDescription>,
+    }
+
+    convert_errors(async move {
+        let base_url = base_url(&state, &headers);
+        let mut feeds = state
+            .feeds
+            .iter()
+            .filter(|(_, feed)| feed.token.is_none())
+            .filter(|(_, feed)| params.group.is_none() || feed.group == params.group)
+            .map(|(name, feed)| FeedDescription {
+                name: name.into(),
+                group: feed.group.clone(),
+                rss_url: format!("{base_url}/feeds/{}", urlencoding::encode(name)),
+                fetch_url: feed.request_url.to_string(),
+            })
+            .collect::<Vec<_>>();
+        feeds.sort_unstable_by(|lhs, rhs| lhs.name.cmp(&rhs.name));
+
+        let now = OffsetDateTime::now_utc();
+        let ctx = Context {
+            date_created: now
+                .format(&Rfc2822)
+                .with_context(|| anyhow!("could not format the date {now}"))?,
+            feeds,
+        };
+        let opml = state
+            .template
+            .render(Template::Opml.as_str(), &ctx)
+            .context("could not render the OPML template")?;
+
+        Ok(([(header::CONTENT_TYPE, "text/x-opml")], opml))
+    })
+    .await
+}
+
 pub async fn update_feed(State(state): State<AppState>, Path(name): Path<String>) -> Result<()> {
     let feed = state.feeds.get(&name).ok_or(StatusCode::NOT_FOUND)?;
     let notify = feed.force_update.as_ref().ok_or(FeedCannotBeUpdated { name })?;
@@ -162,3 +702,213 @@ pub async fn update_feed(State(state): State<AppState>, Path(name): Path<String>
 
     Ok(())
 }
+
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct ImageParams {
+    /// The access token of a private feed.
+    token: Option<String>,
+}
+
+/// Serves an image registered for the image proxy, fetching it with the feed's client on the first
+/// request.
+pub async fn get_image(
+    State(state): State<AppState>,
+    Path((name, hash)): Path<(String, String)>,
+    Query(params): Query<ImageParams>,
+) -> Result<Response> {
+    let feed = state.feeds.get(&name).ok_or(StatusCode::NOT_FOUND)?;
+
+    if !has_feed_access(feed, params.token.as_deref()) {
+        return Err(StatusCode::FORBIDDEN.into());
+    }
+
+    let image = convert_errors(async {
+        let mut tx = state.storage.begin().await?;
+        let image = tx.get_image(&name, &hash).await?;
+        tx.commit().await?;
+
+        Ok(image)
+    })
+    .await?
+    .ok_or(StatusCode::NOT_FOUND)?;
+
+    if let (Some(content_type), Some(data)) = (image.content_type, image.data) {
+        return Ok(image_response(content_type, data));
+    }
+
+    let fetched: anyhow::Result<_> = async {
+        let url = Url::parse(&image.url).context("the stored image URL is malformed")?;
+        let response = feed
+            .http_client
+            .get(url)
+            .send()
+            .await
+            .map_err(Into::into)
+            .and_then(|r| r.error_for_status().context("server returned an error"))?;
+
+        let content_type = response
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .filter(|content_type| content_type.starts_with("image/"))
+            .map(String::from)
+            .ok_or_else(|| anyhow!("the response is not an image"))?;
+
+        if response
+            .content_length()
+            .is_some_and(|length| length > MAX_IMAGE_SIZE as u64)
+        {
+            bail!("the image is too large");
+        }
+
+        let data = response
+            .bytes()
+            .await
+            .context("could not read the response")?;
+
+        if data.len() > MAX_IMAGE_SIZE {
+            bail!("the image is too large");
+        }
+
+        Ok((content_type, data))
+    }
+    .await;
+
+    let (content_type, data) = match fetched {
+        Ok(fetched) => fetched,
+
+        Err(e) => {
+            warn!("Could not fetch the image `{}`: {e:#}", image.url);
+
+            return Err(StatusCode::BAD_GATEWAY.into());
+        }
+    };
+
+    convert_errors(async {
+        let mut tx = state.storage.begin().await?;
+        tx.store_image_data(&name, &hash, &content_type, &data)
+            .await?;
+        tx.commit().await?;
+
+        Ok(())
+    })
+    .await?;
+
+    Ok(image_response(content_type, data))
+}
+
+fn image_response(content_type: String, data: impl IntoResponse) -> Response {
+    (
+        [
+            (header::CONTENT_TYPE, content_type),
+            (header::CACHE_CONTROL, IMAGE_CACHE_CONTROL.into()),
+            (
+                header::CONTENT_SECURITY_POLICY,
+                IMAGE_CONTENT_SECURITY_POLICY.into(),
+            ),
+            (header::X_CONTENT_TYPE_OPTIONS, "nosniff".into()),
+        ],
+        data,
+    )
+        .into_response()
+}
+
+pub async fn feed_stylesheet() -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, "text/xsl; charset=utf-8")],
+        include_str!("../static/feed.xsl"),
+    )
+}
+
+pub async fn metrics(State(state): State<AppState>) -> impl IntoResponse {
+    let mut body = String::new();
+
+    let mut gauge = |name: &str, help: &str, value: usize| {
+        let _ = writeln!(body, "# HELP {name} {help}");
+        let _ = writeln!(body, "# TYPE {name} gauge");
+        let _ = writeln!(body, "{name} {value}");
+    };
+
+    gauge(
+        "feedgen_fetch_queue_depth",
+        "The number of feeds waiting for a fetch slot.",
+        state.fetch_queue.depth(),
+    );
+    gauge(
+        "feedgen_fetch_queue_active",
+        "The number of fetches in progress.",
+        state.fetch_queue.active(),
+    );
+
+    ([(header::CONTENT_TYPE, "text/plain; version=0.0.4")], body)
+}
+
+pub async fn health(State(state): State<AppState>) -> Result<impl IntoResponse> {
+    #[derive(Serialize, Debug, Clone)]
+    struct FeedHealth {
+        task_alive: bool,
+
+        #[serde(with = "time::serde::rfc3339::option")]
+        last_updated: Option<OffsetDateTime>,
+
+        stale: bool,
+    }
+
+    #[derive(Serialize, Debug, Clone)]
+    struct Health {
+        healthy: bool,
+        feeds: BTreeMap<String, FeedHealth>,
+    }
+
+    convert_errors(async move {
+        let mut tx = state.storage.begin().await?;
+        let stored_feeds = tx.get_feeds().await?;
+        tx.commit().await?;
+
+        let last_updated = stored_feeds
+            .into_iter()
+            .map(|feed| (feed.name, feed.last_updated))
+            .collect::<HashMap<_, _>>();
+
+        let now = OffsetDateTime::now_utc();
+        let mut feeds = BTreeMap::new();
+
+        for (name, feed) in state.feeds.iter().filter(|(_, feed)| feed.enabled) {
+            let last_updated = last_updated.get(name).copied();
+
+            // a feed that was last updated before a restart gets a fresh grace period.
+            let since = last_updated.map_or(state.started, |last_updated| {
+                last_updated.max(state.started)
+            });
+            // an adaptive fetch interval may grow up to its maximum.
+            let fetch_interval = feed
+                .adaptive_fetch_interval
+                .map_or(feed.fetch_interval, |(_, max)| max);
+            let threshold = fetch_interval
+                .checked_mul(state.cfg.health_stale_factor)
+                .and_then(|threshold| {
+                    threshold.checked_add(state.cfg.max_initial_fetch_sleep.into())
+                })
+                .unwrap_or(Duration::MAX);
+
+            feeds.insert(
+                name.clone(),
+                FeedHealth {
+                    task_alive: feed.task_alive.load(Ordering::Relaxed),
+                    last_updated,
+                    stale: now - since > threshold,
+                },
+            );
+        }
+
+        let healthy = feeds.values().all(|feed| feed.task_alive && !feed.stale);
+        let status = if healthy {
+            StatusCode::OK
+        } else {
+            StatusCode::SERVICE_UNAVAILABLE
+        };
+
+        Ok((status, Json(Health { healthy, feeds })))
+    })
+    .await
+}
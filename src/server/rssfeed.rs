@@ -0,0 +1,221 @@
+use std::collections::BTreeMap;
+
+use atom_syndication::LinkBuilder;
+use axum::http::header;
+use axum::response::{IntoResponse, Response};
+use rss::extension::atom::{self, AtomExtensionBuilder};
+use rss::extension::itunes::{self, ITunesChannelExtensionBuilder, ITunesItemExtensionBuilder};
+use rss::extension::{ExtensionBuilder, ExtensionMap};
+use rss::{CategoryBuilder, ChannelBuilder, EnclosureBuilder, GuidBuilder, ItemBuilder};
+use time::format_description::well_known::Rfc2822;
+use time::OffsetDateTime;
+use tracing::{error, warn};
+
+use super::channel::Channel;
+
+const MEDIA_NAMESPACE: &str = "http://search.yahoo.com/mrss/";
+
+/// The namespaces feeds declare by themselves, keyed by their prefixes.
+pub const BUILTIN_NAMESPACES: [(&str, &str); 3] = [
+    ("atom", atom::NAMESPACE),
+    ("itunes", itunes::NAMESPACE),
+    ("media", MEDIA_NAMESPACE),
+];
+
+/// Builds Media RSS elements for an entry's preview image.
+fn add_media_extensions(extensions: &mut ExtensionMap, image_url: &str) {
+    let media_element = |name: &str, attrs: &[(&str, &str)]| {
+        ExtensionBuilder::default()
+            .name(format!("media:{name}"))
+            .attrs(
+                attrs
+                    .iter()
+                    .map(|&(key, value)| (key.into(), value.into()))
+                    .collect::<BTreeMap<_, _>>(),
+            )
+            .build()
+    };
+
+    let elements = extensions.entry("media".into()).or_default();
+    elements.insert(
+        "thumbnail".into(),
+        vec![media_element("thumbnail", &[("url", image_url)])],
+    );
+    elements.insert(
+        "content".into(),
+        vec![media_element(
+            "content",
+            &[("url", image_url), ("medium", "image")],
+        )],
+    );
+}
+
+/// Builds elements for an entry's extra fields whose namespaces are declared.
+fn add_extra_extensions(
+    extensions: &mut ExtensionMap,
+    namespaces: &BTreeMap<String, String>,
+    extra: BTreeMap<String, String>,
+) {
+    for (name, value) in extra {
+        let Some((prefix, local_name)) = name
+            .split_once(':')
+            .filter(|(prefix, _)| namespaces.contains_key(*prefix))
+        else {
+            warn!("Skipping the element `{name}`: its namespace prefix is not declared");
+
+            continue;
+        };
+
+        extensions
+            .entry(prefix.into())
+            .or_default()
+            .entry(local_name.into())
+            .or_default()
+            .push(
+                ExtensionBuilder::default()
+                    .name(name.clone())
+                    .value(Some(value))
+                    .build(),
+            );
+    }
+}
+
+/// An RSS 2.0 document.
+pub struct RssFeed {
+    channel: rss::Channel,
+    stylesheet_url: Option<String>,
+}
+
+impl RssFeed {
+    pub fn new(channel: Channel) -> Self {
+        let now = OffsetDateTime::now_utc();
+        let mut builder = ChannelBuilder::default();
+        builder
+            .title(channel.title)
+            .link(channel.link)
+            .description(channel.description.clone().unwrap_or_default())
+            .language(channel.language)
+            .copyright(channel.copyright)
+            .last_build_date(
+                now.format(&Rfc2822)
+                    .inspect_err(|e| error!("could not format the last build date ({now}): {e:#}"))
+                    .ok(),
+            )
+            .generator(Some(format!("Feedgen {}", env!("CARGO_PKG_VERSION"))))
+            .atom_ext(Some(
+                AtomExtensionBuilder::default()
+                    .links(vec![LinkBuilder::default()
+                        .rel("self")
+                        .href(channel.self_url)
+                        .mime_type(Some("application/rss+xml".into()))
+                        .build()])
+                    .build(),
+            ));
+
+        // podcast apps expect iTunes tags in feeds with enclosures.
+        if channel
+            .items
+            .iter()
+            .any(|item| item.entry.enclosure.is_some())
+        {
+            builder.itunes_ext(Some(
+                ITunesChannelExtensionBuilder::default()
+                    .summary(channel.description.clone())
+                    .build(),
+            ));
+        }
+
+        let mut namespaces = channel.namespaces;
+
+        if channel.items.iter().any(|item| item.entry.image.is_some()) {
+            namespaces.insert("media".into(), MEDIA_NAMESPACE.into());
+        }
+
+        for item in channel.items {
+            let entry = item.entry;
+            let mut extensions = ExtensionMap::new();
+
+            if let Some(image) = &entry.image {
+                add_media_extensions(&mut extensions, image.as_str());
+            }
+
+            add_extra_extensions(&mut extensions, &namespaces, entry.extra);
+
+            let itunes_ext = entry.enclosure.as_ref().map(|_| {
+                ITunesItemExtensionBuilder::default()
+                    .author(entry.author.clone())
+                    .summary(Some(entry.description.clone()))
+                    .build()
+            });
+
+            builder.item(
+                ItemBuilder::default()
+                    .title(Some(entry.title))
+                    .link(Some(entry.url.into()))
+                    .comments(entry.comments.map(Into::into))
+                    .description(Some(entry.description))
+                    .content(entry.content)
+                    .author(entry.author)
+                    .categories(
+                        entry
+                            .categories
+                            .into_iter()
+                            .map(|name| CategoryBuilder::default().name(name).build())
+                            .collect::<Vec<_>>(),
+                    )
+                    .enclosure(entry.enclosure.map(|enclosure| {
+                        EnclosureBuilder::default()
+                            .url(enclosure.url)
+                            .mime_type(enclosure.mime_type)
+                            .length(enclosure.length.unwrap_or(0).to_string())
+                            .build()
+                    }))
+                    .itunes_ext(itunes_ext)
+                    .extensions(extensions)
+                    .guid(Some(
+                        GuidBuilder::default()
+                            .value(item.guid)
+                            .permalink(item.guid_is_permalink)
+                            .build(),
+                    ))
+                    .pub_date(entry.pub_date.and_then(|pub_date| {
+                        pub_date
+                            .format(&Rfc2822)
+                            .inspect_err(|e| {
+                                error!("could not format the publication date ({pub_date}): {e:#}")
+                            })
+                            .ok()
+                    }))
+                    .build(),
+            );
+        }
+
+        builder.namespaces(namespaces);
+
+        Self {
+            channel: builder.build(),
+            stylesheet_url: channel.stylesheet_url,
+        }
+    }
+}
+
+impl IntoResponse for RssFeed {
+    fn into_response(self) -> Response {
+        let mut body = self.channel.to_string();
+
+        if let Some(stylesheet_url) = self.stylesheet_url {
+            // the stylesheet instruction must follow the XML declaration.
+            let pos = body
+                .strip_prefix("<?xml")
+                .and_then(|_| body.find("?>"))
+                .map_or(0, |pos| pos + 2);
+            let href = stylesheet_url.replace('&', "&amp;").replace('"', "&quot;");
+            body.insert_str(
+                pos,
+                &format!(r#"<?xml-stylesheet type="text/xsl" href="{href}"?>"#),
+            );
+        }
+
+        IntoResponse::into_response(([(header::CONTENT_TYPE, "application/rss+xml")], body))
+    }
+}
@@ -1,14 +1,26 @@
 use std::collections::HashMap;
+use std::sync::atomic::AtomicBool;
 use std::sync::{Arc, Mutex};
 use std::time::Duration;
 
-use anyhow::Result;
+use anyhow::{bail, Context, Result};
 use handlebars::Handlebars;
 use reqwest::Url;
+use reqwest_middleware::ClientWithMiddleware;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use time::OffsetDateTime;
 use tokio::sync::Notify;
+use tracing::{info, warn};
 
-use crate::config::{self, Config, ExtractorConfig};
-use crate::extractor::{Extractor, LuaExtractor, XPathExtractor};
+use crate::config::{self, Config, ExtractorConfig, LuaConfig};
+use crate::extractor::{
+    CssExtractor, Extractor, FeedExtractor, JsonExtractor, LuaExtractor, MetadataExtractor,
+    RegexExtractor, SitemapExtractor, WatchExtractor, XPathExtractor,
+};
+use crate::fetch::{self, CookieJar, FetchQueue};
+use crate::postprocess::PostProcessor;
+use crate::server::BUILTIN_NAMESPACES;
 use crate::storage::Storage;
 use crate::template;
 
@@ -18,57 +30,434 @@ pub struct State {
     pub cfg: Arc<Config>,
     pub feeds: Arc<HashMap<String, Feed>>,
     pub template: Arc<Handlebars<'static>>,
+    pub fetch_queue: Arc<FetchQueue>,
+    pub http_client: ClientWithMiddleware,
+    pub started: OffsetDateTime,
 }
 
 impl State {
-    pub async fn new(cfg: Config) -> Result<Self> {
+    pub async fn new(mut cfg: Config) -> Result<Self> {
         let storage = Arc::new(Storage::new(&cfg.db_path).await?);
-        let feeds = Arc::new(Self::make_feeds(&cfg)?);
+        Self::apply_saved_feeds(&mut cfg, &storage).await?;
+
+        for (name, feed) in &cfg.feeds {
+            check_feed(&cfg, name, feed)?;
+        }
+
+        for (name, aggregate) in &cfg.aggregates {
+            for feed_name in &aggregate.feeds {
+                if !cfg.feeds.contains_key(feed_name) {
+                    bail!("the aggregate feed `{name}` refers to an unknown feed `{feed_name}`");
+                }
+            }
+        }
+
+        let http_client = fetch::make_http_client(&cfg)?;
+        let feeds = Arc::new(Self::make_feeds(&cfg, &http_client)?);
         let cfg = Arc::new(cfg);
         let template = Arc::new(template::new());
+        let fetch_queue = Arc::new(FetchQueue::new(cfg.max_concurrent_fetches));
 
         Ok(State {
             storage,
             cfg,
             feeds,
             template,
+            fetch_queue,
+            http_client,
+            started: OffsetDateTime::now_utc(),
         })
     }
 
-    fn make_feeds(cfg: &Config) -> Result<HashMap<String, Feed>> {
+    /// Replaces the feed definitions of the config with the ones saved on the admin page. The
+    /// credentials and cookie file of a feed are kept from the config file.
+    pub async fn apply_saved_feeds(cfg: &mut Config, storage: &Storage) -> Result<()> {
+        let mut tx = storage.begin().await?;
+        let saved_feeds = tx.get_saved_feeds().await?;
+        tx.commit().await?;
+
+        for (name, definition) in saved_feeds {
+            let feed = serde_json::from_str::<config::Feed>(&definition)
+                .context("the definition is malformed")
+                .and_then(|mut feed| {
+                    feed.resolve_relative_paths(&cfg.config_dir);
+
+                    if let Some(current) = cfg.feeds.get(&name) {
+                        feed.credentials = current.credentials.clone();
+                        feed.cookie_file = current.cookie_file.clone();
+                    }
+
+                    check_feed(cfg, &name, &feed)?;
+
+                    Ok(feed)
+                });
+
+            match feed {
+                Ok(feed) => {
+                    info!("Using the definition of the feed `{name}` saved on the admin page");
+                    cfg.feeds.insert(name, feed);
+                }
+
+                Err(e) => warn!(
+                    "Ignoring the definition of the feed `{name}` saved on the admin page: {e:#}"
+                ),
+            }
+        }
+
+        Ok(())
+    }
+
+    fn make_feeds(
+        cfg: &Config,
+        http_client: &ClientWithMiddleware,
+    ) -> Result<HashMap<String, Feed>> {
         cfg.feeds
             .iter()
-            .map(|(name, feed)| Feed::new(cfg, feed).map(|feed| (name.clone(), feed)))
+            .map(|(name, feed)| Feed::new(cfg, feed, http_client).map(|feed| (name.clone(), feed)))
             .collect()
     }
 }
 
 pub struct Feed {
     pub request_url: Url,
+
+    /// The client for the feed's requests, which sends the feed's custom headers and cookies.
+    pub http_client: ClientWithMiddleware,
+
+    /// The cookies of `http_client`, if the feed has its own.
+    pub cookie_jar: Option<CookieJar>,
+
     pub extractor: Mutex<Box<dyn Extractor + Send>>,
+    pub postprocessor: PostProcessor,
+
+    /// How to extract the full content of new entries from their pages.
+    pub full_content: Option<config::FullContentConfig>,
+
+    /// The timezone of extracted datetimes that have no UTC offset.
+    pub timezone: Option<config::Timezone>,
+
+    /// The kind of document the source page must be.
+    pub expected_content: config::ContentKind,
+
+    pub on_extraction_failure: config::ExtractionFailurePolicy,
+
+    /// The minimum number of entries an update must extract to be stored.
+    pub min_entries: usize,
+
+    pub proxy_images: bool,
+
+    /// Whether to drop entries that fail validation.
+    pub strict_output: bool,
+
+    /// The maximum number of pages fetched per update (at least 1).
+    pub max_pages: usize,
+
+    /// Hashes the source page along with the feed's config, if extracting an unchanged page is
+    /// skipped.
+    pub page_hasher: Option<Sha256>,
+
+    /// The interval between updates (the initial one if it's adaptive).
     pub fetch_interval: Duration,
+
+    /// The bounds of the fetch interval if it's adjusted to how often new entries appear.
+    pub adaptive_fetch_interval: Option<(Duration, Duration)>,
+
+    /// A daily period during which scheduled updates are deferred.
+    pub no_fetch_between: Option<config::TimeWindow>,
+
     pub enabled: bool,
     pub force_update: Option<Arc<Notify>>,
+    pub priority: i32,
+    pub max_entries: usize,
+    pub group: Option<String>,
+    pub token: Option<String>,
+
+    /// URLs to notify about new entries.
+    pub webhooks: Vec<Url>,
+
+    /// Whether the fetcher task for this feed is running.
+    pub task_alive: AtomicBool,
+
+    pub status: Mutex<FeedStatus>,
+}
+
+/// The outcomes of the feed's updates since startup.
+#[derive(Debug, Clone, Default)]
+pub struct FeedStatus {
+    pub last_fetch: Option<(OffsetDateTime, FetchOutcome)>,
+    pub last_success: Option<OffsetDateTime>,
+    pub consecutive_failures: u32,
+
+    /// When the next update is scheduled.
+    pub next_fetch: Option<OffsetDateTime>,
+}
+
+impl FeedStatus {
+    pub fn record(&mut self, outcome: FetchOutcome) {
+        let now = OffsetDateTime::now_utc();
+
+        if outcome.is_success() {
+            self.last_success = Some(now);
+            self.consecutive_failures = 0;
+        } else if let FetchOutcome::Discarded { .. } = outcome {
+            // under the `skip` policy, the feed is not updated, but the update is not a failure
+            // either.
+        } else {
+            self.consecutive_failures = self.consecutive_failures.saturating_add(1);
+        }
+
+        self.last_fetch = Some((now, outcome));
+    }
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(tag = "result", rename_all = "kebab-case")]
+pub enum FetchOutcome {
+    #[serde(rename_all = "kebab-case")]
+    Success {
+        entry_count: usize,
+        new_entry_count: usize,
+    },
+
+    /// The source page has not changed since the previous update, so it was not extracted.
+    Unchanged,
+
+    /// The source page could not be retrieved.
+    #[serde(rename_all = "kebab-case")]
+    HttpError {
+        status: Option<u16>,
+        message: String,
+    },
+
+    ExtractionError {
+        message: String,
+    },
+
+    /// The extracted entries were not stored because of extraction failures (under the feed's
+    /// `skip` policy).
+    #[serde(rename_all = "kebab-case")]
+    Discarded {
+        entry_count: usize,
+        message: String,
+    },
+
+    StorageError {
+        message: String,
+    },
+}
+
+impl FetchOutcome {
+    pub fn http_error(e: &anyhow::Error) -> Self {
+        let status = e
+            .chain()
+            .find_map(|e| e.downcast_ref::<reqwest::Error>())
+            .and_then(|e| e.status())
+            .map(|status| status.as_u16());
+
+        Self::HttpError {
+            status,
+            message: format!("{e:#}"),
+        }
+    }
+
+    /// Whether the update did not fail and the feed is up to date. Discarding the entries is
+    /// neither a success nor a failure.
+    pub fn is_success(&self) -> bool {
+        matches!(self, Self::Success { .. } | Self::Unchanged)
+    }
+
+    pub fn error_message(&self) -> Option<&str> {
+        match self {
+            Self::Success { .. } | Self::Unchanged => None,
+
+            Self::HttpError { message, .. }
+            | Self::ExtractionError { message }
+            | Self::Discarded { message, .. }
+            | Self::StorageError { message } => Some(message),
+        }
+    }
 }
 
 impl Feed {
-    fn new(cfg: &Config, feed: &config::Feed) -> Result<Self> {
-        let fetch_interval = feed.fetch_interval.unwrap_or(cfg.fetch_interval).into();
-        let extractor = Mutex::new(make_extractor(&feed.extractor)?);
+    fn new(cfg: &Config, feed: &config::Feed, http_client: &ClientWithMiddleware) -> Result<Self> {
+        let mut fetch_interval: Duration = feed.fetch_interval.unwrap_or(cfg.fetch_interval).into();
+        let adaptive_fetch_interval = feed
+            .adaptive_fetch_interval
+            .map(|bounds| (bounds.min.into(), bounds.max.into()));
+
+        if let Some((min, max)) = adaptive_fetch_interval {
+            fetch_interval = fetch_interval.clamp(min, max);
+        }
+
+        // only the first page is compared, so later pages could change unnoticed.
+        let skip_unchanged = feed.max_pages <= 1
+            && feed
+                .skip_unchanged
+                .unwrap_or(!feed.extractor.reads_other_pages());
+
+        // the config is hashed as well so that changing it gets the page extracted again.
+        let page_hasher = if skip_unchanged {
+            let cfg_json =
+                serde_json::to_vec(feed).context("could not serialize the feed config")?;
+
+            Some(Sha256::new_with_prefix(cfg_json))
+        } else {
+            None
+        };
+
+        let extractor = Mutex::new(make_extractor(&feed.extractor, &cfg.lua)?);
+        let (http_client, cookie_jar) = fetch::make_feed_http_client(http_client, cfg, feed)?;
 
         Ok(Feed {
             request_url: feed.request_url.clone(),
+            http_client,
+            cookie_jar,
             extractor,
+            postprocessor: PostProcessor::from_cfg(feed),
+            full_content: feed.full_content.clone(),
+            timezone: feed.timezone,
+            expected_content: feed.content_kind(),
+            on_extraction_failure: feed.on_extraction_failure,
+            min_entries: feed.min_entries,
+            proxy_images: feed.proxy_images,
+            strict_output: feed.strict_output.unwrap_or(cfg.strict_output),
+            max_pages: feed.max_pages.max(1),
+            page_hasher,
             fetch_interval,
+            adaptive_fetch_interval,
+            no_fetch_between: feed.no_fetch_between.or(cfg.no_fetch_between),
             enabled: feed.enabled,
             force_update: feed.enabled.then(|| Arc::new(Notify::new())),
+            priority: feed.priority,
+            max_entries: feed.max_entries.unwrap_or(cfg.max_entries),
+            group: feed.group.clone(),
+            token: feed.token.clone(),
+            webhooks: cfg.webhooks.iter().chain(&feed.webhooks).cloned().collect(),
+            task_alive: AtomicBool::new(false),
+            status: Default::default(),
         })
     }
+
+    /// Hashes the source page to compare it with the one of the previous update (`None` if
+    /// unchanged pages are extracted anyway).
+    pub fn page_hash(&self, body: &str) -> Option<String> {
+        let hash = self.page_hasher.clone()?.chain_update(body).finalize();
+
+        Some(hash.iter().map(|byte| format!("{byte:02x}")).collect())
+    }
+}
+
+/// Checks what deserializing the definition of a feed doesn't.
+pub fn check_feed(cfg: &Config, name: &str, feed: &config::Feed) -> Result<()> {
+    let extra: Vec<_> = match &feed.extractor {
+        ExtractorConfig::XPath(extractor) => extractor.extra.keys().collect(),
+        ExtractorConfig::Css(extractor) => extractor.extra.keys().collect(),
+        ExtractorConfig::Json(extractor) => extractor.extra.keys().collect(),
+        ExtractorConfig::Regex(_)
+        | ExtractorConfig::Feed(_)
+        | ExtractorConfig::Watch(_)
+        | ExtractorConfig::Sitemap(_)
+        | ExtractorConfig::Metadata(_)
+        | ExtractorConfig::Lua(_) => vec![],
+    };
+
+    for element in extra {
+        match element.split_once(':') {
+            Some((prefix, _)) if feed.namespaces.contains_key(prefix) => {}
+
+            _ => bail!(
+                "the extra element `{element}` of the feed `{name}` \
+                    must have a namespace prefix declared in `namespaces`"
+            ),
+        }
+    }
+
+    for (prefix, uri) in &feed.namespaces {
+        if let Some((_, builtin_uri)) = BUILTIN_NAMESPACES
+            .iter()
+            .find(|(builtin_prefix, _)| builtin_prefix == prefix)
+        {
+            if uri != builtin_uri {
+                bail!(
+                    "the namespace prefix `{prefix}` of the feed `{name}` \
+                        is reserved for `{builtin_uri}`"
+                );
+            }
+        }
+
+        // an aggregate feed declares the namespaces of all its feeds at once.
+        for (aggregate_name, aggregate) in &cfg.aggregates {
+            if !aggregate.feeds.iter().any(|feed_name| feed_name == name) {
+                continue;
+            }
+
+            for other_name in &aggregate.feeds {
+                if other_name == name {
+                    continue;
+                }
+
+                let Some(other_uri) = cfg
+                    .feeds
+                    .get(other_name)
+                    .and_then(|other| other.namespaces.get(prefix))
+                else {
+                    continue;
+                };
+
+                if other_uri != uri {
+                    bail!(
+                        "the aggregate feed `{aggregate_name}` includes the feeds `{name}` \
+                            and `{other_name}`, which bind the namespace prefix `{prefix}` \
+                            to different URIs (`{uri}` and `{other_uri}`)"
+                    );
+                }
+            }
+        }
+    }
+
+    if let Some(bounds) = &feed.adaptive_fetch_interval {
+        if Duration::from(bounds.min) > Duration::from(bounds.max) {
+            bail!(
+                "the adaptive fetch interval of the feed `{name}` \
+                    has a minimum greater than its maximum"
+            );
+        }
+    }
+
+    if feed.skip_unchanged == Some(true) && feed.max_pages > 1 {
+        bail!("the feed `{name}` cannot set `skip-unchanged` when it fetches several pages");
+    }
+
+    if let Some(config::FullContentConfig::XPath { xpath }) = &feed.full_content {
+        xpath
+            .check_xpath1()
+            .context("the full-content XPath expressions must be XPath 1.0")?;
+    }
+
+    if feed.token.is_some() {
+        for (aggregate_name, aggregate) in &cfg.aggregates {
+            if aggregate.feeds.iter().any(|feed_name| feed_name == name) {
+                bail!("the aggregate feed `{aggregate_name}` includes a private feed `{name}`");
+            }
+        }
+    }
+
+    Ok(())
 }
 
-fn make_extractor(cfg: &ExtractorConfig) -> Result<Box<dyn Extractor + Send>> {
+pub fn make_extractor(
+    cfg: &ExtractorConfig,
+    lua_cfg: &LuaConfig,
+) -> Result<Box<dyn Extractor + Send>> {
     Ok(match cfg {
-        ExtractorConfig::XPath(cfg) => Box::new(XPathExtractor::from_cfg(cfg)),
-        ExtractorConfig::Lua(cfg) => Box::new(LuaExtractor::from_cfg(cfg)?),
+        ExtractorConfig::XPath(cfg) => Box::new(XPathExtractor::from_cfg(cfg)?),
+        ExtractorConfig::Css(cfg) => Box::new(CssExtractor::from_cfg(cfg)),
+        ExtractorConfig::Json(cfg) => Box::new(JsonExtractor::from_cfg(cfg)),
+        ExtractorConfig::Regex(cfg) => Box::new(RegexExtractor::from_cfg(cfg)?),
+        ExtractorConfig::Feed(cfg) => Box::new(FeedExtractor::from_cfg(cfg)),
+        ExtractorConfig::Watch(cfg) => Box::new(WatchExtractor::from_cfg(cfg)),
+        ExtractorConfig::Sitemap(cfg) => Box::new(SitemapExtractor::from_cfg(cfg)),
+        ExtractorConfig::Metadata(cfg) => Box::new(MetadataExtractor::from_cfg(cfg)),
+        ExtractorConfig::Lua(cfg) => Box::new(LuaExtractor::from_cfg(cfg, lua_cfg)?),
     })
 }
@@ -0,0 +1,37 @@
+<?xml version="1.0" encoding="utf-8"?>
+<xsl:stylesheet version="1.0" xmlns:xsl="http://www.w3.org/1999/XSL/Transform">
+  <xsl:output method="html" encoding="utf-8"/>
+
+  <xsl:template match="/rss/channel">
+    <html>
+      <head>
+        <meta charset="utf-8"/>
+        <title><xsl:value-of select="title"/></title>
+      </head>
+
+      <body>
+        <h1><xsl:value-of select="title"/></h1>
+        <p>
+          This is an RSS feed generated from
+          <a href="{link}"><xsl:value-of select="link"/></a>.
+          Copy the URL from the address bar into your feed reader to subscribe.
+        </p>
+
+        <xsl:for-each select="item">
+          <article>
+            <h2><a href="{link}"><xsl:value-of select="title"/></a></h2>
+            <p>
+              <xsl:if test="author">
+                <xsl:value-of select="author"/>
+                <xsl:text> — </xsl:text>
+              </xsl:if>
+              <xsl:value-of select="pubDate"/>
+            </p>
+            <!-- the description is untrusted markup: render it without scripts or same-origin access. -->
+            <iframe sandbox="" srcdoc="{description}" style="width: 100%; border: none"/>
+          </article>
+        </xsl:for-each>
+      </body>
+    </html>
+  </xsl:template>
+</xsl:stylesheet>
@@ -6,12 +6,14 @@ use anyhow::{anyhow, Context, Result};
 use reqwest::Url;
 use sqlx::sqlite::{SqliteConnectOptions, SqliteJournalMode, SqlitePoolOptions};
 use sqlx::{Sqlite, SqlitePool, Transaction};
-use time::OffsetDateTime;
+use time::{OffsetDateTime, UtcOffset};
 use tracing::{debug, error, info, instrument, trace_span, Instrument, Span};
 
-use crate::extractor::Entry;
+use crate::extractor::{ChannelInfo, Enclosure, Entry, Response};
+use crate::imageproxy;
+use crate::postprocess;
 
-use self::entities::{Feed, FeedInfo};
+use self::entities::{ArchivedResponse, Feed, FeedInfo, Image};
 
 pub struct Storage {
     pool: SqlitePool,
@@ -61,8 +63,17 @@ impl Tx {
             .context("could not commit a DB transaction")
     }
 
+    /// Stores the entries of a feed, returning those that were not stored before.
+    ///
+    /// If `dedup_by_content` is set, entries with the same content hash as a stored entry under a
+    /// different id are skipped.
     #[instrument(level = "TRACE", skip(self, entries), fields(entry_count = entries.len()))]
-    pub async fn store_entries(&mut self, feed_name: &str, entries: Vec<Entry>) -> Result<()> {
+    pub async fn store_entries(
+        &mut self,
+        feed_name: &str,
+        entries: Vec<Entry>,
+        dedup_by_content: bool,
+    ) -> Result<Vec<Entry>> {
         let now = OffsetDateTime::now_utc();
         let feed_id: i64 = sqlx::query_scalar(
             "INSERT
@@ -78,10 +89,51 @@ impl Tx {
         .context("could not retrieve the feed id")?;
 
         Span::current().record("feed_id", feed_id);
+        let mut new_entries = vec![];
 
         for (idx, entry) in entries.into_iter().enumerate() {
             async {
                 debug!(%entry.id, %entry.title, "Storing entry");
+                let content_hash = postprocess::content_hash(&entry);
+
+                if dedup_by_content {
+                    let duplicate_id: Option<String> = sqlx::query_scalar(
+                        "SELECT entry_id
+                        FROM entries
+                        WHERE feed_id = ?1 AND content_hash = ?2 AND entry_id <> ?3
+                        LIMIT 1",
+                    )
+                    .bind(feed_id)
+                    .bind(&content_hash)
+                    .bind(&entry.id)
+                    .fetch_optional(self.0.as_mut())
+                    .await
+                    .context("could not look up entries with the same content")?;
+
+                    if let Some(duplicate_id) = duplicate_id {
+                        debug!(%entry.id, %duplicate_id, "Skipping a duplicate entry");
+
+                        return Ok(());
+                    }
+                }
+
+                let exists: bool = sqlx::query_scalar(
+                    "SELECT EXISTS (
+                      SELECT 1
+                      FROM entries
+                      WHERE feed_id = ?1 AND entry_id = ?2
+                    )",
+                )
+                .bind(feed_id)
+                .bind(&entry.id)
+                .fetch_one(self.0.as_mut())
+                .await
+                .context("could not check whether the entry exists")?;
+
+                if !exists {
+                    new_entries.push(entry.clone());
+                }
+
                 sqlx::query(
                     "INSERT
                     INTO entries (
@@ -90,33 +142,503 @@ impl Tx {
                       entry_id,
                       title,
                       description,
+                      content,
                       url,
                       author,
-                      published
-                    ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+                      published,
+                      enclosure_url,
+                      enclosure_type,
+                      enclosure_length,
+                      image_url,
+                      comments_url,
+                      categories,
+                      guid,
+                      guid_is_permalink,
+                      extra,
+                      content_hash
+                    ) VALUES (
+                      ?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17,
+                      ?18, ?19
+                    )
                     ON CONFLICT (feed_id, entry_id) DO UPDATE SET
                       title = excluded.title,
                       description = excluded.description,
+                      content = excluded.content,
                       url = excluded.url,
                       author = excluded.author,
-                      published = excluded.published",
+                      published = excluded.published,
+                      enclosure_url = excluded.enclosure_url,
+                      enclosure_type = excluded.enclosure_type,
+                      enclosure_length = excluded.enclosure_length,
+                      image_url = excluded.image_url,
+                      comments_url = excluded.comments_url,
+                      categories = excluded.categories,
+                      guid = excluded.guid,
+                      guid_is_permalink = excluded.guid_is_permalink,
+                      extra = excluded.extra,
+                      content_hash = excluded.content_hash",
                 )
                 .bind(feed_id)
                 .bind(now)
                 .bind(entry.id)
                 .bind(entry.title)
                 .bind(entry.description)
+                .bind(entry.content)
                 .bind(entry.url.to_string())
                 .bind(entry.author)
                 .bind(entry.pub_date)
+                .bind(
+                    entry
+                        .enclosure
+                        .as_ref()
+                        .map(|enclosure| enclosure.url.to_string()),
+                )
+                .bind(
+                    entry
+                        .enclosure
+                        .as_ref()
+                        .map(|enclosure| enclosure.mime_type.clone()),
+                )
+                .bind(
+                    entry
+                        .enclosure
+                        .as_ref()
+                        .and_then(|enclosure| enclosure.length)
+                        .map(|length| length as i64),
+                )
+                .bind(entry.image.as_ref().map(Url::to_string))
+                .bind(entry.comments.as_ref().map(Url::to_string))
+                .bind(
+                    (!entry.categories.is_empty())
+                        .then(|| serde_json::to_string(&entry.categories))
+                        .transpose()
+                        .context("could not serialize categories")?,
+                )
+                .bind(entry.guid)
+                .bind(entry.guid_is_permalink)
+                .bind(
+                    (!entry.extra.is_empty())
+                        .then(|| serde_json::to_string(&entry.extra))
+                        .transpose()
+                        .context("could not serialize extra elements")?,
+                )
+                .bind(content_hash)
                 .execute(self.0.as_mut())
                 .await
-                .context("could not insert an entry")
+                .context("could not insert an entry")?;
+
+                Ok::<_, anyhow::Error>(())
             }
             .instrument(trace_span!("insert_entry", %idx))
             .await?;
         }
 
+        Ok(new_entries)
+    }
+
+    /// Retrieves the content of a stored entry, returning `None` if the entry is not stored.
+    #[instrument(level = "TRACE", skip(self))]
+    pub async fn get_entry_content(
+        &mut self,
+        feed_name: &str,
+        entry_id: &str,
+    ) -> Result<Option<Option<String>>> {
+        sqlx::query_scalar(
+            "SELECT entries.content
+            FROM entries
+              JOIN feeds ON (feeds.id = entries.feed_id)
+            WHERE feeds.name = ?1 AND entries.entry_id = ?2",
+        )
+        .bind(feed_name)
+        .bind(entry_id)
+        .fetch_optional(self.0.as_mut())
+        .await
+        .context("could not retrieve the entry content")
+    }
+
+    /// Retrieves a stored entry.
+    #[instrument(level = "TRACE", skip(self))]
+    pub async fn get_entry(&mut self, feed_name: &str, entry_id: &str) -> Result<Option<Entry>> {
+        let entry: Option<entities::Entry> = sqlx::query_as(
+            "SELECT
+              entries.retrieved,
+              entries.entry_id,
+              entries.title,
+              entries.description,
+              entries.content,
+              entries.url,
+              entries.author,
+              entries.published,
+              entries.enclosure_url,
+              entries.enclosure_type,
+              entries.enclosure_length,
+              entries.image_url,
+              entries.comments_url,
+              entries.categories,
+              entries.guid,
+              entries.guid_is_permalink,
+              entries.extra
+            FROM entries
+              JOIN feeds ON (feeds.id = entries.feed_id)
+            WHERE feeds.name = ?1 AND entries.entry_id = ?2",
+        )
+        .bind(feed_name)
+        .bind(entry_id)
+        .fetch_optional(self.0.as_mut())
+        .await
+        .context("could not retrieve the entry")?;
+
+        Ok(entry.and_then(|entry| convert_entry(feed_name, entry)))
+    }
+
+    /// Records the URLs of images of the feed that can be served through the image proxy.
+    #[instrument(level = "TRACE", skip(self, urls), fields(url_count = urls.len()))]
+    pub async fn register_images(&mut self, feed_name: &str, urls: &[Url]) -> Result<()> {
+        for url in urls {
+            sqlx::query(
+                "INSERT
+                INTO images (feed_id, hash, url)
+                SELECT id, ?2, ?3
+                FROM feeds
+                WHERE name = ?1
+                ON CONFLICT (feed_id, hash) DO NOTHING",
+            )
+            .bind(feed_name)
+            .bind(imageproxy::hash(url))
+            .bind(url.as_str())
+            .execute(self.0.as_mut())
+            .await
+            .context("could not register an image")?;
+        }
+
+        Ok(())
+    }
+
+    /// Removes the images of the feed that none of its stored entries refer to anymore (e.g.,
+    /// because an entry's description has changed).
+    #[instrument(level = "TRACE", skip(self))]
+    pub async fn prune_images(&mut self, feed_name: &str) -> Result<()> {
+        let result = sqlx::query(
+            "DELETE
+            FROM images
+            WHERE feed_id = (SELECT id FROM feeds WHERE name = ?1)
+              AND NOT EXISTS (
+                SELECT 1
+                FROM entries
+                WHERE entries.feed_id = images.feed_id
+                  AND (
+                    entries.image_url = images.url
+                    OR instr(entries.description || coalesce(entries.content, ''), images.url) > 0
+                    OR instr(
+                      entries.description || coalesce(entries.content, ''),
+                      replace(images.url, '&', '&amp;')
+                    ) > 0
+                  )
+              )",
+        )
+        .bind(feed_name)
+        .execute(self.0.as_mut())
+        .await
+        .context("could not remove unused images")?;
+
+        debug!(count = result.rows_affected(), "Removed unused images");
+
+        Ok(())
+    }
+
+    #[instrument(level = "TRACE", skip(self))]
+    pub async fn get_image(&mut self, feed_name: &str, hash: &str) -> Result<Option<Image>> {
+        sqlx::query_as(
+            "SELECT images.url, images.content_type, images.data
+            FROM images
+              JOIN feeds ON (feeds.id = images.feed_id)
+            WHERE feeds.name = ?1 AND images.hash = ?2",
+        )
+        .bind(feed_name)
+        .bind(hash)
+        .fetch_optional(self.0.as_mut())
+        .await
+        .context("could not retrieve the image")
+    }
+
+    #[instrument(level = "TRACE", skip(self, data), fields(size = data.len()))]
+    pub async fn store_image_data(
+        &mut self,
+        feed_name: &str,
+        hash: &str,
+        content_type: &str,
+        data: &[u8],
+    ) -> Result<()> {
+        sqlx::query(
+            "UPDATE images
+            SET content_type = ?3, data = ?4, fetched = ?5
+            WHERE feed_id = (SELECT id FROM feeds WHERE name = ?1) AND hash = ?2",
+        )
+        .bind(feed_name)
+        .bind(hash)
+        .bind(content_type)
+        .bind(data)
+        .bind(OffsetDateTime::now_utc())
+        .execute(self.0.as_mut())
+        .await
+        .context("could not store the image")?;
+
+        Ok(())
+    }
+
+    /// Retrieves a value stored by the feed's extractor (see `feedgen.kv`).
+    #[instrument(level = "TRACE", skip(self))]
+    pub async fn get_kv(&mut self, feed_name: &str, key: &str) -> Result<Option<String>> {
+        sqlx::query_scalar(
+            "SELECT value
+            FROM kv
+            WHERE feed_name = ?1 AND key = ?2",
+        )
+        .bind(feed_name)
+        .bind(key)
+        .fetch_optional(self.0.as_mut())
+        .await
+        .context("could not retrieve the value")
+    }
+
+    /// Stores a value for the feed's extractor, or removes it if `value` is `None`.
+    #[instrument(level = "TRACE", skip(self, value))]
+    pub async fn set_kv(&mut self, feed_name: &str, key: &str, value: Option<&str>) -> Result<()> {
+        let Some(value) = value else {
+            sqlx::query(
+                "DELETE
+                FROM kv
+                WHERE feed_name = ?1 AND key = ?2",
+            )
+            .bind(feed_name)
+            .bind(key)
+            .execute(self.0.as_mut())
+            .await
+            .context("could not remove the value")?;
+
+            return Ok(());
+        };
+
+        sqlx::query(
+            "INSERT
+            INTO kv (feed_name, key, value)
+            VALUES (?1, ?2, ?3)
+            ON CONFLICT (feed_name, key) DO UPDATE SET value = excluded.value",
+        )
+        .bind(feed_name)
+        .bind(key)
+        .bind(value)
+        .execute(self.0.as_mut())
+        .await
+        .context("could not store the value")?;
+
+        Ok(())
+    }
+
+    /// Retrieves the feed definitions saved on the admin page, keyed by the feed names.
+    #[instrument(level = "TRACE", skip(self))]
+    pub async fn get_saved_feeds(&mut self) -> Result<Vec<(String, String)>> {
+        sqlx::query_as(
+            "SELECT name, definition
+            FROM saved_feeds
+            ORDER BY name ASC",
+        )
+        .fetch_all(self.0.as_mut())
+        .await
+        .context("could not retrieve the saved feed definitions")
+    }
+
+    /// Saves the definition of a feed, or removes it if `definition` is `None`.
+    #[instrument(level = "TRACE", skip(self, definition))]
+    pub async fn set_saved_feed(&mut self, name: &str, definition: Option<&str>) -> Result<()> {
+        let Some(definition) = definition else {
+            sqlx::query(
+                "DELETE
+                FROM saved_feeds
+                WHERE name = ?1",
+            )
+            .bind(name)
+            .execute(self.0.as_mut())
+            .await
+            .context("could not remove the feed definition")?;
+
+            return Ok(());
+        };
+
+        sqlx::query(
+            "INSERT
+            INTO saved_feeds (name, definition)
+            VALUES (?1, ?2)
+            ON CONFLICT (name) DO UPDATE SET definition = excluded.definition",
+        )
+        .bind(name)
+        .bind(definition)
+        .execute(self.0.as_mut())
+        .await
+        .context("could not save the feed definition")?;
+
+        Ok(())
+    }
+
+    /// Stores the feed metadata provided by the extractor (or clears it if there is none).
+    ///
+    /// The feed must already be stored (see [`store_entries`](Self::store_entries)).
+    #[instrument(level = "TRACE", skip(self, channel))]
+    pub async fn store_channel_info(
+        &mut self,
+        feed_name: &str,
+        channel: Option<&ChannelInfo>,
+    ) -> Result<()> {
+        let channel = channel.cloned().unwrap_or_default();
+
+        sqlx::query(
+            "UPDATE feeds
+            SET title = ?2, description = ?3, link = ?4
+            WHERE name = ?1",
+        )
+        .bind(feed_name)
+        .bind(channel.title)
+        .bind(channel.description)
+        .bind(channel.link.map(String::from))
+        .execute(self.0.as_mut())
+        .await
+        .context("could not store the feed metadata")?;
+
+        Ok(())
+    }
+
+    /// Retrieves the feed metadata provided by the extractor.
+    #[instrument(level = "TRACE", skip(self))]
+    pub async fn get_channel_info(&mut self, feed_name: &str) -> Result<ChannelInfo> {
+        let row: Option<(Option<String>, Option<String>, Option<String>)> = sqlx::query_as(
+            "SELECT title, description, link
+            FROM feeds
+            WHERE name = ?1",
+        )
+        .bind(feed_name)
+        .fetch_optional(self.0.as_mut())
+        .await
+        .context("could not retrieve the feed metadata")?;
+
+        let Some((title, description, link)) = row else {
+            return Ok(Default::default());
+        };
+
+        Ok(ChannelInfo {
+            title,
+            description,
+            link: link.and_then(|link| Url::parse(&link).ok()),
+        })
+    }
+
+    /// Keeps the source page of the feed's latest update, replacing the previous one.
+    ///
+    /// The feed must already be stored (see [`store_entries`](Self::store_entries)).
+    #[instrument(level = "TRACE", skip(self, response, body))]
+    pub async fn store_response(
+        &mut self,
+        feed_name: &str,
+        response: &Response,
+        body: &str,
+        body_hash: Option<&str>,
+        fetched: OffsetDateTime,
+    ) -> Result<()> {
+        let headers = serde_json::to_string(&response.headers)
+            .context("could not serialize the response headers")?;
+
+        sqlx::query(
+            "INSERT
+            INTO responses (feed_id, url, status, headers, body, body_hash, fetched)
+            SELECT id, ?2, ?3, ?4, ?5, ?6, ?7
+            FROM feeds
+            WHERE name = ?1
+            ON CONFLICT (feed_id) DO UPDATE SET
+              url = excluded.url,
+              status = excluded.status,
+              headers = excluded.headers,
+              body = excluded.body,
+              body_hash = excluded.body_hash,
+              fetched = excluded.fetched",
+        )
+        .bind(feed_name)
+        .bind(response.url.as_str())
+        .bind(i64::from(response.status))
+        .bind(headers)
+        .bind(body)
+        .bind(body_hash)
+        .bind(fetched)
+        .execute(self.0.as_mut())
+        .await
+        .context("could not store the response")?;
+
+        Ok(())
+    }
+
+    /// Retrieves the source page of the feed's latest update, along with the time it was fetched.
+    #[instrument(level = "TRACE", skip(self))]
+    pub async fn get_response(
+        &mut self,
+        feed_name: &str,
+    ) -> Result<Option<(Response, String, OffsetDateTime)>> {
+        let response: Option<ArchivedResponse> = sqlx::query_as(
+            "SELECT responses.url, status, headers, body, fetched
+            FROM responses
+              JOIN feeds ON (feeds.id = responses.feed_id)
+            WHERE feeds.name = ?1",
+        )
+        .bind(feed_name)
+        .fetch_optional(self.0.as_mut())
+        .await
+        .context("could not retrieve the response")?;
+
+        let Some(response) = response else {
+            return Ok(None);
+        };
+
+        let meta = Response {
+            status: response
+                .status
+                .try_into()
+                .context("the stored response status is malformed")?,
+            url: Url::parse(&response.url).context("the stored response URL is malformed")?,
+            headers: serde_json::from_str(&response.headers)
+                .context("the stored response headers are malformed")?,
+        };
+
+        Ok(Some((meta, response.body, response.fetched)))
+    }
+
+    /// Retrieves the hash of the source page stored by [`store_response`](Self::store_response).
+    #[instrument(level = "TRACE", skip(self))]
+    pub async fn get_response_body_hash(&mut self, feed_name: &str) -> Result<Option<String>> {
+        let body_hash: Option<Option<String>> = sqlx::query_scalar(
+            "SELECT body_hash
+            FROM responses
+              JOIN feeds ON (feeds.id = responses.feed_id)
+            WHERE feeds.name = ?1",
+        )
+        .bind(feed_name)
+        .fetch_optional(self.0.as_mut())
+        .await
+        .context("could not retrieve the hash of the response")?;
+
+        Ok(body_hash.flatten())
+    }
+
+    /// Records that the feed was updated without storing anything.
+    #[instrument(level = "TRACE", skip(self))]
+    pub async fn touch_feed(&mut self, feed_name: &str) -> Result<()> {
+        sqlx::query(
+            "UPDATE feeds
+            SET last_updated = ?2
+            WHERE name = ?1",
+        )
+        .bind(feed_name)
+        .bind(OffsetDateTime::now_utc())
+        .execute(self.0.as_mut())
+        .await
+        .context("could not update the feed")?;
+
         Ok(())
     }
 
@@ -190,8 +712,19 @@ impl Tx {
         Ok(result)
     }
 
+    /// Retrieves the entries of a feed, most recently retrieved first.
+    ///
+    /// If `since` is given, only the entries retrieved at or after that time are returned.
     #[instrument(level = "TRACE", skip(self))]
-    pub async fn get_feed_entries(&mut self, feed_name: &str, count: usize) -> Result<Vec<Entry>> {
+    pub async fn get_feed_entries(
+        &mut self,
+        feed_name: &str,
+        count: usize,
+        offset: usize,
+        since: Option<OffsetDateTime>,
+    ) -> Result<Vec<Entry>> {
+        // timestamps are stored as text, so they only compare correctly in the same offset.
+        let since = since.map(|since| since.to_offset(UtcOffset::UTC));
         let feed_id: Option<i64> = sqlx::query_scalar(
             "SELECT id
             FROM feeds
@@ -211,45 +744,213 @@ impl Tx {
               entry_id,
               title,
               description,
+              content,
               url,
               author,
-              published
+              published,
+              enclosure_url,
+              enclosure_type,
+              enclosure_length,
+              image_url,
+              comments_url,
+              categories,
+              guid,
+              guid_is_permalink,
+              extra
             FROM entries
-            WHERE feed_id = ?1
+            WHERE feed_id = ?1 AND (?4 IS NULL OR retrieved >= ?4)
             ORDER BY retrieved DESC
-            LIMIT ?2",
+            LIMIT ?2
+            OFFSET ?3",
         )
         .bind(feed_id)
         .bind(count as i64)
+        .bind(offset as i64)
+        .bind(since)
         .fetch_all(self.0.as_mut())
         .await
         .context("could not retrieve feed entries")?;
 
-        let mut result = Vec::with_capacity(entries.len());
+        Ok(entries
+            .into_iter()
+            .filter_map(|entry| convert_entry(feed_name, entry))
+            .collect())
+    }
 
-        for entry in entries {
-            let url = match Url::parse(&entry.url) {
-                Ok(url) => url,
+    /// Retrieves the latest entries of several feeds along with the names of their feeds.
+    ///
+    /// If `since` is given, only the entries retrieved at or after that time are returned.
+    #[instrument(level = "TRACE", skip(self))]
+    pub async fn get_aggregate_entries(
+        &mut self,
+        feed_names: &[String],
+        count: usize,
+        offset: usize,
+        since: Option<OffsetDateTime>,
+    ) -> Result<Vec<(String, Entry)>> {
+        if feed_names.is_empty() {
+            return Ok(vec![]);
+        }
 
-                Err(e) => {
-                    error!(
-                        %feed_name, entry_id = %entry.entry_id,
-                        "The value of the column `url` is malformed: {e:#}",
-                    );
-                    continue;
-                }
-            };
+        // see `get_feed_entries`.
+        let since = since.map(|since| since.to_offset(UtcOffset::UTC));
+        let placeholders = vec!["?"; feed_names.len()].join(", ");
+        let sql = format!(
+            "SELECT
+              feeds.name AS feed_name,
+              retrieved,
+              entry_id,
+              title,
+              description,
+              content,
+              url,
+              author,
+              published,
+              enclosure_url,
+              enclosure_type,
+              enclosure_length,
+              image_url,
+              comments_url,
+              categories,
+              guid,
+              guid_is_permalink,
+              extra
+            FROM entries
+              JOIN feeds ON (feeds.id = entries.feed_id)
+            WHERE feeds.name IN ({placeholders}) AND (? IS NULL OR retrieved >= ?)
+            ORDER BY COALESCE(published, retrieved) DESC
+            LIMIT ?
+            OFFSET ?"
+        );
 
-            result.push(Entry {
-                id: entry.entry_id,
-                title: entry.title,
-                description: entry.description,
-                url,
-                author: entry.author,
-                pub_date: Some(entry.published.unwrap_or(entry.retrieved)),
-            });
+        let mut query = sqlx::query_as::<_, entities::AggregateEntry>(&sql);
+
+        for feed_name in feed_names {
+            query = query.bind(feed_name);
         }
 
-        Ok(result)
+        let entries = query
+            .bind(since)
+            .bind(since)
+            .bind(count as i64)
+            .bind(offset as i64)
+            .fetch_all(self.0.as_mut())
+            .await
+            .context("could not retrieve aggregate entries")?;
+
+        Ok(entries
+            .into_iter()
+            .filter_map(|entry| {
+                let feed_name = entry.feed_name;
+
+                convert_entry(&feed_name, entry.entry).map(|entry| (feed_name, entry))
+            })
+            .collect())
     }
 }
+
+fn convert_entry(feed_name: &str, entry: entities::Entry) -> Option<Entry> {
+    let url = match Url::parse(&entry.url) {
+        Ok(url) => url,
+
+        Err(e) => {
+            error!(
+                %feed_name, entry_id = %entry.entry_id,
+                "The value of the column `url` is malformed: {e:#}",
+            );
+
+            return None;
+        }
+    };
+
+    let enclosure = match (entry.enclosure_url, entry.enclosure_type) {
+        (Some(enclosure_url), Some(mime_type)) => match Url::parse(&enclosure_url) {
+            Ok(url) => Some(Enclosure {
+                url,
+                mime_type,
+                length: entry.enclosure_length.map(|length| length as u64),
+            }),
+
+            Err(e) => {
+                error!(
+                    %feed_name, entry_id = %entry.entry_id,
+                    "The value of the column `enclosure_url` is malformed: {e:#}",
+                );
+
+                None
+            }
+        },
+
+        _ => None,
+    };
+
+    let image = entry.image_url.and_then(|image_url| {
+        Url::parse(&image_url)
+            .inspect_err(|e| {
+                error!(
+                    %feed_name, entry_id = %entry.entry_id,
+                    "The value of the column `image_url` is malformed: {e:#}",
+                );
+            })
+            .ok()
+    });
+
+    let comments = entry.comments_url.and_then(|comments_url| {
+        Url::parse(&comments_url)
+            .inspect_err(|e| {
+                error!(
+                    %feed_name, entry_id = %entry.entry_id,
+                    "The value of the column `comments_url` is malformed: {e:#}",
+                );
+            })
+            .ok()
+    });
+
+    let categories = match entry
+        .categories
+        .as_deref()
+        .map(serde_json::from_str)
+        .transpose()
+    {
+        Ok(categories) => categories.unwrap_or_default(),
+
+        Err(e) => {
+            error!(
+                %feed_name, entry_id = %entry.entry_id,
+                "The value of the column `categories` is malformed: {e:#}",
+            );
+
+            Default::default()
+        }
+    };
+
+    let extra = match entry.extra.as_deref().map(serde_json::from_str).transpose() {
+        Ok(extra) => extra.unwrap_or_default(),
+
+        Err(e) => {
+            error!(
+                %feed_name, entry_id = %entry.entry_id,
+                "The value of the column `extra` is malformed: {e:#}",
+            );
+
+            Default::default()
+        }
+    };
+
+    Some(Entry {
+        id: entry.entry_id,
+        title: entry.title,
+        description: entry.description,
+        content: entry.content,
+        url,
+        author: entry.author,
+        pub_date: Some(entry.published.unwrap_or(entry.retrieved)),
+        enclosure,
+        image,
+        comments,
+        categories,
+        guid: entry.guid,
+        guid_is_permalink: entry.guid_is_permalink,
+        extra,
+    })
+}
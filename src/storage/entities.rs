@@ -14,9 +14,46 @@ pub struct Entry {
     pub entry_id: String,
     pub title: String,
     pub description: String,
+    pub content: Option<String>,
     pub url: String,
     pub author: Option<String>,
     pub published: Option<OffsetDateTime>,
+    pub enclosure_url: Option<String>,
+    pub enclosure_type: Option<String>,
+    pub enclosure_length: Option<i64>,
+    pub image_url: Option<String>,
+    pub comments_url: Option<String>,
+    pub categories: Option<String>,
+    pub guid: Option<String>,
+    pub guid_is_permalink: bool,
+    pub extra: Option<String>,
+}
+
+#[derive(FromRow, Debug, Clone)]
+pub struct AggregateEntry {
+    pub feed_name: String,
+
+    #[sqlx(flatten)]
+    pub entry: Entry,
+}
+
+#[derive(FromRow, Debug, Clone)]
+pub struct Image {
+    pub url: String,
+    pub content_type: Option<String>,
+    pub data: Option<Vec<u8>>,
+}
+
+#[derive(FromRow, Debug, Clone)]
+pub struct ArchivedResponse {
+    pub url: String,
+    pub status: i64,
+
+    /// The JSON-encoded header map.
+    pub headers: String,
+
+    pub body: String,
+    pub fetched: OffsetDateTime,
 }
 
 #[derive(Debug, Clone)]
@@ -0,0 +1,125 @@
+//! Integration with the systemd service manager: socket activation and readiness/watchdog
+//! notifications. Everything here is a no-op if Feedgen is not started by systemd.
+
+use std::time::Duration;
+
+use anyhow::Result;
+
+#[cfg(target_os = "linux")]
+mod imp {
+    use std::env;
+    use std::ffi::OsStr;
+    use std::net::TcpListener;
+    use std::os::fd::{FromRawFd, RawFd};
+    use std::os::linux::net::SocketAddrExt;
+    use std::os::unix::ffi::OsStrExt;
+    use std::os::unix::net::{SocketAddr, UnixDatagram};
+    use std::process;
+    use std::time::Duration;
+
+    use anyhow::{anyhow, Context, Result};
+
+    /// The first file descriptor passed by socket activation.
+    const LISTEN_FDS_START: RawFd = 3;
+
+    /// Checks that an environment variable set by systemd is meant for this process.
+    fn is_for_this_process(pid_var: &str) -> bool {
+        env::var(pid_var)
+            .ok()
+            .and_then(|pid| pid.parse::<u32>().ok())
+            .is_some_and(|pid| pid == process::id())
+    }
+
+    pub fn take_listeners() -> Result<Vec<TcpListener>> {
+        if !is_for_this_process("LISTEN_PID") {
+            return Ok(vec![]);
+        }
+
+        let count = env::var("LISTEN_FDS")
+            .context("`LISTEN_PID` is set, but `LISTEN_FDS` is not")?
+            .parse::<RawFd>()
+            .context("could not parse `LISTEN_FDS`")?;
+
+        (LISTEN_FDS_START..LISTEN_FDS_START + count)
+            .map(|fd| {
+                // SAFETY: systemd passes the sockets as open file descriptors starting from 3, and
+                // nothing else in the process uses them.
+                let listener = unsafe { TcpListener::from_raw_fd(fd) };
+                listener
+                    .set_nonblocking(true)
+                    .with_context(|| anyhow!("could not use the socket passed as fd {fd}"))?;
+
+                Ok(listener)
+            })
+            .collect()
+    }
+
+    pub fn notify(state: &str) -> Result<()> {
+        let Some(path) = env::var_os("NOTIFY_SOCKET") else {
+            return Ok(());
+        };
+
+        let socket = UnixDatagram::unbound().context("could not create a notification socket")?;
+        let path = path.as_bytes();
+
+        // a leading `@` denotes a socket in the abstract namespace.
+        let addr = match path.strip_prefix(b"@") {
+            Some(name) => SocketAddr::from_abstract_name(name),
+            None => SocketAddr::from_pathname(OsStr::from_bytes(path)),
+        }
+        .context("`NOTIFY_SOCKET` is malformed")?;
+
+        socket
+            .send_to_addr(state.as_bytes(), &addr)
+            .context("could not send a notification to the service manager")?;
+
+        Ok(())
+    }
+
+    pub fn watchdog_interval() -> Option<Duration> {
+        if env::var_os("WATCHDOG_PID").is_some() && !is_for_this_process("WATCHDOG_PID") {
+            return None;
+        }
+
+        env::var("WATCHDOG_USEC")
+            .ok()
+            .and_then(|usec| usec.parse::<u64>().ok())
+            .filter(|&usec| usec > 0)
+            .map(Duration::from_micros)
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+mod imp {
+    use std::net::TcpListener;
+    use std::time::Duration;
+
+    use anyhow::Result;
+
+    pub fn take_listeners() -> Result<Vec<TcpListener>> {
+        Ok(vec![])
+    }
+
+    pub fn notify(_state: &str) -> Result<()> {
+        Ok(())
+    }
+
+    pub fn watchdog_interval() -> Option<Duration> {
+        None
+    }
+}
+
+/// Takes the listening sockets passed by systemd socket activation.
+pub fn take_listeners() -> Result<Vec<std::net::TcpListener>> {
+    imp::take_listeners()
+}
+
+/// Sends a state update (e.g., `READY=1`) to the service manager.
+pub fn notify(state: &str) -> Result<()> {
+    imp::notify(state)
+}
+
+/// Returns how often the service manager expects a `WATCHDOG=1` notification, if at all.
+pub fn watchdog_interval() -> Option<Duration> {
+    imp::watchdog_interval()
+}
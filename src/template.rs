@@ -5,12 +5,16 @@ use handlebars::Handlebars;
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Template {
     Index,
+    Opml,
+    Admin,
 }
 
 impl Template {
     pub fn as_str(&self) -> &'static str {
         match self {
             Self::Index => "index",
+            Self::Opml => "opml",
+            Self::Admin => "admin",
         }
     }
 }
@@ -23,11 +27,12 @@ impl Display for Template {
 
 pub fn new() -> Handlebars<'static> {
     let mut tt = Handlebars::new();
-    tt.register_template_string(
-        Template::Index.as_str(),
-        include_str!("template/index.hbs"),
-    )
-    .unwrap();
+    tt.register_template_string(Template::Index.as_str(), include_str!("template/index.hbs"))
+        .unwrap();
+    tt.register_template_string(Template::Opml.as_str(), include_str!("template/opml.hbs"))
+        .unwrap();
+    tt.register_template_string(Template::Admin.as_str(), include_str!("template/admin.hbs"))
+        .unwrap();
 
     tt
 }
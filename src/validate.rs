@@ -0,0 +1,100 @@
+use std::collections::HashSet;
+use std::fmt::{self, Display};
+
+use reqwest::Url;
+
+use crate::extractor::Entry;
+
+/// A problem with an extracted entry that may cause feed readers to drop or misplace it.
+#[derive(Debug, Clone)]
+pub struct Problem {
+    /// The index of the entry in the extracted list.
+    pub index: usize,
+
+    pub entry_id: String,
+    pub message: String,
+
+    /// Whether the entry is unusable. Entries with other problems are still served: a missing
+    /// publication date, for one, is replaced with the retrieval time.
+    pub fatal: bool,
+}
+
+impl Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "the entry `{}` {}", self.entry_id, self.message)
+    }
+}
+
+fn check_url(url: &Url, field: &str) -> Option<String> {
+    if !matches!(url.scheme(), "http" | "https") {
+        Some(format!(
+            "has an invalid {field} `{url}`: the scheme must be `http` or `https`"
+        ))
+    } else if url.host().is_none() {
+        Some(format!(
+            "has an invalid {field} `{url}`: the host is missing"
+        ))
+    } else {
+        None
+    }
+}
+
+/// Checks the entries for missing publication dates, invalid URLs, and duplicate ids (which
+/// become duplicate GUIDs in the feed).
+pub fn check(entries: &[Entry]) -> Vec<Problem> {
+    let mut problems = vec![];
+    let mut seen_ids = HashSet::new();
+
+    for (index, entry) in entries.iter().enumerate() {
+        let mut report = |message: String, fatal: bool| {
+            problems.push(Problem {
+                index,
+                entry_id: entry.id.clone(),
+                message,
+                fatal,
+            })
+        };
+
+        if !seen_ids.insert(&entry.id) {
+            report("has the same id as a previous entry".into(), true);
+        }
+
+        if entry.pub_date.is_none() {
+            report("has no publication date".into(), false);
+        }
+
+        let urls = [
+            Some((&entry.url, "URL")),
+            entry.image.as_ref().map(|url| (url, "image URL")),
+            entry.comments.as_ref().map(|url| (url, "comments URL")),
+            entry
+                .enclosure
+                .as_ref()
+                .map(|enclosure| (&enclosure.url, "enclosure URL")),
+        ];
+
+        for (url, field) in urls.into_iter().flatten() {
+            if let Some(message) = check_url(url, field) {
+                report(message, true);
+            }
+        }
+    }
+
+    problems
+}
+
+/// Removes the entries that have fatal problems.
+pub fn reject(entries: Vec<Entry>, problems: &[Problem]) -> Vec<Entry> {
+    let rejected = problems
+        .iter()
+        .filter(|problem| problem.fatal)
+        .map(|problem| problem.index)
+        .collect::<HashSet<_>>();
+
+    entries
+        .into_iter()
+        .enumerate()
+        .filter(|(index, _)| !rejected.contains(index))
+        .map(|(_, entry)| entry)
+        .collect()
+}
@@ -0,0 +1,185 @@
+//! `feedgen verify`: replays the source page of each feed's latest update through its extractor
+//! and compares the result with the stored entries.
+
+use anyhow::{bail, Context as _, Result};
+use reqwest_middleware::ClientWithMiddleware;
+
+use crate::cli::VerifyArgs;
+use crate::config::{self, Config};
+use crate::extractor::{Context as ExtractorContext, Entry, Extraction};
+use crate::fetch;
+use crate::postprocess::PostProcessor;
+use crate::state::{make_extractor, State};
+use crate::storage::Storage;
+
+/// The result of replaying a feed's source page.
+struct Report {
+    entry_count: usize,
+    skipped: usize,
+
+    /// The ids of the extracted entries that are not stored.
+    unstored: Vec<String>,
+
+    /// The ids of the stored entries whose fields differ, along with the names of those fields.
+    changed: Vec<(String, Vec<&'static str>)>,
+}
+
+fn changed_fields(stored: &Entry, extracted: &Entry) -> Vec<&'static str> {
+    [
+        ("title", stored.title == extracted.title),
+        ("description", stored.description == extracted.description),
+        ("url", stored.url == extracted.url),
+        ("author", stored.author == extracted.author),
+        ("pub-date", stored.pub_date == extracted.pub_date),
+        ("categories", stored.categories == extracted.categories),
+    ]
+    .into_iter()
+    .filter(|&(_, same)| !same)
+    .map(|(name, _)| name)
+    .collect()
+}
+
+/// Replays the feed's source page, returning `None` if none is stored.
+async fn verify_feed(
+    config: &Config,
+    storage: &Storage,
+    http_client: &ClientWithMiddleware,
+    name: &str,
+    feed: &config::Feed,
+) -> Result<Option<Report>> {
+    let mut tx = storage.begin().await?;
+    let page = tx.get_response(name).await?;
+    tx.commit().await?;
+
+    let Some((response, body, fetch_time)) = page else {
+        return Ok(None);
+    };
+
+    let mut extractor =
+        make_extractor(&feed.extractor, &config.lua).context("could not create the extractor")?;
+    let postprocessor = PostProcessor::from_cfg(feed);
+    response.check_content_type(feed.content_kind())?;
+
+    let request_url = feed.request_url.clone();
+    let timezone = feed.timezone;
+    let (http_client, _) = fetch::make_feed_http_client(http_client, config, feed)?;
+
+    // nothing is stored, so extractors that keep state see every page as their first one.
+    let extraction = tokio::task::spawn_blocking(move || -> Result<Extraction> {
+        let ctx = ExtractorContext::new(&request_url)
+            .with_response(Some(&response))
+            .with_fetch_time(fetch_time)
+            .with_timezone(timezone)
+            .with_http_client(&http_client, None);
+        let base_url = ctx.base_url();
+
+        let mut extraction = extractor
+            .extract(ctx, &body)
+            .context("could not extract feed entries")?;
+        postprocessor.process(base_url, &mut extraction.entries);
+
+        Ok(extraction)
+    })
+    .await
+    .context("running the extractor failed")??;
+
+    if extraction.entries.len() < feed.min_entries.max(1) {
+        bail!(
+            "extracted {} entries, fewer than the minimum of {}",
+            extraction.entries.len(),
+            feed.min_entries.max(1),
+        );
+    }
+
+    let mut report = Report {
+        entry_count: extraction.entries.len(),
+        skipped: extraction.skipped,
+        unstored: vec![],
+        changed: vec![],
+    };
+
+    let mut tx = storage.begin().await?;
+
+    for entry in &extraction.entries {
+        match tx.get_entry(name, &entry.id).await? {
+            Some(stored) => {
+                let fields = changed_fields(&stored, entry);
+
+                if !fields.is_empty() {
+                    report.changed.push((entry.id.clone(), fields));
+                }
+            }
+
+            None => report.unstored.push(entry.id.clone()),
+        }
+    }
+
+    tx.commit().await?;
+
+    Ok(Some(report))
+}
+
+/// Runs `feedgen verify`, failing if any feed could not be verified.
+pub async fn run(mut config: Config, args: VerifyArgs) -> Result<()> {
+    let storage = Storage::new(&config.db_path).await?;
+    State::apply_saved_feeds(&mut config, &storage).await?;
+
+    let mut names = if args.feeds.is_empty() {
+        config.feeds.keys().cloned().collect::<Vec<_>>()
+    } else {
+        for name in &args.feeds {
+            if !config.feeds.contains_key(name) {
+                bail!("unknown feed `{name}`");
+            }
+        }
+
+        args.feeds
+    };
+    names.sort();
+
+    let http_client = fetch::make_http_client(&config)?;
+    let mut failed = 0;
+
+    for name in &names {
+        let feed = &config.feeds[name];
+
+        match verify_feed(&config, &storage, &http_client, name, feed).await {
+            Ok(None) => println!("{name}: skipped (no source page stored yet)"),
+
+            Ok(Some(report)) => {
+                let status = if report.unstored.is_empty() && report.changed.is_empty() {
+                    "ok"
+                } else {
+                    "differs"
+                };
+
+                println!(
+                    "{name}: {status}, {} entries ({} skipped, {} not stored, {} changed)",
+                    report.entry_count,
+                    report.skipped,
+                    report.unstored.len(),
+                    report.changed.len(),
+                );
+
+                for id in &report.unstored {
+                    println!("  not stored: {id}");
+                }
+
+                for (id, fields) in &report.changed {
+                    println!("  changed: {id} ({})", fields.join(", "));
+                }
+            }
+
+            Err(e) => {
+                println!("{name}: FAILED: {e:#}");
+                failed += 1;
+            }
+        }
+    }
+
+    if failed > 0 {
+        bail!("{failed} of {} feeds failed verification", names.len());
+    }
+
+    Ok(())
+}
@@ -1,3 +1,6 @@
+#[cfg(feature = "xpath3")]
+pub mod xpath3;
+
 use std::cell::RefCell;
 use std::collections::HashMap;
 use std::fmt;
@@ -5,11 +8,11 @@ use std::fmt::Formatter;
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
 
-use anyhow::{Context as _, Result};
+use anyhow::{bail, Context as _, Result};
 use serde::de::Visitor;
-use serde::{Deserialize, Deserializer};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use sxd_xpath::nodeset::Node;
-use sxd_xpath::{Context, ExecutionError, Factory, Value};
+use sxd_xpath::{Context, Factory, Value};
 
 static NEXT_XPATH_ID: AtomicUsize = AtomicUsize::new(0);
 
@@ -21,6 +24,9 @@ thread_local! {
 struct XPathInner {
     id: usize,
     s: String,
+
+    /// Whether the expression is valid XPath 1.0 (and not only XPath 3.1).
+    xpath1: bool,
 }
 
 #[derive(Debug, Clone)]
@@ -28,18 +34,45 @@ pub struct XPath(Arc<XPathInner>);
 
 impl XPath {
     pub fn new(s: String) -> Result<Self> {
-        let xpath = Factory::new()
-            .build(&s)
-            .context("could not compile the XPath expression")?;
-
         let id = NEXT_XPATH_ID.fetch_add(1, Ordering::Relaxed);
+
+        let xpath = match Factory::new().build(&s) {
+            Ok(xpath) => xpath,
+
+            // the extractor checks that it's configured for XPath 3.1 expressions.
+            #[cfg(feature = "xpath3")]
+            Err(_) if xpath3::check(&s).is_ok() => {
+                return Ok(XPath(Arc::new(XPathInner {
+                    id,
+                    s,
+                    xpath1: false,
+                })));
+            }
+
+            Err(e) => return Err(e).context("could not compile the XPath expression"),
+        };
+
         XPATH_REGISTRY.with_borrow_mut(|registry| {
             registry.insert(id, xpath);
         });
 
-        Ok(XPath(Arc::new(XPathInner { id, s })))
+        Ok(XPath(Arc::new(XPathInner {
+            id,
+            s,
+            xpath1: true,
+        })))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0.s
     }
 
+    /// Whether the expression can be evaluated by the XPath 1.0 engine.
+    pub fn is_xpath1(&self) -> bool {
+        self.0.xpath1
+    }
+
+    /// Runs `f` with the compiled expression. Panics if the expression is not XPath 1.0.
     pub fn with<R>(&self, f: impl FnOnce(&sxd_xpath::XPath) -> R) -> R {
         XPATH_REGISTRY.with_borrow_mut(|registry| {
             f(registry
@@ -48,15 +81,15 @@ impl XPath {
         })
     }
 
-    pub fn evaluate<'d, N>(
-        &self,
-        context: &Context<'d>,
-        node: N,
-    ) -> Result<Value<'d>, ExecutionError>
+    pub fn evaluate<'d, N>(&self, context: &Context<'d>, node: N) -> Result<Value<'d>>
     where
         N: Into<Node<'d>>,
     {
-        self.with(|xpath| xpath.evaluate(context, node))
+        if !self.is_xpath1() {
+            bail!("the expression is only valid in XPath 3.1");
+        }
+
+        Ok(self.with(|xpath| xpath.evaluate(context, node))?)
     }
 }
 
@@ -92,3 +125,106 @@ impl<'de> Deserialize<'de> for XPath {
         deserializer.deserialize_string(XPathVisitor)
     }
 }
+
+impl Serialize for XPath {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+/// One or several XPath expressions, written in configs as a string or an array of strings.
+#[derive(Debug, Clone)]
+pub struct XPathList(Vec<XPath>);
+
+impl XPathList {
+    pub fn iter(&self) -> impl Iterator<Item = &XPath> {
+        self.0.iter()
+    }
+
+    /// Fails unless all the expressions are valid XPath 1.0.
+    pub fn check_xpath1(&self) -> Result<()> {
+        for xpath in self.iter() {
+            if !xpath.is_xpath1() {
+                bail!("`{}` is not a valid XPath 1.0 expression", xpath.as_str());
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl From<XPath> for XPathList {
+    fn from(xpath: XPath) -> Self {
+        Self(vec![xpath])
+    }
+}
+
+impl<'de> Deserialize<'de> for XPathList {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct XPathListVisitor;
+
+        impl<'de> Visitor<'de> for XPathListVisitor {
+            type Value = XPathList;
+
+            fn expecting(&self, formatter: &mut Formatter<'_>) -> fmt::Result {
+                write!(
+                    formatter,
+                    "an XPath expression or a non-empty array of XPath expressions"
+                )
+            }
+
+            fn visit_string<E>(self, v: String) -> Result<Self::Value, E>
+            where
+                E: serde::de::Error,
+            {
+                XPath::new(v)
+                    .map(XPathList::from)
+                    .map_err(|e| E::custom(format!("{e:#}")))
+            }
+
+            fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+            where
+                E: serde::de::Error,
+            {
+                self.visit_string(v.into())
+            }
+
+            fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+            where
+                A: serde::de::SeqAccess<'de>,
+            {
+                let mut xpaths = vec![];
+
+                while let Some(xpath) = seq.next_element()? {
+                    xpaths.push(xpath);
+                }
+
+                if xpaths.is_empty() {
+                    return Err(serde::de::Error::invalid_length(0, &self));
+                }
+
+                Ok(XPathList(xpaths))
+            }
+        }
+
+        deserializer.deserialize_any(XPathListVisitor)
+    }
+}
+
+impl Serialize for XPathList {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match &*self.0 {
+            [xpath] => xpath.serialize(serializer),
+            xpaths => xpaths.serialize(serializer),
+        }
+    }
+}
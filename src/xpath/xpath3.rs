@@ -0,0 +1,103 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+use anyhow::{anyhow, Context as _, Result};
+use xee_xpath::context::StaticContextBuilder;
+use xee_xpath::error::{Error, ErrorValue};
+use xee_xpath::query::SequenceQuery;
+use xee_xpath::{Documents, Queries, Query};
+
+pub use xee_xpath::Item;
+
+use super::XPath;
+
+const HTML_XMLNS_URI: &str = "http://www.w3.org/1999/xhtml";
+
+/// Adds the message to the error code (such as `XPST0081`), which is all the error displays.
+fn describe(code: &ErrorValue, e: impl std::fmt::Display) -> anyhow::Error {
+    match code.message() {
+        "" => anyhow!("{e}"),
+        message => anyhow!("{e}: {message}"),
+    }
+}
+
+fn describe_spanned(e: Error) -> anyhow::Error {
+    describe(&e.error, &e)
+}
+
+fn compile(s: &str) -> Result<SequenceQuery> {
+    let mut static_ctx = StaticContextBuilder::default();
+    static_ctx
+        .add_namespace("html", HTML_XMLNS_URI)
+        .default_element_namespace(HTML_XMLNS_URI);
+
+    Queries::new(static_ctx)
+        .sequence(s)
+        .map_err(describe_spanned)
+        .context("could not compile the XPath 3.1 expression")
+}
+
+/// Fails if `s` is not a valid XPath 3.1 expression.
+pub fn check(s: &str) -> Result<()> {
+    compile(s).map(|_| ())
+}
+
+/// An XML document XPath 3.1 expressions are evaluated on.
+pub struct Document {
+    documents: RefCell<Documents>,
+    root: Item,
+
+    /// The compiled expressions, keyed by their source.
+    queries: RefCell<HashMap<String, SequenceQuery>>,
+}
+
+impl Document {
+    pub fn new(xml: &str) -> Result<Self> {
+        let mut documents = Documents::new();
+        let handle = documents
+            .add_string_without_uri(xml)
+            .context("could not load the document")?;
+        let root = documents
+            .document_node(handle)
+            .map(Item::Node)
+            .context("the document has no root node")?;
+
+        Ok(Self {
+            documents: RefCell::new(documents),
+            root,
+            queries: Default::default(),
+        })
+    }
+
+    /// The document node.
+    pub fn root(&self) -> &Item {
+        &self.root
+    }
+
+    /// Evaluates the expression `s` with `item` as the context item.
+    pub fn evaluate_str(&self, s: &str, item: &Item) -> Result<Vec<Item>> {
+        let mut queries = self.queries.borrow_mut();
+
+        if !queries.contains_key(s) {
+            queries.insert(s.into(), compile(s)?);
+        }
+
+        let query = &queries[s];
+
+        let sequence = query
+            .execute(&mut self.documents.borrow_mut(), item)
+            .map_err(describe_spanned)?;
+
+        Ok(sequence.iter().collect())
+    }
+
+    pub fn evaluate(&self, xpath: &XPath, item: &Item) -> Result<Vec<Item>> {
+        self.evaluate_str(xpath.as_str(), item)
+    }
+
+    /// Returns the string value of a node or an atomic value (functions have none).
+    pub fn string_value(&self, item: &Item) -> Result<String> {
+        item.string_value(self.documents.borrow().xot())
+            .map_err(|e| describe(&e, &e))
+    }
+}